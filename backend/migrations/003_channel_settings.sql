@@ -0,0 +1,11 @@
+-- Per-channel settings editable through PATCH /api/guilds/:guild_id/channels/:channel_id
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS topic TEXT;
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS nsfw BOOLEAN DEFAULT FALSE;
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS slowmode_seconds INTEGER DEFAULT 0;
+
+-- Slowmode is capped at 6 hours
+ALTER TABLE channels DROP CONSTRAINT IF EXISTS channels_slowmode_range;
+ALTER TABLE channels ADD CONSTRAINT channels_slowmode_range CHECK (slowmode_seconds BETWEEN 0 AND 21600);
+
+-- Used by slowmode enforcement to find the author's latest message in a channel
+CREATE INDEX IF NOT EXISTS idx_messages_channel_author ON messages(channel, author_id, created_at DESC);
@@ -0,0 +1,17 @@
+-- Guild templates: a snapshot of a guild's roles, categories, channels and
+-- permission overwrites that new guilds can be created from
+CREATE TABLE IF NOT EXISTS guild_templates (
+    id UUID PRIMARY KEY,
+    code VARCHAR(16) UNIQUE NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    source_guild_id UUID REFERENCES guilds(id) ON DELETE SET NULL,
+    creator_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    serialized_guild JSONB NOT NULL,
+    usage_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_guild_templates_code ON guild_templates(code);
+CREATE INDEX IF NOT EXISTS idx_guild_templates_source ON guild_templates(source_guild_id);
@@ -0,0 +1,23 @@
+-- AutoMod: per-guild rules evaluated against messages as they are sent or edited
+CREATE TABLE IF NOT EXISTS automod_rules (
+    id UUID PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    trigger_type VARCHAR(32) NOT NULL,
+    trigger_metadata JSONB NOT NULL DEFAULT '{}',
+    actions JSONB NOT NULL DEFAULT '[]',
+    exempt_roles UUID[] NOT NULL DEFAULT '{}',
+    exempt_channels UUID[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    creator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    CONSTRAINT automod_rules_trigger_type CHECK (
+        trigger_type IN ('keyword', 'mention_spam', 'repeated_message', 'invite_link', 'domain_block')
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_automod_rules_guild ON automod_rules(guild_id) WHERE enabled;
+
+-- Timeouts applied by AutoMod (or moderators); members can't send messages until this passes
+ALTER TABLE guild_members ADD COLUMN IF NOT EXISTS timed_out_until TIMESTAMPTZ;
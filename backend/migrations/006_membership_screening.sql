@@ -0,0 +1,21 @@
+-- Verification levels: 0 = none, 1 = verified email, 2 = + account older than 5 minutes,
+-- 3 = + member of the guild for longer than 10 minutes
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS verification_level SMALLINT NOT NULL DEFAULT 0;
+
+ALTER TABLE guilds DROP CONSTRAINT IF EXISTS guilds_verification_level_range;
+ALTER TABLE guilds ADD CONSTRAINT guilds_verification_level_range CHECK (verification_level BETWEEN 0 AND 3);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Rules screen new members must accept before they can participate
+CREATE TABLE IF NOT EXISTS guild_member_screening (
+    guild_id UUID PRIMARY KEY REFERENCES guilds(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    description TEXT,
+    rules TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Members who joined while screening was enabled and haven't accepted the rules yet
+ALTER TABLE guild_members ADD COLUMN IF NOT EXISTS pending BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE guild_members ADD COLUMN IF NOT EXISTS rules_accepted_at TIMESTAMPTZ;
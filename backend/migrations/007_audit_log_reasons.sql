@@ -0,0 +1,10 @@
+-- Optional moderator-supplied reason (X-Audit-Log-Reason header)
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS reason TEXT;
+
+-- Filtering and cursor pagination in GET /api/guilds/:guild_id/audit-logs
+CREATE INDEX IF NOT EXISTS idx_audit_logs_guild_created ON audit_logs(guild_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_guild_user ON audit_logs(guild_id, user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_guild_target ON audit_logs(guild_id, target_id);
+
+-- Retention sweep
+CREATE INDEX IF NOT EXISTS idx_audit_logs_created ON audit_logs(created_at);
@@ -0,0 +1,27 @@
+-- Message types: 'default' for user messages, anything else is generated by the server
+-- (member_join, member_leave, guild_boost, channel_pin, automod_alert)
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS message_type VARCHAR(32) NOT NULL DEFAULT 'default';
+-- The message a system message refers to (e.g. the message that was pinned)
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS reference_message_id UUID REFERENCES messages(id) ON DELETE SET NULL;
+
+-- Pins
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS pinned_at TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS pinned_by UUID REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_messages_channel_pinned ON messages(channel, pinned_at DESC) WHERE pinned_at IS NOT NULL;
+
+-- System channel and which events get posted to it
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS system_channel_id UUID REFERENCES channels(id) ON DELETE SET NULL;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS join_notifications BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS leave_notifications BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS boost_notifications BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS pin_notifications BOOLEAN NOT NULL DEFAULT TRUE;
+-- Custom welcome text; {user} and {guild} are substituted
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS welcome_message TEXT;
+
+-- Premium members boosting a guild (one boost per member per guild)
+CREATE TABLE IF NOT EXISTS guild_boosts (
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (guild_id, user_id)
+);
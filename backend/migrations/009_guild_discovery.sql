@@ -0,0 +1,12 @@
+-- Discovery metadata for public guilds
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS discovery_category VARCHAR(32);
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+-- Bumped (at most every few minutes) when messages are sent; used to rank by recent activity
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS last_activity_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_guilds_public_category ON guilds(discovery_category) WHERE is_public = TRUE;
+CREATE INDEX IF NOT EXISTS idx_guilds_public_tags ON guilds USING GIN (tags) WHERE is_public = TRUE;
+CREATE INDEX IF NOT EXISTS idx_guilds_public_activity ON guilds(last_activity_at DESC) WHERE is_public = TRUE;
+
+-- Channels of a public guild that non-members may browse before joining
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS public_preview BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,42 @@
+-- Scheduled guild events (weekly calls, game nights, ...)
+CREATE TABLE IF NOT EXISTS guild_scheduled_events (
+    id UUID PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    creator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    title VARCHAR(100) NOT NULL,
+    description TEXT,
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ,
+    -- 'voice' events happen in channel_id, 'external' events at a free-form location
+    entity_type VARCHAR(16) NOT NULL,
+    channel_id UUID REFERENCES channels(id) ON DELETE SET NULL,
+    location VARCHAR(100),
+    recurrence VARCHAR(16) NOT NULL DEFAULT 'none',
+    status VARCHAR(16) NOT NULL DEFAULT 'scheduled',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    CONSTRAINT guild_scheduled_events_entity_type CHECK (entity_type IN ('voice', 'external')),
+    CONSTRAINT guild_scheduled_events_recurrence CHECK (
+        recurrence IN ('none', 'daily', 'weekly', 'biweekly', 'monthly')
+    ),
+    CONSTRAINT guild_scheduled_events_status CHECK (
+        status IN ('scheduled', 'active', 'completed', 'canceled')
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_events_guild ON guild_scheduled_events(guild_id, start_time);
+-- Used by the scheduler to find events that are due for a status transition
+CREATE INDEX IF NOT EXISTS idx_scheduled_events_pending ON guild_scheduled_events(start_time)
+    WHERE status IN ('scheduled', 'active');
+
+-- RSVPs: members are either 'going' or just 'interested'
+CREATE TABLE IF NOT EXISTS guild_scheduled_event_rsvps (
+    event_id UUID NOT NULL REFERENCES guild_scheduled_events(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'interested',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (event_id, user_id),
+    CONSTRAINT guild_scheduled_event_rsvps_status CHECK (status IN ('going', 'interested'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_event_rsvps_user ON guild_scheduled_event_rsvps(user_id);
@@ -0,0 +1,40 @@
+-- Onboarding: groups of roles members can pick for themselves
+CREATE TABLE IF NOT EXISTS self_role_groups (
+    id UUID PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    -- FALSE means members may hold at most one role of the group
+    multiple_choice BOOLEAN NOT NULL DEFAULT TRUE,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_self_role_groups_guild ON self_role_groups(guild_id);
+
+CREATE TABLE IF NOT EXISTS self_assignable_roles (
+    role_id UUID PRIMARY KEY REFERENCES roles(id) ON DELETE CASCADE,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    group_id UUID REFERENCES self_role_groups(id) ON DELETE SET NULL,
+    description VARCHAR(200),
+    emoji VARCHAR(100),
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_self_assignable_roles_guild ON self_assignable_roles(guild_id);
+CREATE INDEX IF NOT EXISTS idx_self_assignable_roles_group ON self_assignable_roles(group_id);
+
+-- Reaction roles: reacting with `emoji` on `message_id` grants `role_id`, un-reacting removes it
+CREATE TABLE IF NOT EXISTS reaction_roles (
+    id UUID PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    emoji VARCHAR(100) NOT NULL,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    creator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (message_id, emoji)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reaction_roles_guild ON reaction_roles(guild_id);
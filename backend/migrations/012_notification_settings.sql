@@ -0,0 +1,27 @@
+-- Per-user notification settings for a guild, a channel or a DM.
+-- A NULL level inherits (channel -> guild -> default); muted without muted_until mutes indefinitely.
+CREATE TABLE IF NOT EXISTS notification_settings (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    scope_type VARCHAR(16) NOT NULL,
+    scope_id UUID NOT NULL,
+    level VARCHAR(16),
+    muted BOOLEAN NOT NULL DEFAULT FALSE,
+    muted_until TIMESTAMPTZ,
+    suppress_everyone BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (user_id, scope_type, scope_id),
+    CONSTRAINT notification_settings_scope_type CHECK (scope_type IN ('guild', 'channel', 'dm')),
+    CONSTRAINT notification_settings_level CHECK (level IS NULL OR level IN ('all', 'mentions', 'nothing'))
+);
+
+-- Lookups while fanning out a message: everyone with a setting on this guild/channel
+CREATE INDEX IF NOT EXISTS idx_notification_settings_scope ON notification_settings(scope_type, scope_id);
+
+-- Unread mention counts per user per channel, reset when the channel is acknowledged
+CREATE TABLE IF NOT EXISTS channel_read_states (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    mention_count INTEGER NOT NULL DEFAULT 0,
+    last_read_at TIMESTAMPTZ,
+    PRIMARY KEY (user_id, channel_id)
+);
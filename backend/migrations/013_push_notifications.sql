@@ -0,0 +1,38 @@
+-- Devices that receive push notifications while the user has no open gateway session.
+-- 'webpush' subscriptions come from browsers (endpoint + p256dh/auth keys),
+-- 'http' subscriptions are device tokens handed to the generic HTTP push provider (wryft-mobile).
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(16) NOT NULL,
+    endpoint TEXT,
+    p256dh TEXT,
+    auth TEXT,
+    device_token TEXT,
+    platform VARCHAR(32),
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+    CONSTRAINT push_subscriptions_provider CHECK (provider IN ('webpush', 'http')),
+    CONSTRAINT push_subscriptions_target CHECK (
+        (provider = 'webpush' AND endpoint IS NOT NULL AND p256dh IS NOT NULL AND auth IS NOT NULL)
+        OR (provider = 'http' AND device_token IS NOT NULL)
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user ON push_subscriptions(user_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_push_subscriptions_endpoint ON push_subscriptions(endpoint) WHERE endpoint IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_push_subscriptions_device_token ON push_subscriptions(device_token) WHERE device_token IS NOT NULL;
+
+-- Outgoing notifications waiting for the push worker; one row per notification per user
+CREATE TABLE IF NOT EXISTS push_queue (
+    id BIGSERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_queue_due ON push_queue(next_attempt_at);
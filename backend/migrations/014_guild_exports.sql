@@ -0,0 +1,18 @@
+-- Guild export jobs. The archive itself lives in object storage under storage_key;
+-- the row tracks progress so clients can poll for it.
+CREATE TABLE IF NOT EXISTS guild_exports (
+    id UUID PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    requested_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    include_messages BOOLEAN NOT NULL DEFAULT FALSE,
+    format_version INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    storage_key TEXT,
+    size_bytes BIGINT,
+    error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    CONSTRAINT guild_exports_status CHECK (status IN ('pending', 'running', 'completed', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_guild_exports_guild ON guild_exports(guild_id, created_at DESC);
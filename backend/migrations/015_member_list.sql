@@ -0,0 +1,6 @@
+-- Per-guild nicknames, and indexes for paginated / prefix-searched member lists
+ALTER TABLE guild_members ADD COLUMN IF NOT EXISTS nickname VARCHAR(32);
+
+CREATE INDEX IF NOT EXISTS idx_guild_members_nickname_prefix ON guild_members (guild_id, LOWER(nickname) text_pattern_ops) WHERE nickname IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_users_username_prefix ON users (LOWER(username) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_role_members_guild_user ON role_members (guild_id, user_id);
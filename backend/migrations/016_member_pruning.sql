@@ -0,0 +1,23 @@
+-- Last time each member did something in the guild, used to prune dormant members.
+-- Existing members start from their latest message in the guild, or when they joined.
+ALTER TABLE guild_members ADD COLUMN IF NOT EXISTS last_active_at TIMESTAMPTZ;
+
+UPDATE guild_members gm
+SET last_active_at = COALESCE(
+    GREATEST(
+        gm.joined_at,
+        (
+            SELECT MAX(m.created_at)
+            FROM messages m
+            INNER JOIN channels c ON c.id::text = m.channel
+            WHERE c.guild_id = gm.guild_id AND m.author_id = gm.user_id
+        )
+    ),
+    NOW()
+)
+WHERE gm.last_active_at IS NULL;
+
+ALTER TABLE guild_members ALTER COLUMN last_active_at SET DEFAULT NOW();
+ALTER TABLE guild_members ALTER COLUMN last_active_at SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_guild_members_last_active ON guild_members (guild_id, last_active_at);
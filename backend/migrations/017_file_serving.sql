@@ -0,0 +1,15 @@
+-- Uploaded files, served through /api/files/:id. Older installs created this table by hand.
+CREATE TABLE IF NOT EXISTS file_uploads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    file_type VARCHAR(20) NOT NULL,
+    file_key TEXT NOT NULL,
+    file_url TEXT,
+    file_size INTEGER,
+    content_type VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    accessed_at TIMESTAMP
+);
+
+-- Name the file was uploaded with, sent back in Content-Disposition
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS original_filename VARCHAR(255);
@@ -0,0 +1,23 @@
+-- Where an attachment was posted. Downloads are authorized against the channel or DM;
+-- attachments that were never posted are only visible to the uploader.
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS message_id UUID;
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS channel_id UUID REFERENCES channels(id) ON DELETE SET NULL;
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS dm_id UUID REFERENCES direct_messages(id) ON DELETE SET NULL;
+
+-- Link attachments that were posted before this existed
+UPDATE file_uploads f
+SET message_id = ma.message_id, channel_id = c.id
+FROM message_attachments ma
+INNER JOIN messages m ON m.id = ma.message_id
+INNER JOIN channels c ON c.id::text = m.channel
+WHERE f.file_type = 'attachment'
+  AND f.message_id IS NULL
+  AND ma.file_url = '/api/files/' || f.id::text;
+
+UPDATE file_uploads f
+SET message_id = dma.message_id, dm_id = dm.dm_id
+FROM dm_message_attachments dma
+INNER JOIN dm_messages dm ON dm.id = dma.message_id
+WHERE f.file_type = 'attachment'
+  AND f.message_id IS NULL
+  AND dma.file_url = '/api/files/' || f.id::text;
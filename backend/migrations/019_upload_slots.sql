@@ -0,0 +1,16 @@
+-- Presigned direct uploads waiting for the client to confirm them. Slots that expire
+-- unconfirmed are swept together with whatever was uploaded to them.
+CREATE TABLE IF NOT EXISTS upload_slots (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    file_type VARCHAR(20) NOT NULL,
+    file_key TEXT NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    file_size BIGINT NOT NULL,
+    original_filename VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_slots_user ON upload_slots(user_id);
+CREATE INDEX IF NOT EXISTS idx_upload_slots_expires ON upload_slots(expires_at);
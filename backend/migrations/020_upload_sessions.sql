@@ -0,0 +1,21 @@
+-- Resumable uploads. Chunks are stored as parts of a storage multipart upload; the
+-- session tracks how far the client got so it can continue after a dropped connection.
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    file_type VARCHAR(20) NOT NULL,
+    file_key TEXT NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    file_size BIGINT NOT NULL,
+    original_filename VARCHAR(255) NOT NULL,
+    chunk_size INTEGER NOT NULL,
+    storage_upload_id TEXT NOT NULL,
+    upload_offset BIGINT NOT NULL DEFAULT 0,
+    part_etags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    -- Pushed back with every chunk; abandoned sessions are swept once it passes
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_user ON upload_sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_expires ON upload_sessions(expires_at);
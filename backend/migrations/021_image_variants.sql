@@ -0,0 +1,17 @@
+-- Dimensions of uploaded images, as stored after processing
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS height INTEGER;
+
+-- Smaller copies of processed images, picked with ?size= when serving. For animated
+-- images these are stills of the first frame.
+CREATE TABLE IF NOT EXISTS file_variants (
+    file_id UUID NOT NULL REFERENCES file_uploads(id) ON DELETE CASCADE,
+    -- Longest edge in pixels
+    size INTEGER NOT NULL,
+    file_key TEXT NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    file_size INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    PRIMARY KEY (file_id, size)
+);
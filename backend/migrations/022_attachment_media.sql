@@ -0,0 +1,18 @@
+-- What clients need to lay out an attachment before downloading it: duration of audio and
+-- video, and a blurhash placeholder for images. Thumbnails are stored as file_variants.
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS duration_secs DOUBLE PRECISION;
+ALTER TABLE file_uploads ADD COLUMN IF NOT EXISTS placeholder TEXT;
+
+-- Copied onto attachments when they are posted, so listing messages needs no joins
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS height INTEGER;
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS duration_secs DOUBLE PRECISION;
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS placeholder TEXT;
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS thumbnail_url TEXT;
+
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS height INTEGER;
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS duration_secs DOUBLE PRECISION;
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS placeholder TEXT;
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS thumbnail_url TEXT;
+
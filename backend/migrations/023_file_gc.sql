@@ -0,0 +1,30 @@
+-- Which upload an attachment is, so finding unreferenced uploads doesn't have to match URLs
+ALTER TABLE message_attachments ADD COLUMN IF NOT EXISTS file_id UUID;
+ALTER TABLE dm_message_attachments ADD COLUMN IF NOT EXISTS file_id UUID;
+
+UPDATE message_attachments
+SET file_id = substring(file_url from '/api/files/([0-9a-fA-F-]{36})')::uuid
+WHERE file_id IS NULL AND file_url ~ '/api/files/[0-9a-fA-F-]{36}';
+
+UPDATE dm_message_attachments
+SET file_id = substring(file_url from '/api/files/([0-9a-fA-F-]{36})')::uuid
+WHERE file_id IS NULL AND file_url ~ '/api/files/[0-9a-fA-F-]{36}';
+
+CREATE INDEX IF NOT EXISTS idx_message_attachments_file_id ON message_attachments(file_id);
+CREATE INDEX IF NOT EXISTS idx_dm_message_attachments_file_id ON dm_message_attachments(file_id);
+
+-- Stored objects by content, so identical uploads share one object. ref_count is the
+-- number of uploads and variants using it; the object is deleted when it drops to zero.
+-- Objects stored before this existed have no row and belong to a single upload.
+CREATE TABLE IF NOT EXISTS stored_objects (
+    file_key TEXT PRIMARY KEY,
+    -- SHA-256, hex
+    content_hash TEXT NOT NULL UNIQUE,
+    file_size BIGINT NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 1,
+    last_acquired_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_uploads_file_key ON file_uploads(file_key);
+CREATE INDEX IF NOT EXISTS idx_file_variants_file_key ON file_variants(file_key);
+CREATE INDEX IF NOT EXISTS idx_file_uploads_type_created ON file_uploads(file_type, created_at);
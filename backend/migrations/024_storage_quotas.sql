@@ -0,0 +1,2 @@
+-- Storage used per user is the sum of their uploads, counted on every upload
+CREATE INDEX IF NOT EXISTS idx_file_uploads_user ON file_uploads(user_id);
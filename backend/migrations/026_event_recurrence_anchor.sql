@@ -0,0 +1,5 @@
+-- Start of the first occurrence of a recurring event. Later occurrences are counted from it,
+-- so a monthly event on the 31st is back on the 31st after a shorter month.
+ALTER TABLE guild_scheduled_events ADD COLUMN IF NOT EXISTS recurrence_anchor TIMESTAMPTZ;
+UPDATE guild_scheduled_events SET recurrence_anchor = start_time WHERE recurrence_anchor IS NULL;
+ALTER TABLE guild_scheduled_events ALTER COLUMN recurrence_anchor SET NOT NULL;
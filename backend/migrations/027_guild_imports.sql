@@ -0,0 +1,15 @@
+-- Guild import jobs. guild_id is set as soon as the guild is created, while its messages
+-- are still being imported, so an interrupted import can be removed again.
+CREATE TABLE IF NOT EXISTS guild_imports (
+    id UUID PRIMARY KEY,
+    requested_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    guild_id UUID REFERENCES guilds(id) ON DELETE SET NULL,
+    messages_imported INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    CONSTRAINT guild_imports_status CHECK (status IN ('pending', 'running', 'completed', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_guild_imports_requested_by ON guild_imports(requested_by, created_at DESC);
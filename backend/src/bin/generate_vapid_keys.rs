@@ -0,0 +1,12 @@
+// Generate a VAPID key pair for web push. Put the private key in VAPID_PRIVATE_KEY;
+// clients fetch the public key from /api/push/vapid-public-key.
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use p256::ecdsa::SigningKey;
+
+fn main() {
+    let signing_key = SigningKey::random(&mut rand::rngs::OsRng);
+    let public_key = signing_key.verifying_key().to_encoded_point(false);
+
+    println!("VAPID_PRIVATE_KEY={}", URL_SAFE_NO_PAD.encode(signing_key.to_bytes()));
+    println!("VAPID public key: {}", URL_SAFE_NO_PAD.encode(public_key.as_bytes()));
+}
@@ -0,0 +1,41 @@
+// Local stand-in for the HTTP push relay when testing notifications without real devices.
+// Point PUSH_HTTP_PROVIDER_URL at it; every request is logged and answered with
+// MOCK_PUSH_STATUS (default 201), so retries (500) and expired subscriptions (410) can be
+// exercised too. Web push subscriptions can't use it, as their endpoints must be public
+// HTTPS hosts.
+use axum::{body::Bytes, extract::State, http::{HeaderMap, StatusCode}, Router};
+
+async fn receive(State(status): State<StatusCode>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    println!("📨 Push received ({} bytes)", body.len());
+    for name in ["authorization", "content-encoding", "ttl", "urgency"] {
+        if let Some(value) = headers.get(name).and_then(|v| v.to_str().ok()) {
+            println!("   {}: {}", name, value);
+        }
+    }
+    if let Ok(text) = std::str::from_utf8(&body) {
+        println!("   {}", text);
+    }
+
+    status
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+
+    let port = std::env::var("MOCK_PUSH_PORT").unwrap_or_else(|_| "9099".to_string());
+    let status = std::env::var("MOCK_PUSH_STATUS")
+        .ok()
+        .and_then(|s| s.parse::<u16>().ok())
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .unwrap_or(StatusCode::CREATED);
+
+    let app = Router::new().fallback(receive).with_state(status);
+
+    let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+        .await
+        .expect("Failed to bind mock push server");
+    println!("Mock push service listening on 127.0.0.1:{} (responding {})", port, status);
+
+    axum::serve(listener, app).await.expect("Mock push server failed");
+}
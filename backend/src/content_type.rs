@@ -0,0 +1,230 @@
+// Working out what an upload really is from its first bytes. Filenames and the types
+// clients declare are only used to tell apart formats that look the same inside (ZIP based
+// documents, plain text), never to turn content into something it isn't.
+
+// How much of a file detection looks at
+pub const SNIFF_BYTES: usize = 512;
+
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"II*\x00", "image/tiff"),
+    (b"MM\x00*", "image/tiff"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"\xfd7zXZ\x00", "application/x-xz"),
+    (b"ID3", "audio/mpeg"),
+    (b"fLaC", "audio/flac"),
+    (b"OggS", "audio/ogg"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"\x00asm", "application/wasm"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"MZ", "application/x-msdownload"),
+    (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+    (b"\xca\xfe\xba\xbe", "application/x-mach-binary"),
+    (b"#!", "text/x-shellscript"),
+];
+
+// Tags that make browsers treat a document as HTML, as in the WHATWG sniffing rules
+const HTML_PREFIXES: &[&str] = &[
+    "<!doctype html", "<html", "<head", "<body", "<script", "<iframe", "<title", "<style",
+    "<table", "<font", "<div", "<img", "<a ", "<a>", "<b ", "<b>", "<br", "<p ", "<p>",
+    "<h1", "<!--",
+];
+
+// Types that run script or markup when a browser opens them, or that are programs. These
+// are always downloaded rather than displayed.
+const ACTIVE_CONTENT_TYPES: &[&str] = &[
+    "text/html",
+    "application/xhtml+xml",
+    "image/svg+xml",
+    "text/xml",
+    "application/xml",
+    "text/javascript",
+    "application/javascript",
+    "application/ecmascript",
+    "text/ecmascript",
+    "application/x-shockwave-flash",
+    "application/wasm",
+    "text/x-shellscript",
+    "application/x-executable",
+    "application/x-msdownload",
+    "application/x-mach-binary",
+    "application/java-archive",
+    "application/vnd.android.package-archive",
+];
+
+// The type of `data` going by its content alone, if it has a recognisable signature
+pub fn detect(data: &[u8]) -> Option<&'static str> {
+    if let Some(&(_, content_type)) = SIGNATURES.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(content_type);
+    }
+
+    if data.len() >= 12 && &data[..4] == b"RIFF" {
+        return match &data[8..12] {
+            b"WEBP" => Some("image/webp"),
+            b"WAVE" => Some("audio/wav"),
+            b"AVI " => Some("video/x-msvideo"),
+            _ => None,
+        };
+    }
+
+    // ISO base media files: MP4, QuickTime, M4A, AVIF, HEIC
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return Some(match &data[8..12] {
+            b"qt  " => "video/quicktime",
+            b"M4A " => "audio/mp4",
+            b"avif" | b"avis" => "image/avif",
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            _ => "video/mp4",
+        });
+    }
+
+    // BMP files have two reserved, zeroed words after the size
+    if data.len() >= 14 && data.starts_with(b"BM") && data[6..10] == [0, 0, 0, 0] {
+        return Some("image/bmp");
+    }
+
+    // MPEG audio frame sync without an ID3 tag
+    if data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 {
+        return Some("audio/mpeg");
+    }
+
+    detect_markup(data)
+}
+
+fn detect_markup(data: &[u8]) -> Option<&'static str> {
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let start = data.iter().position(|b| !b.is_ascii_whitespace())?;
+    let text = String::from_utf8_lossy(&data[start..]).to_lowercase();
+
+    if text.starts_with("<svg") {
+        return Some("image/svg+xml");
+    }
+    if text.starts_with("<?xml") {
+        return Some(if text.contains("<svg") { "image/svg+xml" } else { "application/xml" });
+    }
+    if HTML_PREFIXES.iter().any(|prefix| text.starts_with(prefix)) {
+        return Some("text/html");
+    }
+    None
+}
+
+// The type to store an upload under. Content decides; the filename only narrows down ZIP
+// based formats and text.
+pub fn detect_content_type(data: &[u8], filename: &str) -> &'static str {
+    let sample = &data[..data.len().min(SNIFF_BYTES)];
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+
+    match detect(sample) {
+        Some("application/zip") => zip_content_type(&extension).unwrap_or("application/zip"),
+        Some(content_type) => content_type,
+        None if looks_like_text(sample) => text_content_type(&extension),
+        None => "application/octet-stream",
+    }
+}
+
+fn zip_content_type(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "odp" => "application/vnd.oasis.opendocument.presentation",
+        "epub" => "application/epub+zip",
+        "jar" => "application/java-archive",
+        "apk" => "application/vnd.android.package-archive",
+        _ => return None,
+    })
+}
+
+fn text_content_type(extension: &str) -> &'static str {
+    match extension {
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "html" | "htm" => "text/html",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        _ => "text/plain",
+    }
+}
+
+// UTF-8 without control characters other than whitespace. The sample may end in the
+// middle of a character.
+fn looks_like_text(sample: &[u8]) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+
+    let text = match std::str::from_utf8(sample) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&sample[..e.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return false,
+    };
+
+    text.chars().all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+}
+
+pub fn is_active_content(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    ACTIVE_CONTENT_TYPES.contains(&essence.as_str()) || essence.ends_with("+xml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_decides_over_the_filename() {
+        assert_eq!(detect_content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "photo.txt"), "image/png");
+        assert_eq!(detect_content_type(b"MZ\x90\0\x03\0\0\0", "cat.jpg"), "application/x-msdownload");
+        assert_eq!(detect_content_type(b"RIFF\0\0\0\0WEBPVP8 ", "a.bin"), "image/webp");
+        assert_eq!(detect_content_type(b"\0\0\0\x18ftypmp42\0\0\0\0", "clip"), "video/mp4");
+        assert_eq!(detect_content_type(b"\0\x01\x02\x03binary", "notes.txt"), "application/octet-stream");
+    }
+
+    #[test]
+    fn markup_is_recognised_whatever_it_is_called() {
+        assert_eq!(detect_content_type(b"\xef\xbb\xbf  <!DOCTYPE html><html>", "image.png"), "text/html");
+        assert_eq!(detect_content_type(b"<script>alert(1)</script>", "notes.txt"), "text/html");
+        assert_eq!(detect_content_type(b"<?xml version=\"1.0\"?><svg xmlns=\"...\">", "a.xml"), "image/svg+xml");
+        assert_eq!(detect_content_type(b"<svg onload=\"x\">", "a.gif"), "image/svg+xml");
+    }
+
+    #[test]
+    fn filenames_only_narrow_down_zip_and_text() {
+        assert_eq!(
+            detect_content_type(b"PK\x03\x04\x14\0\0\0", "report.docx"),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        );
+        assert_eq!(detect_content_type(b"PK\x03\x04\x14\0\0\0", "archive.png"), "application/zip");
+        assert_eq!(detect_content_type(b"a,b\n1,2\n", "data.csv"), "text/csv");
+        assert_eq!(detect_content_type(b"plain words", "readme"), "text/plain");
+        // A sample cut off in the middle of a multi-byte character is still text
+        assert_eq!(detect_content_type("héllo".as_bytes().split_at(2).0, "a.md"), "text/markdown");
+    }
+
+    #[test]
+    fn active_content_is_flagged() {
+        assert!(is_active_content("text/html; charset=utf-8"));
+        assert!(is_active_content("IMAGE/SVG+XML"));
+        assert!(is_active_content("application/rss+xml"));
+        assert!(is_active_content("application/x-msdownload"));
+        assert!(!is_active_content("image/png"));
+        assert!(!is_active_content("text/plain"));
+    }
+}
@@ -0,0 +1,25 @@
+// Outgoing email. Messages are POSTed as JSON to an HTTP relay (EMAIL_HTTP_PROVIDER_URL)
+// that does the actual sending; without one only the recipient and subject are logged, so
+// tokens and links in the body don't end up in the logs.
+pub async fn send_email(to: &str, subject: &str, text: &str) -> Result<(), String> {
+    let Ok(url) = std::env::var("EMAIL_HTTP_PROVIDER_URL") else {
+        tracing::info!(to, subject, "EMAIL_HTTP_PROVIDER_URL not set, email not sent");
+        return Ok(());
+    };
+
+    let mut request = reqwest::Client::new().post(&url).json(&serde_json::json!({
+        "to": to,
+        "subject": subject,
+        "text": text,
+    }));
+
+    if let Ok(token) = std::env::var("EMAIL_HTTP_PROVIDER_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("email provider returned {}", response.status()));
+    }
+    Ok(())
+}
@@ -396,3 +396,53 @@ pub async fn delete_guild(
     
     Ok(StatusCode::OK)
 }
+
+#[derive(Deserialize)]
+pub struct SetGuildVerifiedRequest {
+    verified: bool,
+    reason: String,
+}
+
+// Grant or revoke a guild's verified badge in discovery
+pub async fn set_guild_verified(
+    State(state): State<AppState>,
+    Path(guild_id): Path<String>,
+    Extension(admin_id): Extension<String>,
+    Json(payload): Json<SetGuildVerifiedRequest>,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&admin_id, &state).await?;
+    
+    let guild_uuid = Uuid::parse_str(&guild_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let admin_uuid = Uuid::parse_str(&admin_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    
+    let result = sqlx::query!(
+        "UPDATE guilds SET is_verified = $1 WHERE id = $2",
+        payload.verified,
+        guild_uuid
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    
+    // Log admin action
+    let action_type = if payload.verified { "verify_guild" } else { "unverify_guild" };
+    sqlx::query!(
+        r#"
+        INSERT INTO admin_actions (admin_id, action_type, target_type, target_id, reason)
+        VALUES ($1, $2, 'guild', $3, $4)
+        "#,
+        admin_uuid,
+        action_type,
+        guild_uuid,
+        payload.reason
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    
+    Ok(StatusCode::OK)
+}
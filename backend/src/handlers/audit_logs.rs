@@ -5,6 +5,7 @@ use uuid::Uuid;
 use serde_json::Value as JsonValue;
 
 use crate::AppState;
+use crate::handlers::roles::{has_permission, VIEW_AUDIT_LOG};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
@@ -44,10 +45,50 @@ pub struct AuditLog {
     pub target_type: Option<String>,
     pub target_id: Option<Uuid>,
     pub details: Option<JsonValue>,
+    pub reason: Option<String>,
     pub created_at: chrono::NaiveDateTime,
 }
 
+// Filters for get_guild_audit_logs. `before` is the id of the last entry of the previous page.
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub user_id: Option<Uuid>,
+    // Comma-separated list of action types
+    pub action_type: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    pub before: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+// Optional free-text reason a moderator can attach to any mutating request (URL-encoded)
+pub const AUDIT_LOG_REASON_HEADER: &str = "x-audit-log-reason";
+const MAX_REASON_LENGTH: usize = 512;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+// Entries older than this many days are swept; 0 keeps them forever
+const DEFAULT_RETENTION_DAYS: i32 = 90;
+const RETENTION_SWEEP_INTERVAL_SECS: u64 = 60 * 60;
+
+// Read the audit log reason header, if the client sent one
+pub fn audit_log_reason(headers: &HeaderMap) -> Option<String> {
+    let raw = headers.get(AUDIT_LOG_REASON_HEADER)?.to_str().ok()?;
+    let decoded = percent_encoding::percent_decode_str(raw).decode_utf8_lossy();
+    let reason: String = decoded.trim().chars().take(MAX_REASON_LENGTH).collect();
+
+    if reason.is_empty() {
+        None
+    } else {
+        Some(reason)
+    }
+}
+
 // Helper function to create audit log entries
+#[allow(clippy::too_many_arguments)]
 pub async fn create_audit_log(
     db: &sqlx::PgPool,
     guild_id: Uuid,
@@ -56,15 +97,17 @@ pub async fn create_audit_log(
     target_type: Option<&str>,
     target_id: Option<Uuid>,
     details: Option<JsonValue>,
+    reason: Option<String>,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "INSERT INTO audit_logs (guild_id, user_id, action_type, target_type, target_id, details) VALUES ($1, $2, $3, $4, $5, $6)",
+        "INSERT INTO audit_logs (guild_id, user_id, action_type, target_type, target_id, details, reason) VALUES ($1, $2, $3, $4, $5, $6, $7)",
         guild_id,
         user_id,
         action_type,
         target_type,
         target_id,
-        details
+        details,
+        reason
     )
     .execute(db)
     .await?;
@@ -72,29 +115,28 @@ pub async fn create_audit_log(
     Ok(())
 }
 
-// Get audit logs for a guild
+// Get audit logs for a guild, newest first
 pub async fn get_guild_audit_logs(
     State(state): State<AppState>,
     headers: HeaderMap,
     axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<AuditLogQuery>,
 ) -> Result<Json<Vec<AuditLog>>, StatusCode> {
     let user_id = extract_user_id(&headers)?;
-    
-    // Check if user is guild member
-    let is_member = sqlx::query_scalar!(
-        "SELECT EXISTS(SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2)",
-        guild_id,
-        user_id
-    )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .unwrap_or(false);
 
-    if !is_member {
+    if !has_permission(&state, user_id, guild_id, VIEW_AUDIT_LOG).await? {
         return Err(StatusCode::FORBIDDEN);
     }
 
+    let action_types: Option<Vec<String>> = query.action_type.map(|types| {
+        types
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect()
+    });
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
     let logs = sqlx::query!(
         r#"
         SELECT 
@@ -106,14 +148,32 @@ pub async fn get_guild_audit_logs(
             al.target_type,
             al.target_id,
             al.details,
+            al.reason,
             al.created_at
         FROM audit_logs al
         LEFT JOIN users u ON al.user_id = u.id
         WHERE al.guild_id = $1
-        ORDER BY al.created_at DESC
-        LIMIT 100
+          AND ($2::uuid IS NULL OR al.user_id = $2)
+          AND ($3::text[] IS NULL OR al.action_type = ANY($3))
+          AND ($4::text IS NULL OR al.target_type = $4)
+          AND ($5::uuid IS NULL OR al.target_id = $5)
+          AND ($6::timestamp IS NULL OR al.created_at >= $6)
+          AND ($7::timestamp IS NULL OR al.created_at < $7)
+          AND ($8::uuid IS NULL OR (al.created_at, al.id) < (
+              SELECT created_at, id FROM audit_logs WHERE id = $8 AND guild_id = $1
+          ))
+        ORDER BY al.created_at DESC, al.id DESC
+        LIMIT $9
         "#,
-        guild_id
+        guild_id,
+        query.user_id,
+        action_types.as_deref(),
+        query.target_type,
+        query.target_id,
+        query.since.map(|t| t.naive_utc()),
+        query.until.map(|t| t.naive_utc()),
+        query.before,
+        limit
     )
     .fetch_all(&state.db)
     .await
@@ -130,9 +190,45 @@ pub async fn get_guild_audit_logs(
             target_type: log.target_type,
             target_id: log.target_id,
             details: log.details,
+            reason: log.reason,
             created_at: log.created_at.unwrap_or_else(|| chrono::Utc::now().naive_utc()),
         })
         .collect();
 
     Ok(Json(response))
 }
+
+// Periodically delete audit log entries past the retention window (AUDIT_LOG_RETENTION_DAYS)
+pub fn spawn_retention_sweep(db: sqlx::PgPool) {
+    let retention_days = std::env::var("AUDIT_LOG_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    if retention_days <= 0 {
+        println!("ℹ️  Audit log retention disabled, keeping entries forever");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(RETENTION_SWEEP_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match sqlx::query!(
+                "DELETE FROM audit_logs WHERE created_at < (NOW() AT TIME ZONE 'UTC') - make_interval(days => $1)",
+                retention_days
+            )
+            .execute(&db)
+            .await
+            {
+                Ok(result) if result.rows_affected() > 0 => {
+                    println!("🧹 Swept {} audit log entries older than {} days", result.rows_affected(), retention_days);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Audit log retention sweep failed: {:?}", e),
+            }
+        }
+    });
+}
@@ -1,7 +1,9 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, http::StatusCode, Extension, Json};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{encode, EncodingKey, Header};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use crate::{models::*, AppState};
@@ -12,6 +14,15 @@ pub struct Claims {
     pub exp: usize,
 }
 
+const EMAIL_VERIFICATION_TTL_HOURS: i32 = 24;
+// A new verification email can be asked for this often
+const EMAIL_VERIFICATION_RESEND_SECS: i64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailRequest {
+    pub token: String,
+}
+
 pub async fn register(
     State(state): State<AppState>,
     Json(payload): Json<RegisterRequest>,
@@ -78,6 +89,7 @@ pub async fn register(
             id: user_id,
             email: payload.email,
             username: username.clone(),
+            email_verified: false,
             is_premium: false,
             premium_since: None,
             premium_expires_at: None,
@@ -94,7 +106,7 @@ pub async fn login(
     std::fs::write("/tmp/wryft_login.log", format!("Login attempt: {}\n", payload.email)).ok();
     
     let user = sqlx::query!(
-        "SELECT id, email, username, password_hash, is_premium, premium_since, premium_expires_at, is_banned, is_admin, admin_level FROM users WHERE email = $1",
+        "SELECT id, email, username, password_hash, email_verified, is_premium, premium_since, premium_expires_at, is_banned, is_admin, admin_level FROM users WHERE email = $1",
         payload.email
     )
     .fetch_optional(&state.db)
@@ -127,6 +139,7 @@ pub async fn login(
             id: user.id,
             email: user.email,
             username: user.username,
+            email_verified: user.email_verified,
             is_premium: user.is_premium.unwrap_or(false),
             premium_since: user.premium_since.map(|dt| dt.to_string()),
             premium_expires_at: user.premium_expires_at.map(|dt| dt.to_string()),
@@ -160,3 +173,125 @@ fn create_token(user_id: &Uuid) -> Result<String, StatusCode> {
         StatusCode::INTERNAL_SERVER_ERROR
     })
 }
+
+fn hash_verification_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// Email the user a link that confirms their address. Replaces any link sent before.
+pub async fn request_email_verification(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = Uuid::parse_str(&user_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let user = sqlx::query!("SELECT email, email_verified FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if user.email_verified {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let recently_sent = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM email_verification_tokens
+            WHERE user_id = $1 AND created_at > NOW() - make_interval(secs => $2)
+        ) AS "exists!"
+        "#,
+        user_id,
+        EMAIL_VERIFICATION_RESEND_SECS as f64
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if recently_sent {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let mut token_bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut token_bytes);
+    let token: String = token_bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!("DELETE FROM email_verification_tokens WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (token_hash, user_id, email, expires_at)
+        VALUES ($1, $2, $3, NOW() + make_interval(hours => $4))
+        "#,
+        hash_verification_token(&token),
+        user_id,
+        user.email,
+        EMAIL_VERIFICATION_TTL_HOURS
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let base_url = std::env::var("EMAIL_VERIFICATION_URL")
+        .unwrap_or_else(|_| "http://localhost:5173/verify-email".to_string());
+    let text = format!(
+        "Confirm your email address for Wryft by opening this link:\n\n{}?token={}\n\nThe link expires in {} hours. If you didn't ask for this, you can ignore this email.",
+        base_url, token, EMAIL_VERIFICATION_TTL_HOURS
+    );
+
+    crate::email::send_email(&user.email, "Confirm your email address", &text)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to send verification email: {}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// Confirm an email address with the token from the verification link
+pub async fn confirm_email_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ConfirmEmailRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let pending = sqlx::query!(
+        r#"
+        DELETE FROM email_verification_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, email, expires_at > NOW() AS "valid!"
+        "#,
+        hash_verification_token(payload.token.trim())
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    if !pending.valid {
+        return Err(StatusCode::GONE);
+    }
+
+    // The address may have changed since the link was sent
+    let verified = sqlx::query!(
+        "UPDATE users SET email_verified = TRUE WHERE id = $1 AND email = $2",
+        pending.user_id,
+        pending.email
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if verified.rows_affected() == 0 {
+        return Err(StatusCode::GONE);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
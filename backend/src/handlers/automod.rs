@@ -0,0 +1,862 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, MANAGE_GUILD};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+const MAX_RULES_PER_GUILD: i64 = 25;
+const MAX_KEYWORDS: usize = 1000;
+const MAX_KEYWORD_LENGTH: usize = 60;
+const MAX_REGEX_PATTERNS: usize = 10;
+const MAX_REGEX_LENGTH: usize = 260;
+const MAX_MENTION_LIMIT: i32 = 50;
+const MAX_REPEAT_LIMIT: i32 = 20;
+const MAX_REPEAT_WINDOW_SECONDS: i32 = 3600;
+const DEFAULT_REPEAT_WINDOW_SECONDS: i32 = 60;
+const MAX_TIMEOUT_SECONDS: i64 = 28 * 24 * 60 * 60;
+
+// Rule-specific settings; which fields apply depends on the rule's trigger_type
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TriggerMetadata {
+    // keyword: plain words, matched as whole words unless prefixed/suffixed with `*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    // keyword: case-insensitive regular expressions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regex_patterns: Vec<String>,
+    // keyword: matches equal to one of these are ignored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_list: Vec<String>,
+    // mention_spam: most distinct mentions allowed in one message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mention_limit: Option<i32>,
+    // repeated_message: identical messages allowed within the window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_limit: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_window_seconds: Option<i32>,
+    // domain_block: blocked hosts, subdomains included
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutoModAction {
+    BlockMessage,
+    SendAlert { channel_id: Uuid },
+    Timeout { duration_seconds: i64 },
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutoModRuleResponse {
+    pub id: Uuid,
+    pub guild_id: Uuid,
+    pub name: String,
+    pub trigger_type: String,
+    pub trigger_metadata: TriggerMetadata,
+    pub actions: Vec<AutoModAction>,
+    pub exempt_roles: Vec<Uuid>,
+    pub exempt_channels: Vec<Uuid>,
+    pub enabled: bool,
+    pub creator_id: Option<Uuid>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAutoModRuleRequest {
+    pub name: String,
+    pub trigger_type: String,
+    #[serde(default)]
+    pub trigger_metadata: TriggerMetadata,
+    pub actions: Vec<AutoModAction>,
+    #[serde(default)]
+    pub exempt_roles: Vec<Uuid>,
+    #[serde(default)]
+    pub exempt_channels: Vec<Uuid>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAutoModRuleRequest {
+    pub name: Option<String>,
+    pub trigger_metadata: Option<TriggerMetadata>,
+    pub actions: Option<Vec<AutoModAction>>,
+    pub exempt_roles: Option<Vec<Uuid>>,
+    pub exempt_channels: Option<Vec<Uuid>>,
+    pub enabled: Option<bool>,
+}
+
+struct AutoModRuleRow {
+    id: Uuid,
+    guild_id: Uuid,
+    name: String,
+    trigger_type: String,
+    trigger_metadata: serde_json::Value,
+    actions: serde_json::Value,
+    exempt_roles: Vec<Uuid>,
+    exempt_channels: Vec<Uuid>,
+    enabled: bool,
+    creator_id: Option<Uuid>,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl AutoModRuleRow {
+    fn into_response(self) -> AutoModRuleResponse {
+        AutoModRuleResponse {
+            id: self.id,
+            guild_id: self.guild_id,
+            name: self.name,
+            trigger_type: self.trigger_type,
+            trigger_metadata: serde_json::from_value(self.trigger_metadata).unwrap_or_default(),
+            actions: serde_json::from_value(self.actions).unwrap_or_default(),
+            exempt_roles: self.exempt_roles,
+            exempt_channels: self.exempt_channels,
+            enabled: self.enabled,
+            creator_id: self.creator_id,
+            created_at: self.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+            updated_at: self.updated_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+}
+
+// Turn a keyword into a regex: whole-word by default, `*` on either end drops that word boundary
+fn keyword_pattern(keyword: &str) -> String {
+    let prefix = keyword.starts_with('*');
+    let suffix = keyword.ends_with('*') && keyword.len() > 1;
+    let core = keyword.trim_matches('*');
+    format!(
+        "{}{}{}",
+        if prefix { "" } else { r"\b" },
+        regex::escape(core),
+        if suffix { "" } else { r"\b" }
+    )
+}
+
+fn compile_pattern(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .ok()
+}
+
+// Compiled keyword and regex patterns of each rule, keyed by rule id with the rule's updated_at
+type PatternCache = Mutex<HashMap<Uuid, (String, Arc<Vec<Regex>>)>>;
+
+fn pattern_cache() -> &'static PatternCache {
+    static CACHE: OnceLock<PatternCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn rule_patterns(rule: &AutoModRuleResponse) -> Arc<Vec<Regex>> {
+    if let Some((updated_at, patterns)) = pattern_cache().lock().unwrap().get(&rule.id) {
+        if *updated_at == rule.updated_at {
+            return patterns.clone();
+        }
+    }
+
+    let metadata = &rule.trigger_metadata;
+    let patterns: Arc<Vec<Regex>> = Arc::new(
+        metadata
+            .keywords
+            .iter()
+            .map(|k| keyword_pattern(k))
+            .chain(metadata.regex_patterns.iter().cloned())
+            .filter_map(|pattern| compile_pattern(&pattern))
+            .collect(),
+    );
+    pattern_cache()
+        .lock()
+        .unwrap()
+        .insert(rule.id, (rule.updated_at.clone(), patterns.clone()));
+    patterns
+}
+
+fn mention_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"@(?:everyone|here|[^\s@#]+#\d{4})").unwrap())
+}
+
+fn invite_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)(?:https?://)?(?:www\.)?([a-z0-9.-]+(?::\d+)?)/(?:invite/)?([A-Za-z0-9]{2,32})\b").unwrap()
+    })
+}
+
+fn domain_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)\b((?:[a-z0-9-]+\.)+[a-z]{2,})\b").unwrap())
+}
+
+async fn validate_rule(
+    db: &sqlx::PgPool,
+    guild_id: Uuid,
+    name: &str,
+    trigger_type: &str,
+    metadata: &TriggerMetadata,
+    actions: &[AutoModAction],
+) -> Result<(), StatusCode> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match trigger_type {
+        "keyword" => {
+            if metadata.keywords.is_empty() && metadata.regex_patterns.is_empty() {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if metadata.keywords.len() > MAX_KEYWORDS
+                || metadata.regex_patterns.len() > MAX_REGEX_PATTERNS
+                || metadata.allow_list.len() > MAX_KEYWORDS
+            {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if metadata.keywords.iter().chain(&metadata.allow_list).any(|k| {
+                let k = k.trim_matches('*');
+                k.trim().is_empty() || k.chars().count() > MAX_KEYWORD_LENGTH
+            }) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            for pattern in &metadata.regex_patterns {
+                if pattern.chars().count() > MAX_REGEX_LENGTH || compile_pattern(pattern).is_none() {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+            }
+        }
+        "mention_spam" => {
+            match metadata.mention_limit {
+                Some(limit) if (1..=MAX_MENTION_LIMIT).contains(&limit) => {}
+                _ => return Err(StatusCode::BAD_REQUEST),
+            }
+        }
+        "repeated_message" => {
+            match metadata.repeat_limit {
+                Some(limit) if (1..=MAX_REPEAT_LIMIT).contains(&limit) => {}
+                _ => return Err(StatusCode::BAD_REQUEST),
+            }
+            if let Some(window) = metadata.repeat_window_seconds {
+                if !(1..=MAX_REPEAT_WINDOW_SECONDS).contains(&window) {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+            }
+        }
+        "invite_link" => {}
+        "domain_block" => {
+            if metadata.blocked_domains.is_empty()
+                || metadata.blocked_domains.len() > MAX_KEYWORDS
+                || metadata.blocked_domains.iter().any(|d| d.trim().is_empty() || d.contains(char::is_whitespace))
+            {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+
+    if actions.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    for action in actions {
+        match action {
+            AutoModAction::BlockMessage => {}
+            AutoModAction::SendAlert { channel_id } => {
+                let in_guild = sqlx::query_scalar!(
+                    "SELECT 1 FROM channels WHERE id = $1 AND guild_id = $2 AND channel_type = 'text'",
+                    channel_id,
+                    guild_id
+                )
+                .fetch_optional(db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+                if in_guild.is_none() {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+            }
+            AutoModAction::Timeout { duration_seconds } => {
+                if !(1..=MAX_TIMEOUT_SECONDS).contains(duration_seconds) {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// List a guild's AutoMod rules
+pub async fn get_automod_rules(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<AutoModRuleResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let rules = sqlx::query_as!(
+        AutoModRuleRow,
+        r#"
+        SELECT id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles,
+               exempt_channels, enabled, creator_id, created_at, updated_at
+        FROM automod_rules
+        WHERE guild_id = $1
+        ORDER BY created_at
+        "#,
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rules.into_iter().map(AutoModRuleRow::into_response).collect()))
+}
+
+// Create an AutoMod rule
+pub async fn create_automod_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateAutoModRuleRequest>,
+) -> Result<Json<AutoModRuleResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    validate_rule(&state.db, guild_id, &payload.name, &payload.trigger_type, &payload.trigger_metadata, &payload.actions).await?;
+
+    let rule_count = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM automod_rules WHERE guild_id = $1",
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(0);
+
+    if rule_count >= MAX_RULES_PER_GUILD {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let trigger_metadata = serde_json::to_value(&payload.trigger_metadata).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let actions = serde_json::to_value(&payload.actions).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rule = sqlx::query_as!(
+        AutoModRuleRow,
+        r#"
+        INSERT INTO automod_rules (id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles, exempt_channels, enabled, creator_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles,
+                  exempt_channels, enabled, creator_id, created_at, updated_at
+        "#,
+        Uuid::new_v4(),
+        guild_id,
+        payload.name.trim(),
+        payload.trigger_type,
+        trigger_metadata,
+        actions,
+        &payload.exempt_roles,
+        &payload.exempt_channels,
+        payload.enabled.unwrap_or(true),
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "automod_rule_create",
+        Some("automod_rule"),
+        Some(rule.id),
+        Some(serde_json::json!({
+            "name": rule.name,
+            "trigger_type": rule.trigger_type,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(rule.into_response()))
+}
+
+// Update an AutoMod rule (the trigger type can't change)
+pub async fn update_automod_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, rule_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateAutoModRuleRequest>,
+) -> Result<Json<AutoModRuleResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let existing = sqlx::query_as!(
+        AutoModRuleRow,
+        r#"
+        SELECT id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles,
+               exempt_channels, enabled, creator_id, created_at, updated_at
+        FROM automod_rules
+        WHERE id = $1 AND guild_id = $2
+        "#,
+        rule_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?
+    .into_response();
+
+    let name = payload.name.unwrap_or_else(|| existing.name.clone());
+    let trigger_metadata = payload.trigger_metadata.unwrap_or_else(|| existing.trigger_metadata.clone());
+    let actions = payload.actions.unwrap_or_else(|| existing.actions.clone());
+    let exempt_roles = payload.exempt_roles.unwrap_or_else(|| existing.exempt_roles.clone());
+    let exempt_channels = payload.exempt_channels.unwrap_or_else(|| existing.exempt_channels.clone());
+    let enabled = payload.enabled.unwrap_or(existing.enabled);
+
+    validate_rule(&state.db, guild_id, &name, &existing.trigger_type, &trigger_metadata, &actions).await?;
+
+    let rule = sqlx::query_as!(
+        AutoModRuleRow,
+        r#"
+        UPDATE automod_rules
+        SET name = $1, trigger_metadata = $2, actions = $3, exempt_roles = $4,
+            exempt_channels = $5, enabled = $6, updated_at = NOW()
+        WHERE id = $7 AND guild_id = $8
+        RETURNING id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles,
+                  exempt_channels, enabled, creator_id, created_at, updated_at
+        "#,
+        name.trim(),
+        serde_json::to_value(&trigger_metadata).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        serde_json::to_value(&actions).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        &exempt_roles,
+        &exempt_channels,
+        enabled,
+        rule_id,
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_response();
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "automod_rule_update",
+        Some("automod_rule"),
+        Some(rule_id),
+        Some(serde_json::json!({
+            "before": existing,
+            "after": rule,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(rule))
+}
+
+// Delete an AutoMod rule
+pub async fn delete_automod_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, rule_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let deleted = sqlx::query!(
+        "DELETE FROM automod_rules WHERE id = $1 AND guild_id = $2 RETURNING name, trigger_type",
+        rule_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    pattern_cache().lock().unwrap().remove(&rule_id);
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "automod_rule_delete",
+        Some("automod_rule"),
+        Some(rule_id),
+        Some(serde_json::json!({
+            "name": deleted.name,
+            "trigger_type": deleted.trigger_type,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// A message about to be sent (or an edit about to be saved) in a guild channel
+pub struct AutoModContext<'a> {
+    pub guild_id: Uuid,
+    pub channel_id: Uuid,
+    pub author_id: Uuid,
+    pub author: &'a str,
+    pub message_id: Uuid,
+    pub text: &'a str,
+}
+
+// The rules a message tripped. Their actions are carried out by the caller once the outcome
+// is settled: right away for a blocked message, otherwise after the message is saved.
+pub struct AutoModVerdict {
+    pub blocked: bool,
+    triggered: Vec<(AutoModRuleResponse, String)>,
+}
+
+impl AutoModVerdict {
+    pub async fn carry_out(self, state: &AppState, ctx: &AutoModContext<'_>) {
+        for (rule, matched) in &self.triggered {
+            let rule_blocks = rule.actions.iter().any(|a| matches!(a, AutoModAction::BlockMessage));
+            for action in &rule.actions {
+                execute_action(state, rule, action, ctx, matched, rule_blocks).await;
+            }
+        }
+    }
+}
+
+// Run a message through the guild's enabled AutoMod rules
+pub async fn evaluate_message(state: &AppState, ctx: &AutoModContext<'_>) -> Result<AutoModVerdict, StatusCode> {
+    let mut verdict = AutoModVerdict {
+        blocked: false,
+        triggered: Vec::new(),
+    };
+
+    let rules = sqlx::query_as!(
+        AutoModRuleRow,
+        r#"
+        SELECT id, guild_id, name, trigger_type, trigger_metadata, actions, exempt_roles,
+               exempt_channels, enabled, creator_id, created_at, updated_at
+        FROM automod_rules
+        WHERE guild_id = $1 AND enabled
+        ORDER BY created_at
+        "#,
+        ctx.guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if rules.is_empty() {
+        return Ok(verdict);
+    }
+
+    // Members who can configure AutoMod are never moderated by it
+    if has_permission(state, ctx.author_id, ctx.guild_id, MANAGE_GUILD).await? {
+        return Ok(verdict);
+    }
+
+    let mut role_ids: HashSet<Uuid> = sqlx::query_scalar!(
+        "SELECT role_id FROM role_members WHERE user_id = $1 AND guild_id = $2",
+        ctx.author_id,
+        ctx.guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_iter()
+    .collect();
+    role_ids.insert(ctx.guild_id);
+
+    for rule in rules.into_iter().map(AutoModRuleRow::into_response) {
+        if rule.exempt_channels.contains(&ctx.channel_id)
+            || rule.exempt_roles.iter().any(|r| role_ids.contains(r))
+        {
+            continue;
+        }
+
+        let Some(matched) = match_rule(state, &rule, ctx).await? else {
+            continue;
+        };
+
+        verdict.blocked |= rule.actions.iter().any(|a| matches!(a, AutoModAction::BlockMessage));
+        verdict.triggered.push((rule, matched));
+    }
+
+    Ok(verdict)
+}
+
+// Returns the offending part of the message if the rule's trigger matches
+async fn match_rule(
+    state: &AppState,
+    rule: &AutoModRuleResponse,
+    ctx: &AutoModContext<'_>,
+) -> Result<Option<String>, StatusCode> {
+    let metadata = &rule.trigger_metadata;
+
+    match rule.trigger_type.as_str() {
+        "keyword" => {
+            let allowed: HashSet<String> = metadata.allow_list.iter().map(|a| a.to_lowercase()).collect();
+
+            for re in rule_patterns(rule).iter() {
+                let found = re
+                    .find_iter(ctx.text)
+                    .find(|m| !allowed.contains(&m.as_str().to_lowercase()))
+                    .map(|m| m.as_str().to_string());
+                if found.is_some() {
+                    return Ok(found);
+                }
+            }
+            Ok(None)
+        }
+        "mention_spam" => {
+            let limit = metadata.mention_limit.unwrap_or(MAX_MENTION_LIMIT) as usize;
+            let mentions: HashSet<String> = mention_regex()
+                .find_iter(ctx.text)
+                .map(|m| m.as_str().to_lowercase())
+                .collect();
+
+            if mentions.len() > limit {
+                Ok(Some(format!("{} mentions", mentions.len())))
+            } else {
+                Ok(None)
+            }
+        }
+        "repeated_message" => {
+            let limit = metadata.repeat_limit.unwrap_or(MAX_REPEAT_LIMIT) as i64;
+            let window = metadata.repeat_window_seconds.unwrap_or(DEFAULT_REPEAT_WINDOW_SECONDS) as f64;
+            let text = ctx.text.trim();
+            if text.is_empty() {
+                return Ok(None);
+            }
+
+            // Counted across the whole guild so spam spread over channels still trips it
+            let repeats = sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) FROM messages
+                WHERE author_id = $1
+                  AND id <> $2
+                  AND deleted = false
+                  AND created_at > NOW() - make_interval(secs => $3)
+                  AND LOWER(TRIM(text)) = LOWER($4)
+                  AND channel IN (SELECT id::text FROM channels WHERE guild_id = $5)
+                "#,
+                ctx.author_id,
+                ctx.message_id,
+                window,
+                text,
+                ctx.guild_id
+            )
+            .fetch_one(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .unwrap_or(0);
+
+            if repeats >= limit {
+                Ok(Some(text.to_string()))
+            } else {
+                Ok(None)
+            }
+        }
+        "invite_link" => {
+            let candidates: Vec<(String, String)> = invite_regex()
+                .captures_iter(ctx.text)
+                .map(|c| (c[0].to_string(), c[2].to_string()))
+                .collect();
+            if candidates.is_empty() {
+                return Ok(None);
+            }
+
+            // Invites to this guild are fine; only links to other guilds trip the rule
+            let codes: Vec<String> = candidates.iter().map(|(_, code)| code.clone()).collect();
+            let foreign = sqlx::query_scalar!(
+                "SELECT code FROM invites WHERE code = ANY($1) AND guild_id <> $2",
+                &codes,
+                ctx.guild_id
+            )
+            .fetch_all(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            Ok(candidates
+                .into_iter()
+                .find(|(_, code)| foreign.contains(code))
+                .map(|(link, _)| link))
+        }
+        "domain_block" => {
+            let blocked: Vec<String> = metadata
+                .blocked_domains
+                .iter()
+                .map(|d| d.trim().trim_start_matches("*.").to_lowercase())
+                .collect();
+
+            Ok(domain_regex().find_iter(ctx.text).map(|m| m.as_str().to_lowercase()).find(|host| {
+                blocked.iter().any(|d| host == d || host.ends_with(&format!(".{}", d)))
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn execute_action(
+    state: &AppState,
+    rule: &AutoModRuleResponse,
+    action: &AutoModAction,
+    ctx: &AutoModContext<'_>,
+    matched: &str,
+    message_blocked: bool,
+) {
+    let mut details = serde_json::json!({
+        "rule_id": rule.id,
+        "rule_name": rule.name,
+        "trigger_type": rule.trigger_type,
+        "matched_content": matched,
+        "channel_id": ctx.channel_id,
+        "message_id": ctx.message_id,
+        "message_blocked": message_blocked,
+    });
+
+    let action_type = match action {
+        AutoModAction::BlockMessage => {
+            details["message_content"] = serde_json::json!(ctx.text);
+            "automod_block_message"
+        }
+        AutoModAction::SendAlert { channel_id } => {
+            details["alert_channel_id"] = serde_json::json!(channel_id);
+            send_alert(state, rule, *channel_id, ctx, matched, message_blocked).await;
+            "automod_alert"
+        }
+        AutoModAction::Timeout { duration_seconds } => {
+            let until = chrono::Utc::now() + chrono::Duration::seconds(*duration_seconds);
+            let _ = sqlx::query!(
+                r#"
+                UPDATE guild_members
+                SET timed_out_until = GREATEST(COALESCE(timed_out_until, $1), $1)
+                WHERE guild_id = $2 AND user_id = $3
+                "#,
+                until,
+                ctx.guild_id,
+                ctx.author_id
+            )
+            .execute(&state.db)
+            .await;
+
+            state.ws_state.send_to_user(&ctx.author_id.to_string(), serde_json::json!({
+                "type": "guild_member_timeout",
+                "guild_id": ctx.guild_id,
+                "timed_out_until": until.to_rfc3339(),
+            })).await;
+
+            details["duration_seconds"] = serde_json::json!(duration_seconds);
+            details["timed_out_until"] = serde_json::json!(until.to_rfc3339());
+            "automod_timeout"
+        }
+    };
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        ctx.guild_id,
+        None,
+        action_type,
+        Some("user"),
+        Some(ctx.author_id),
+        Some(details),
+        None
+    ).await;
+}
+
+// Post an alert into the rule's log channel
+async fn send_alert(
+    state: &AppState,
+    rule: &AutoModRuleResponse,
+    alert_channel_id: Uuid,
+    ctx: &AutoModContext<'_>,
+    matched: &str,
+    message_blocked: bool,
+) {
+    let channel_name = sqlx::query_scalar!("SELECT name FROM channels WHERE id = $1", ctx.channel_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let text = format!(
+        "AutoMod {} a message from {} in #{}: rule \"{}\" matched \"{}\"",
+        if message_blocked { "blocked" } else { "flagged" },
+        ctx.author,
+        channel_name,
+        rule.name,
+        matched
+    );
+    let _ = crate::handlers::system_messages::post_system_message(
+        state,
+        alert_channel_id,
+        crate::handlers::system_messages::MESSAGE_TYPE_AUTOMOD_ALERT,
+        "AutoMod",
+        None,
+        &text,
+        None,
+    ).await;
+}
+
+// Whether a member is currently timed out in a guild
+pub async fn is_timed_out(db: &sqlx::PgPool, guild_id: Uuid, user_id: Uuid) -> Result<bool, StatusCode> {
+    let timed_out = sqlx::query_scalar!(
+        "SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2 AND timed_out_until > NOW()",
+        guild_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(timed_out.is_some())
+}
@@ -4,6 +4,12 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{models::*, AppState};
+use crate::handlers::roles::{has_permission, MANAGE_CHANNELS};
+
+// Slowmode is capped at 6 hours
+pub const MAX_SLOWMODE_SECONDS: i32 = 21600;
+pub const MAX_TOPIC_LENGTH: usize = 1024;
+pub const CHANNEL_TYPES: &[&str] = &["text", "voice"];
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
@@ -42,7 +48,7 @@ pub async fn get_guild_channels(
     
     let channels = sqlx::query!(
         r#"
-        SELECT id, guild_id, name, channel_type, position, category_id, created_at
+        SELECT id, guild_id, name, channel_type, position, category_id, topic, nsfw, slowmode_seconds, created_at
         FROM channels
         WHERE guild_id = $1
         ORDER BY position, created_at
@@ -62,6 +68,9 @@ pub async fn get_guild_channels(
             channel_type: c.channel_type.unwrap_or_else(|| "text".to_string()),
             position: c.position.unwrap_or(0),
             category_id: c.category_id,
+            topic: c.topic,
+            nsfw: c.nsfw.unwrap_or(false),
+            slowmode_seconds: c.slowmode_seconds.unwrap_or(0),
         })
         .collect();
 
@@ -91,6 +100,9 @@ pub async fn create_channel(
 
     let channel_id = Uuid::new_v4();
     let channel_type = payload.channel_type.as_deref().unwrap_or("text");
+    if !CHANNEL_TYPES.contains(&channel_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
     sqlx::query!(
         "INSERT INTO channels (id, guild_id, name, channel_type, category_id) VALUES ($1, $2, $3, $4, $5)",
@@ -117,7 +129,8 @@ pub async fn create_channel(
         "channel_create",
         Some("channel"),
         Some(channel_id),
-        Some(details)
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
     ).await;
 
     Ok(Json(ChannelResponse {
@@ -127,9 +140,225 @@ pub async fn create_channel(
         channel_type: channel_type.to_string(),
         position: 0,
         category_id: payload.category_id,
+        topic: None,
+        nsfw: false,
+        slowmode_seconds: 0,
+    }))
+}
+
+// Update a channel's name, topic, NSFW flag, slowmode or category
+pub async fn update_channel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, channel_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateChannelRequest>,
+) -> Result<Json<ChannelResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_CHANNELS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Validate every field before writing any of them
+    let name = payload.name.as_deref().map(str::trim);
+    if name.is_some_and(|name| name.is_empty() || name.len() > 100) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.topic.as_ref().is_some_and(|topic| topic.len() > MAX_TOPIC_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.slowmode_seconds.is_some_and(|seconds| !(0..=MAX_SLOWMODE_SECONDS).contains(&seconds)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(Some(category_id)) = payload.category_id {
+        ensure_category_in_guild(&state.db, category_id, guild_id).await?;
+    }
+
+    // An empty topic clears it
+    let topic = payload.topic.as_deref().map(|topic| if topic.trim().is_empty() { None } else { Some(topic) });
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let existing = sqlx::query!(
+        "SELECT name, topic, nsfw, slowmode_seconds, category_id FROM channels WHERE id = $1 AND guild_id = $2 FOR UPDATE",
+        channel_id,
+        guild_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let channel = sqlx::query!(
+        r#"
+        UPDATE channels SET
+            name = COALESCE($3, name),
+            topic = CASE WHEN $4 THEN $5 ELSE topic END,
+            nsfw = COALESCE($6, nsfw),
+            slowmode_seconds = COALESCE($7, slowmode_seconds),
+            category_id = CASE WHEN $8 THEN $9 ELSE category_id END
+        WHERE id = $1 AND guild_id = $2
+        RETURNING id, guild_id, name, channel_type, position, category_id, topic, nsfw, slowmode_seconds
+        "#,
+        channel_id,
+        guild_id,
+        name,
+        topic.is_some(),
+        topic.flatten(),
+        payload.nsfw,
+        payload.slowmode_seconds,
+        payload.category_id.is_some(),
+        payload.category_id.flatten()
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Create audit log entry
+    let details = serde_json::json!({
+        "channel_name": channel.name,
+        "before": {
+            "name": existing.name,
+            "topic": existing.topic,
+            "nsfw": existing.nsfw,
+            "slowmode_seconds": existing.slowmode_seconds,
+            "category_id": existing.category_id,
+        },
+        "after": {
+            "name": channel.name,
+            "topic": channel.topic,
+            "nsfw": channel.nsfw,
+            "slowmode_seconds": channel.slowmode_seconds,
+            "category_id": channel.category_id,
+        }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "channel_update",
+        Some("channel"),
+        Some(channel_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(ChannelResponse {
+        id: channel.id,
+        guild_id: channel.guild_id,
+        name: channel.name,
+        channel_type: channel.channel_type.unwrap_or_else(|| "text".to_string()),
+        position: channel.position.unwrap_or(0),
+        category_id: channel.category_id,
+        topic: channel.topic,
+        nsfw: channel.nsfw.unwrap_or(false),
+        slowmode_seconds: channel.slowmode_seconds.unwrap_or(0),
     }))
 }
 
+// Bulk update channel positions and parent categories in a single transaction
+pub async fn reorder_channels(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<Vec<ChannelPositionUpdate>>,
+) -> Result<Json<Vec<ChannelResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_CHANNELS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if payload.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Validate target categories up front so the transaction only contains writes
+    for update in &payload {
+        if update.position < 0 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if let Some(Some(category_id)) = update.category_id {
+            ensure_category_in_guild(&state.db, category_id, guild_id).await?;
+        }
+    }
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for update in &payload {
+        let result = match update.category_id {
+            Some(category_id) => sqlx::query!(
+                "UPDATE channels SET position = $1, category_id = $2 WHERE id = $3 AND guild_id = $4",
+                update.position,
+                category_id,
+                update.id,
+                guild_id
+            )
+            .execute(&mut *tx)
+            .await,
+            None => sqlx::query!(
+                "UPDATE channels SET position = $1 WHERE id = $2 AND guild_id = $3",
+                update.position,
+                update.id,
+                guild_id
+            )
+            .execute(&mut *tx)
+            .await,
+        }
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        // Dropping the transaction rolls back any updates already applied
+        if result.rows_affected() == 0 {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let details = serde_json::json!({
+        "channels": payload.iter().map(|u| serde_json::json!({
+            "id": u.id,
+            "position": u.position,
+            "category_id": u.category_id,
+        })).collect::<Vec<_>>()
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "channel_reorder",
+        Some("guild"),
+        Some(guild_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    get_guild_channels(State(state), headers, axum::extract::Path(guild_id)).await
+}
+
+// Make sure a category exists and belongs to the given guild
+async fn ensure_category_in_guild(
+    db: &sqlx::PgPool,
+    category_id: Uuid,
+    guild_id: Uuid,
+) -> Result<(), StatusCode> {
+    sqlx::query!(
+        "SELECT id FROM channel_categories WHERE id = $1 AND guild_id = $2",
+        category_id,
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    Ok(())
+}
+
 pub async fn delete_channel(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -182,7 +411,8 @@ pub async fn delete_channel(
             "channel_delete",
             Some("channel"),
             Some(channel_id),
-            Some(details)
+            Some(details),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
         ).await;
     }
 
@@ -257,6 +487,17 @@ pub async fn create_category(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "category_create",
+        Some("category"),
+        Some(category_id),
+        Some(serde_json::json!({ "category_name": payload.name })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(CategoryResponse {
         id: category_id,
         guild_id,
@@ -286,6 +527,16 @@ pub async fn update_category(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    let existing = sqlx::query!(
+        "SELECT name, position FROM channel_categories WHERE id = $1 AND guild_id = $2",
+        category_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
     if let Some(name) = &payload.name {
         sqlx::query!(
             "UPDATE channel_categories SET name = $1 WHERE id = $2 AND guild_id = $3",
@@ -318,6 +569,23 @@ pub async fn update_category(
     .await
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let details = serde_json::json!({
+        "category_name": category.name,
+        "before": { "name": existing.name, "position": existing.position },
+        "after": { "name": category.name, "position": category.position }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "category_update",
+        Some("category"),
+        Some(category_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(CategoryResponse {
         id: category.id,
         guild_id: category.guild_id,
@@ -346,15 +614,28 @@ pub async fn delete_category(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    sqlx::query!(
-        "DELETE FROM channel_categories WHERE id = $1 AND guild_id = $2",
+    let deleted = sqlx::query!(
+        "DELETE FROM channel_categories WHERE id = $1 AND guild_id = $2 RETURNING name",
         category_id,
         guild_id
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if let Some(category) = deleted {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "category_delete",
+            Some("category"),
+            Some(category_id),
+            Some(serde_json::json!({ "category_name": category.name })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -455,6 +736,26 @@ pub async fn create_channel_permission(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let details = serde_json::json!({
+        "permission_id": permission_id,
+        "role_id": payload.role_id,
+        "user_id": payload.user_id,
+        "allow_view": payload.allow_view,
+        "allow_send_messages": payload.allow_send_messages,
+        "allow_manage_messages": payload.allow_manage_messages,
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "channel_permission_create",
+        Some("channel"),
+        Some(channel_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(ChannelPermissionResponse {
         id: permission_id,
         channel_id,
@@ -487,6 +788,16 @@ pub async fn update_channel_permission(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    let existing = sqlx::query!(
+        "SELECT allow_view, allow_send_messages, allow_manage_messages FROM channel_permissions WHERE id = $1 AND channel_id = $2",
+        permission_id,
+        channel_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
     if let Some(allow_view) = payload.allow_view {
         sqlx::query!(
             "UPDATE channel_permissions SET allow_view = $1 WHERE id = $2 AND channel_id = $3",
@@ -535,6 +846,33 @@ pub async fn update_channel_permission(
     .await
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let details = serde_json::json!({
+        "permission_id": permission_id,
+        "role_id": permission.role_id,
+        "user_id": permission.user_id,
+        "before": {
+            "allow_view": existing.allow_view,
+            "allow_send_messages": existing.allow_send_messages,
+            "allow_manage_messages": existing.allow_manage_messages,
+        },
+        "after": {
+            "allow_view": permission.allow_view,
+            "allow_send_messages": permission.allow_send_messages,
+            "allow_manage_messages": permission.allow_manage_messages,
+        }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "channel_permission_update",
+        Some("channel"),
+        Some(channel_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(ChannelPermissionResponse {
         id: permission.id,
         channel_id: permission.channel_id,
@@ -566,14 +904,39 @@ pub async fn delete_channel_permission(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    sqlx::query!(
-        "DELETE FROM channel_permissions WHERE id = $1 AND channel_id = $2",
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM channel_permissions WHERE id = $1 AND channel_id = $2
+        RETURNING role_id, user_id, allow_view, allow_send_messages, allow_manage_messages
+        "#,
         permission_id,
         channel_id
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if let Some(permission) = deleted {
+        let details = serde_json::json!({
+            "permission_id": permission_id,
+            "role_id": permission.role_id,
+            "user_id": permission.user_id,
+            "allow_view": permission.allow_view,
+            "allow_send_messages": permission.allow_send_messages,
+            "allow_manage_messages": permission.allow_manage_messages,
+        });
+
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "channel_permission_delete",
+            Some("channel"),
+            Some(channel_id),
+            Some(details),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,484 @@
+use axum::{extract::{Query, State}, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{models::Message, AppState};
+use crate::handlers::roles::{has_permission, MANAGE_GUILD};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+pub const DISCOVERY_CATEGORIES: &[&str] = &[
+    "gaming",
+    "music",
+    "education",
+    "science",
+    "technology",
+    "entertainment",
+    "art",
+    "community",
+    "other",
+];
+
+const MAX_TAGS: usize = 5;
+const MAX_TAG_LENGTH: usize = 24;
+const MAX_PREVIEW_CHANNELS: usize = 5;
+const PREVIEW_MESSAGE_LIMIT: i64 = 50;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+const SORT_MEMBERS: &str = "members";
+const SORT_ACTIVITY: &str = "activity";
+
+#[derive(Debug, Deserialize)]
+pub struct DiscoveryQuery {
+    // Matched against guild name and description
+    pub q: Option<String>,
+    pub category: Option<String>,
+    pub tag: Option<String>,
+    pub verified: Option<bool>,
+    // "members" (default) or "activity"
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct PublicGuildResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub member_count: Option<i32>,
+    pub icon: String,
+    pub icon_url: Option<String>,
+    pub is_verified: bool,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub last_activity_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PreviewChannel {
+    pub id: Uuid,
+    pub name: String,
+    pub topic: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct GuildPreviewResponse {
+    pub guild: PublicGuildResponse,
+    pub banner_url: Option<String>,
+    pub channels: Vec<PreviewChannel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiscoverySettingsResponse {
+    pub guild_id: Uuid,
+    pub is_public: bool,
+    pub is_verified: bool,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub preview_channel_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDiscoverySettingsRequest {
+    // An empty string clears the category
+    pub category: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub preview_channel_ids: Option<Vec<Uuid>>,
+}
+
+// Escape LIKE wildcards so search text is matched literally
+fn like_pattern(q: &str) -> String {
+    let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// Lowercase, trim and dedupe tags; None if any tag is invalid
+pub fn normalize_tags(tags: &[String]) -> Option<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty()
+            || tag.chars().count() > MAX_TAG_LENGTH
+            || !tag.chars().all(|c| c.is_alphanumeric() || c == '-')
+        {
+            return None;
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return None;
+    }
+
+    Some(normalized)
+}
+
+// Browse public guilds with search, filters, sorting and pagination
+pub async fn get_public_guilds(
+    State(state): State<AppState>,
+    Query(params): Query<DiscoveryQuery>,
+) -> Result<Json<Vec<PublicGuildResponse>>, StatusCode> {
+    let sort = params.sort.as_deref().unwrap_or(SORT_MEMBERS);
+    if sort != SORT_MEMBERS && sort != SORT_ACTIVITY {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let pattern = params
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(like_pattern);
+    let category = params.category.map(|c| c.to_lowercase());
+    let tag = params.tag.map(|t| t.trim().to_lowercase());
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let guilds = sqlx::query!(
+        r#"
+        SELECT
+            g.id,
+            g.name,
+            g.description,
+            g.icon,
+            g.icon_url,
+            g.is_verified,
+            g.discovery_category,
+            g.tags,
+            g.last_activity_at,
+            COUNT(gm.user_id) as member_count
+        FROM guilds g
+        LEFT JOIN guild_members gm ON g.id = gm.guild_id
+        WHERE g.is_public = TRUE
+          AND ($1::text IS NULL OR g.name ILIKE $1 ESCAPE '\' OR g.description ILIKE $1 ESCAPE '\')
+          AND ($2::text IS NULL OR g.discovery_category = $2)
+          AND ($3::text IS NULL OR $3 = ANY(g.tags))
+          AND ($4::bool IS NULL OR COALESCE(g.is_verified, FALSE) = $4)
+        GROUP BY g.id
+        ORDER BY
+            CASE WHEN $5 = 'activity' THEN g.last_activity_at END DESC NULLS LAST,
+            g.is_verified DESC NULLS LAST,
+            COUNT(gm.user_id) DESC,
+            g.id
+        LIMIT $6 OFFSET $7
+        "#,
+        pattern,
+        category,
+        tag,
+        params.verified,
+        sort,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let response: Vec<PublicGuildResponse> = guilds
+        .into_iter()
+        .map(|g| PublicGuildResponse {
+            id: g.id.to_string(),
+            name: g.name,
+            description: g.description,
+            member_count: g.member_count.map(|c| c as i32),
+            icon: g.icon,
+            icon_url: g.icon_url,
+            is_verified: g.is_verified.unwrap_or(false),
+            category: g.discovery_category,
+            tags: g.tags,
+            last_activity_at: g.last_activity_at.map(|dt| dt.to_rfc3339()),
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+// Text channels of a public guild that are opted into previews and visible to @everyone
+async fn load_preview_channels(db: &sqlx::PgPool, guild_id: Uuid) -> Result<Vec<PreviewChannel>, StatusCode> {
+    let channels = sqlx::query!(
+        r#"
+        SELECT c.id, c.name, c.topic
+        FROM channels c
+        WHERE c.guild_id = $1
+          AND c.public_preview
+          AND COALESCE(c.channel_type, 'text') = 'text'
+          AND NOT COALESCE(c.nsfw, FALSE)
+          AND NOT EXISTS (
+              SELECT 1 FROM channel_permissions cp
+              WHERE cp.channel_id = c.id AND cp.role_id = $1 AND cp.allow_view = FALSE
+          )
+        ORDER BY c.position
+        "#,
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(channels
+        .into_iter()
+        .map(|c| PreviewChannel { id: c.id, name: c.name, topic: c.topic })
+        .collect())
+}
+
+// Read-only look at a public guild for people who haven't joined yet
+pub async fn get_guild_preview(
+    State(state): State<AppState>,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<GuildPreviewResponse>, StatusCode> {
+    let guild = sqlx::query!(
+        r#"
+        SELECT g.id, g.name, g.description, g.icon, g.icon_url, g.banner_url, g.is_verified,
+               g.discovery_category, g.tags, g.last_activity_at,
+               (SELECT COUNT(*) FROM guild_members gm WHERE gm.guild_id = g.id) as member_count
+        FROM guilds g
+        WHERE g.id = $1 AND g.is_public = TRUE
+        "#,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let channels = load_preview_channels(&state.db, guild_id).await?;
+
+    Ok(Json(GuildPreviewResponse {
+        guild: PublicGuildResponse {
+            id: guild.id.to_string(),
+            name: guild.name,
+            description: guild.description,
+            member_count: guild.member_count.map(|c| c as i32),
+            icon: guild.icon,
+            icon_url: guild.icon_url,
+            is_verified: guild.is_verified.unwrap_or(false),
+            category: guild.discovery_category,
+            tags: guild.tags,
+            last_activity_at: guild.last_activity_at.map(|dt| dt.to_rfc3339()),
+        },
+        banner_url: guild.banner_url,
+        channels,
+    }))
+}
+
+// Recent messages of a preview channel
+pub async fn get_preview_messages(
+    State(state): State<AppState>,
+    axum::extract::Path((guild_id, channel_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<Message>>, StatusCode> {
+    let is_public = sqlx::query_scalar!(
+        "SELECT is_public FROM guilds WHERE id = $1",
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .flatten()
+    .unwrap_or(false);
+
+    if !is_public {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let channels = load_preview_channels(&state.db, guild_id).await?;
+    if !channels.iter().any(|c| c.id == channel_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let messages = crate::handlers::messages::load_channel_messages(
+        &state.db,
+        &channel_id.to_string(),
+        Some(PREVIEW_MESSAGE_LIMIT),
+    )
+    .await?;
+
+    Ok(Json(messages))
+}
+
+async fn load_discovery_settings(db: &sqlx::PgPool, guild_id: Uuid) -> Result<DiscoverySettingsResponse, StatusCode> {
+    let guild = sqlx::query!(
+        "SELECT is_public, is_verified, discovery_category, tags FROM guilds WHERE id = $1",
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let preview_channel_ids = sqlx::query_scalar!(
+        "SELECT id FROM channels WHERE guild_id = $1 AND public_preview ORDER BY position",
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(DiscoverySettingsResponse {
+        guild_id,
+        is_public: guild.is_public.unwrap_or(false),
+        is_verified: guild.is_verified.unwrap_or(false),
+        category: guild.discovery_category,
+        tags: guild.tags,
+        preview_channel_ids,
+    })
+}
+
+// Get a guild's discovery listing settings
+pub async fn get_discovery_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<DiscoverySettingsResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(Json(load_discovery_settings(&state.db, guild_id).await?))
+}
+
+// Update category, tags and preview channels
+pub async fn update_discovery_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<UpdateDiscoverySettingsRequest>,
+) -> Result<Json<DiscoverySettingsResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let category = match payload.category {
+        Some(c) if c.trim().is_empty() => Some(None),
+        Some(c) => {
+            let c = c.trim().to_lowercase();
+            if !DISCOVERY_CATEGORIES.contains(&c.as_str()) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Some(Some(c))
+        }
+        None => None,
+    };
+
+    let tags = match &payload.tags {
+        Some(tags) => Some(normalize_tags(tags).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    let preview_channel_ids = match payload.preview_channel_ids {
+        Some(mut ids) => {
+            ids.sort();
+            ids.dedup();
+            if ids.len() > MAX_PREVIEW_CHANNELS {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            // Only text channels of this guild can be previewed
+            let found = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) as "count!" FROM channels WHERE guild_id = $1 AND id = ANY($2) AND COALESCE(channel_type, 'text') = 'text'"#,
+                guild_id,
+                &ids
+            )
+            .fetch_one(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            if found != ids.len() as i64 {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Some(ids)
+        }
+        None => None,
+    };
+
+    let before = load_discovery_settings(&state.db, guild_id).await?;
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(category) = category {
+        sqlx::query!(
+            "UPDATE guilds SET discovery_category = $1 WHERE id = $2",
+            category,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    if let Some(tags) = &tags {
+        sqlx::query!(
+            "UPDATE guilds SET tags = $1 WHERE id = $2",
+            tags,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    if let Some(ids) = &preview_channel_ids {
+        sqlx::query!(
+            "UPDATE channels SET public_preview = (id = ANY($1)) WHERE guild_id = $2",
+            ids,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let after = load_discovery_settings(&state.db, guild_id).await?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "guild_discovery_update",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "before": { "category": before.category, "tags": before.tags, "preview_channel_ids": before.preview_channel_ids },
+            "after": { "category": after.category, "tags": after.tags, "preview_channel_ids": after.preview_channel_ids },
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(after))
+}
@@ -203,7 +203,7 @@ pub async fn get_dm_messages(
     
     let attachments = if !message_ids.is_empty() {
         sqlx::query!(
-            "SELECT id, message_id, filename, file_url, file_type, file_size FROM dm_message_attachments WHERE message_id = ANY($1)",
+            "SELECT id, message_id, filename, file_url, file_type, file_size, width, height, duration_secs, placeholder, thumbnail_url FROM dm_message_attachments WHERE message_id = ANY($1)",
             &message_ids
         )
         .fetch_all(&state.db)
@@ -225,6 +225,11 @@ pub async fn get_dm_messages(
                     file_url: a.file_url.clone(),
                     file_type: a.file_type.clone(),
                     file_size: a.file_size,
+                    width: a.width,
+                    height: a.height,
+                    duration: a.duration_secs,
+                    placeholder: a.placeholder.clone(),
+                    thumbnail_url: a.thumbnail_url.clone(),
                 })
                 .collect();
 
@@ -286,18 +291,35 @@ pub async fn send_dm_message(
     if let Some(attachments) = payload.attachments {
         for attachment in attachments {
             let attachment_id = Uuid::new_v4();
+            let media = crate::handlers::files::attachment_media(&mut tx, &attachment.file_url, user_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
             sqlx::query!(
-                "INSERT INTO dm_message_attachments (id, message_id, filename, file_url, file_type, file_size) VALUES ($1, $2, $3, $4, $5, $6)",
+                r#"
+                INSERT INTO dm_message_attachments (id, message_id, filename, file_url, file_id, file_type, file_size, width, height, duration_secs, placeholder, thumbnail_url)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                "#,
                 attachment_id,
                 message_id,
                 attachment.filename,
                 attachment.file_url,
+                crate::handlers::files::file_id_from_url(&attachment.file_url),
                 attachment.file_type,
-                attachment.file_size
+                attachment.file_size,
+                media.width,
+                media.height,
+                media.duration_secs,
+                media.placeholder,
+                media.thumbnail_url
             )
             .execute(&mut *tx)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            crate::handlers::files::link_attachment(&mut tx, &attachment.file_url, user_id, message_id, None, Some(dm_uuid))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             
             saved_attachments.push(crate::models::Attachment {
                 id: attachment_id,
@@ -305,6 +327,11 @@ pub async fn send_dm_message(
                 file_url: attachment.file_url.clone(),
                 file_type: attachment.file_type.clone(),
                 file_size: attachment.file_size,
+                width: media.width,
+                height: media.height,
+                duration: media.duration_secs,
+                placeholder: media.placeholder,
+                thumbnail_url: media.thumbnail_url,
             });
         }
     }
@@ -338,6 +365,15 @@ pub async fn send_dm_message(
 
     // Broadcast DM notification to the recipient's user-specific channel
     let other_user_id = if dm.user1_id == user_id { dm.user2_id } else { dm.user1_id };
+
+    // The message is always delivered; `notify` says whether the recipient wants to be alerted.
+    // It's already saved, so a failed lookup falls back to alerting rather than failing the send.
+    let notify = crate::handlers::notifications::should_notify_dm(&state.db, other_user_id, dm_uuid)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load DM notification settings for {}: {:?}", other_user_id, e);
+            true
+        });
     
     let dm_notification = serde_json::json!({
         "type": "dm_message",
@@ -348,9 +384,23 @@ pub async fn send_dm_message(
         "content": payload.text,
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "attachments": saved_attachments,
+        "notify": notify,
     });
     
     let notification_str = dm_notification.to_string();
+
+    // An offline recipient who wants to be alerted gets a push notification instead
+    if notify && state.push.is_enabled() && !state.ws_state.is_user_connected(&other_user_id.to_string()).await {
+        let preview: String = payload.text.chars().take(crate::handlers::notifications::NOTIFICATION_PREVIEW_LENGTH).collect();
+        let _ = crate::push::enqueue(&state.db, other_user_id, serde_json::json!({
+            "type": "dm_message",
+            "dm_id": dm_id,
+            "message_id": message_id,
+            "author_id": user_id,
+            "author": user.username,
+            "content": preview,
+        })).await;
+    }
     
     // Broadcast to the recipient's user-specific channel for notifications
     let recipient_channel = format!("user-{}", other_user_id);
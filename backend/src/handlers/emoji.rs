@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
     Json,
 };
 use serde::{Deserialize, Serialize};
@@ -55,17 +55,23 @@ pub async fn get_guild_emoji(
     Ok(Json(emoji_list))
 }
 
+// Alphanumeric and underscores only, 2-32 chars
+pub fn is_valid_emoji_name(name: &str) -> bool {
+    name.chars().all(|c| c.is_alphanumeric() || c == '_') && name.len() >= 2 && name.len() <= 32
+}
+
 // Create custom emoji
 pub async fn create_emoji(
     State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    headers: HeaderMap,
     Path(guild_id): Path<String>,
     Json(payload): Json<CreateEmojiRequest>,
 ) -> Result<Json<CustomEmoji>, StatusCode> {
     let guild_uuid = uuid::Uuid::parse_str(&guild_id).map_err(|_| StatusCode::BAD_REQUEST)?;
     let user_uuid = uuid::Uuid::parse_str(&payload.created_by).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    // Validate emoji name (alphanumeric and underscores only, 2-32 chars)
-    if !payload.name.chars().all(|c| c.is_alphanumeric() || c == '_') || payload.name.len() < 2 || payload.name.len() > 32 {
+    if !is_valid_emoji_name(&payload.name) || payload.image_url.len() > crate::handlers::guilds::MAX_IMAGE_URL_LENGTH {
         return Err(StatusCode::BAD_REQUEST);
     }
 
@@ -87,6 +93,17 @@ pub async fn create_emoji(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_uuid,
+        uuid::Uuid::parse_str(&user_id).ok(),
+        "emoji_create",
+        Some("emoji"),
+        Some(emoji.id),
+        Some(serde_json::json!({ "emoji_name": emoji.name, "image_url": emoji.image_url })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(CustomEmoji {
         id: emoji.id.to_string(),
         guild_id: emoji.guild_id.to_string(),
@@ -100,19 +117,34 @@ pub async fn create_emoji(
 // Delete custom emoji
 pub async fn delete_emoji(
     State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    headers: HeaderMap,
     Path((guild_id, emoji_id)): Path<(String, String)>,
 ) -> Result<StatusCode, StatusCode> {
     let guild_uuid = uuid::Uuid::parse_str(&guild_id).map_err(|_| StatusCode::BAD_REQUEST)?;
     let emoji_uuid = uuid::Uuid::parse_str(&emoji_id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    sqlx::query!(
-        "DELETE FROM custom_emoji WHERE id = $1 AND guild_id = $2",
+    let deleted = sqlx::query!(
+        "DELETE FROM custom_emoji WHERE id = $1 AND guild_id = $2 RETURNING name, image_url",
         emoji_uuid,
         guild_uuid
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if let Some(emoji) = deleted {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_uuid,
+            uuid::Uuid::parse_str(&user_id).ok(),
+            "emoji_delete",
+            Some("emoji"),
+            Some(emoji_uuid),
+            Some(serde_json::json!({ "emoji_name": emoji.name, "image_url": emoji.image_url })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+    }
+
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,871 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, MANAGE_EVENTS};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+pub const EVENT_STATUS_SCHEDULED: &str = "scheduled";
+pub const EVENT_STATUS_ACTIVE: &str = "active";
+pub const EVENT_STATUS_COMPLETED: &str = "completed";
+pub const EVENT_STATUS_CANCELED: &str = "canceled";
+
+const ENTITY_TYPES: &[&str] = &["voice", "external"];
+const RECURRENCES: &[&str] = &["none", "daily", "weekly", "biweekly", "monthly"];
+const RSVP_STATUSES: &[&str] = &["going", "interested"];
+
+const MAX_TITLE_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+const MAX_LOCATION_LENGTH: usize = 100;
+const MAX_UPCOMING_EVENTS_PER_GUILD: i64 = 100;
+
+// Events without an end time are completed this long after they start
+const DEFAULT_EVENT_DURATION_MINUTES: i32 = 60;
+const SCHEDULER_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledEventResponse {
+    pub id: Uuid,
+    pub guild_id: Uuid,
+    pub creator_id: Option<Uuid>,
+    pub title: String,
+    pub description: Option<String>,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    pub entity_type: String,
+    pub channel_id: Option<Uuid>,
+    pub location: Option<String>,
+    pub recurrence: String,
+    pub status: String,
+    pub going_count: i64,
+    pub interested_count: i64,
+    // The requesting member's RSVP, if any
+    pub user_rsvp: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduledEventRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub entity_type: String,
+    pub channel_id: Option<Uuid>,
+    pub location: Option<String>,
+    pub recurrence: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateScheduledEventRequest {
+    pub title: Option<String>,
+    // An empty string clears the description
+    pub description: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub entity_type: Option<String>,
+    pub channel_id: Option<Uuid>,
+    pub location: Option<String>,
+    pub recurrence: Option<String>,
+    // Only "active" (start early) and "completed" (end now) can be set by hand
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RsvpRequest {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RsvpResponse {
+    pub user_id: Uuid,
+    pub username: String,
+    pub status: String,
+    pub created_at: String,
+}
+
+struct ScheduledEventRow {
+    id: Uuid,
+    guild_id: Uuid,
+    creator_id: Option<Uuid>,
+    title: String,
+    description: Option<String>,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    entity_type: String,
+    channel_id: Option<Uuid>,
+    location: Option<String>,
+    recurrence: String,
+    status: String,
+    going_count: i64,
+    interested_count: i64,
+    user_rsvp: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl ScheduledEventRow {
+    fn into_response(self) -> ScheduledEventResponse {
+        ScheduledEventResponse {
+            id: self.id,
+            guild_id: self.guild_id,
+            creator_id: self.creator_id,
+            title: self.title,
+            description: self.description,
+            start_time: self.start_time.to_rfc3339(),
+            end_time: self.end_time.map(|dt| dt.to_rfc3339()),
+            entity_type: self.entity_type,
+            channel_id: self.channel_id,
+            location: self.location,
+            recurrence: self.recurrence,
+            status: self.status,
+            going_count: self.going_count,
+            interested_count: self.interested_count,
+            user_rsvp: self.user_rsvp,
+            created_at: self.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+}
+
+// Where and when an event happens, after merging an update over the stored values
+struct EventDetails {
+    title: String,
+    description: Option<String>,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    entity_type: String,
+    channel_id: Option<Uuid>,
+    location: Option<String>,
+    recurrence: String,
+}
+
+async fn ensure_member(db: &sqlx::PgPool, guild_id: Uuid, user_id: Uuid) -> Result<(), StatusCode> {
+    let is_member = sqlx::query_scalar!(
+        "SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+        guild_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if is_member.is_none() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+async fn load_event(
+    db: &sqlx::PgPool,
+    guild_id: Uuid,
+    event_id: Uuid,
+    user_id: Option<Uuid>,
+) -> Result<Option<ScheduledEventRow>, StatusCode> {
+    sqlx::query_as!(
+        ScheduledEventRow,
+        r#"
+        SELECT e.id, e.guild_id, e.creator_id, e.title, e.description, e.start_time, e.end_time,
+               e.entity_type, e.channel_id, e.location, e.recurrence, e.status, e.created_at,
+               (SELECT COUNT(*) FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.status = 'going') as "going_count!",
+               (SELECT COUNT(*) FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.status = 'interested') as "interested_count!",
+               (SELECT r.status FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.user_id = $3) as "user_rsvp?"
+        FROM guild_scheduled_events e
+        WHERE e.id = $1 AND e.guild_id = $2
+        "#,
+        event_id,
+        guild_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Send an event to every member of its guild over the gateway
+async fn notify_guild(state: &AppState, guild_id: Uuid, payload: serde_json::Value) {
+    let members = sqlx::query_scalar!(
+        "SELECT user_id FROM guild_members WHERE guild_id = $1",
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for member_id in members {
+        state.ws_state.send_to_user(&member_id.to_string(), payload.clone()).await;
+    }
+}
+
+async fn broadcast_event(state: &AppState, guild_id: Uuid, event_id: Uuid, event_type: &str) {
+    if let Ok(Some(event)) = load_event(&state.db, guild_id, event_id, None).await {
+        notify_guild(state, guild_id, serde_json::json!({
+            "type": event_type,
+            "event": event.into_response(),
+        })).await;
+    }
+}
+
+async fn validate_event(db: &sqlx::PgPool, guild_id: Uuid, details: &EventDetails) -> Result<(), StatusCode> {
+    let title_length = details.title.trim().chars().count();
+    if title_length == 0 || title_length > MAX_TITLE_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(description) = &details.description {
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if !ENTITY_TYPES.contains(&details.entity_type.as_str()) || !RECURRENCES.contains(&details.recurrence.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(end_time) = details.end_time {
+        if end_time <= details.start_time {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if details.entity_type == "voice" {
+        let channel_id = details.channel_id.ok_or(StatusCode::BAD_REQUEST)?;
+        let is_voice_channel = sqlx::query_scalar!(
+            "SELECT 1 FROM channels WHERE id = $1 AND guild_id = $2 AND channel_type = 'voice'",
+            channel_id,
+            guild_id
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if is_voice_channel.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    } else {
+        // External events need somewhere to be and a time to be over
+        let location = details.location.as_deref().map(str::trim).unwrap_or("");
+        if location.is_empty() || location.chars().count() > MAX_LOCATION_LENGTH || details.end_time.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    Ok(())
+}
+
+// The start of the `n`th occurrence after the first one at `anchor`. Counted from the anchor
+// every time, so only the month in question is clamped to its last day.
+fn nth_occurrence(anchor: DateTime<Utc>, recurrence: &str, n: u32) -> Option<DateTime<Utc>> {
+    match recurrence {
+        "daily" => Some(anchor + chrono::Duration::days(n as i64)),
+        "weekly" => Some(anchor + chrono::Duration::weeks(n as i64)),
+        "biweekly" => Some(anchor + chrono::Duration::weeks(2 * n as i64)),
+        "monthly" => anchor.checked_add_months(chrono::Months::new(n)),
+        _ => None,
+    }
+}
+
+// End an event: one-off events are completed, recurring ones move on to their next
+// occurrence (keeping their RSVPs) and go back to scheduled. Only applies while the event is
+// still active at `start_time`; returns false if it was canceled or changed in the meantime.
+async fn finish_event(
+    db: impl sqlx::PgExecutor<'_>,
+    event_id: Uuid,
+    recurrence: &str,
+    recurrence_anchor: DateTime<Utc>,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+) -> Result<bool, sqlx::Error> {
+    let now = Utc::now();
+    let mut next = None;
+
+    for n in 1.. {
+        match nth_occurrence(recurrence_anchor, recurrence, n) {
+            Some(start) if start > start_time && start > now => {
+                next = Some(start);
+                break;
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    let result = if let Some(next_start) = next {
+        let next_end = end_time.map(|end| next_start + (end - start_time));
+        sqlx::query!(
+            "UPDATE guild_scheduled_events SET start_time = $1, end_time = $2, status = $3, updated_at = NOW() WHERE id = $4 AND status = $5 AND start_time = $6",
+            next_start,
+            next_end,
+            EVENT_STATUS_SCHEDULED,
+            event_id,
+            EVENT_STATUS_ACTIVE,
+            start_time
+        )
+        .execute(db)
+        .await?
+    } else {
+        sqlx::query!(
+            "UPDATE guild_scheduled_events SET status = $1, updated_at = NOW() WHERE id = $2 AND status = $3 AND start_time = $4",
+            EVENT_STATUS_COMPLETED,
+            event_id,
+            EVENT_STATUS_ACTIVE,
+            start_time
+        )
+        .execute(db)
+        .await?
+    };
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Move events along on their own: scheduled events become active once their start time
+// passes, and active events finish once their end time (or the default duration) passes
+pub fn spawn_event_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(SCHEDULER_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match sqlx::query!(
+                "UPDATE guild_scheduled_events SET status = $1, updated_at = NOW() WHERE status = $2 AND start_time <= NOW() RETURNING id, guild_id",
+                EVENT_STATUS_ACTIVE,
+                EVENT_STATUS_SCHEDULED
+            )
+            .fetch_all(&state.db)
+            .await
+            {
+                Ok(started) => {
+                    for event in started {
+                        broadcast_event(&state, event.guild_id, event.id, "guild_scheduled_event_update").await;
+                    }
+                }
+                Err(e) => eprintln!("Scheduled event start sweep failed: {:?}", e),
+            }
+
+            let ended = match sqlx::query!(
+                r#"
+                SELECT id, guild_id, recurrence, recurrence_anchor, start_time, end_time
+                FROM guild_scheduled_events
+                WHERE status = $1
+                  AND COALESCE(end_time, start_time + make_interval(mins => $2)) <= NOW()
+                "#,
+                EVENT_STATUS_ACTIVE,
+                DEFAULT_EVENT_DURATION_MINUTES
+            )
+            .fetch_all(&state.db)
+            .await
+            {
+                Ok(ended) => ended,
+                Err(e) => {
+                    eprintln!("Scheduled event end sweep failed: {:?}", e);
+                    continue;
+                }
+            };
+
+            for event in ended {
+                match finish_event(&state.db, event.id, &event.recurrence, event.recurrence_anchor, event.start_time, event.end_time).await {
+                    Ok(true) => {}
+                    // Canceled or edited since it was read
+                    Ok(false) => continue,
+                    Err(e) => {
+                        eprintln!("Failed to finish scheduled event {}: {:?}", event.id, e);
+                        continue;
+                    }
+                }
+                broadcast_event(&state, event.guild_id, event.id, "guild_scheduled_event_update").await;
+            }
+        }
+    });
+}
+
+// List a guild's upcoming and ongoing events
+pub async fn get_scheduled_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<ScheduledEventResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    ensure_member(&state.db, guild_id, user_id).await?;
+
+    let events = sqlx::query_as!(
+        ScheduledEventRow,
+        r#"
+        SELECT e.id, e.guild_id, e.creator_id, e.title, e.description, e.start_time, e.end_time,
+               e.entity_type, e.channel_id, e.location, e.recurrence, e.status, e.created_at,
+               (SELECT COUNT(*) FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.status = 'going') as "going_count!",
+               (SELECT COUNT(*) FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.status = 'interested') as "interested_count!",
+               (SELECT r.status FROM guild_scheduled_event_rsvps r WHERE r.event_id = e.id AND r.user_id = $2) as "user_rsvp?"
+        FROM guild_scheduled_events e
+        WHERE e.guild_id = $1 AND e.status IN ('scheduled', 'active')
+        ORDER BY e.start_time
+        "#,
+        guild_id,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(events.into_iter().map(ScheduledEventRow::into_response).collect()))
+}
+
+// Get a single event
+pub async fn get_scheduled_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<Json<ScheduledEventResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    ensure_member(&state.db, guild_id, user_id).await?;
+
+    let event = load_event(&state.db, guild_id, event_id, Some(user_id))
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(event.into_response()))
+}
+
+// Schedule a new event
+pub async fn create_scheduled_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateScheduledEventRequest>,
+) -> Result<Json<ScheduledEventResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_EVENTS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let details = EventDetails {
+        title: payload.title.trim().to_string(),
+        description: payload.description.filter(|d| !d.trim().is_empty()),
+        start_time: payload.start_time,
+        end_time: payload.end_time,
+        channel_id: if payload.entity_type == "voice" { payload.channel_id } else { None },
+        location: if payload.entity_type == "voice" { None } else { payload.location.map(|l| l.trim().to_string()) },
+        entity_type: payload.entity_type,
+        recurrence: payload.recurrence.unwrap_or_else(|| "none".to_string()),
+    };
+
+    validate_event(&state.db, guild_id, &details).await?;
+
+    if details.start_time <= Utc::now() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let upcoming = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM guild_scheduled_events WHERE guild_id = $1 AND status IN ('scheduled', 'active')"#,
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if upcoming >= MAX_UPCOMING_EVENTS_PER_GUILD {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let event_id = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO guild_scheduled_events
+            (id, guild_id, creator_id, title, description, start_time, end_time, entity_type, channel_id, location, recurrence, recurrence_anchor)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $6)
+        "#,
+        event_id,
+        guild_id,
+        user_id,
+        details.title,
+        details.description,
+        details.start_time,
+        details.end_time,
+        details.entity_type,
+        details.channel_id,
+        details.location,
+        details.recurrence
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "scheduled_event_create",
+        Some("scheduled_event"),
+        Some(event_id),
+        Some(serde_json::json!({
+            "title": details.title,
+            "start_time": details.start_time.to_rfc3339(),
+            "entity_type": details.entity_type,
+            "recurrence": details.recurrence,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    broadcast_event(&state, guild_id, event_id, "guild_scheduled_event_create").await;
+
+    let event = load_event(&state.db, guild_id, event_id, Some(user_id))
+        .await?
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(event.into_response()))
+}
+
+// Edit an event, or start/end it by hand
+pub async fn update_scheduled_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateScheduledEventRequest>,
+) -> Result<Json<ScheduledEventResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_EVENTS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let before = load_event(&state.db, guild_id, event_id, Some(user_id))
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Finished and canceled events are kept for reference only
+    if before.status != EVENT_STATUS_SCHEDULED && before.status != EVENT_STATUS_ACTIVE {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let entity_type = payload.entity_type.unwrap_or_else(|| before.entity_type.clone());
+    let is_voice = entity_type == "voice";
+    let details = EventDetails {
+        title: payload.title.map(|t| t.trim().to_string()).unwrap_or_else(|| before.title.clone()),
+        description: match payload.description {
+            Some(d) if d.trim().is_empty() => None,
+            Some(d) => Some(d),
+            None => before.description.clone(),
+        },
+        start_time: payload.start_time.unwrap_or(before.start_time),
+        end_time: payload.end_time.or(before.end_time),
+        channel_id: if is_voice { payload.channel_id.or(before.channel_id) } else { None },
+        location: if is_voice { None } else { payload.location.map(|l| l.trim().to_string()).or_else(|| before.location.clone()) },
+        entity_type,
+        recurrence: payload.recurrence.unwrap_or_else(|| before.recurrence.clone()),
+    };
+
+    validate_event(&state.db, guild_id, &details).await?;
+
+    // An event that hasn't started can only be moved into the future
+    if before.status == EVENT_STATUS_SCHEDULED && details.start_time != before.start_time && details.start_time <= Utc::now() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // An event that already started keeps its start time
+    if before.status == EVENT_STATUS_ACTIVE && details.start_time != before.start_time {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let status = match payload.status.as_deref() {
+        None => before.status.clone(),
+        Some(s) if s == before.status => before.status.clone(),
+        Some(EVENT_STATUS_ACTIVE) if before.status == EVENT_STATUS_SCHEDULED => EVENT_STATUS_ACTIVE.to_string(),
+        Some(EVENT_STATUS_COMPLETED) if before.status == EVENT_STATUS_ACTIVE => EVENT_STATUS_COMPLETED.to_string(),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Completing goes through finish_event below, which may roll the event over instead.
+    // The status check catches the scheduler having moved the event on in the meantime.
+    // Moving the event or changing how it repeats starts the recurrence over from the new time
+    let updated = sqlx::query_scalar!(
+        r#"
+        UPDATE guild_scheduled_events
+        SET title = $1, description = $2, start_time = $3, end_time = $4, entity_type = $5,
+            channel_id = $6, location = $7, recurrence = $8, status = $9, updated_at = NOW(),
+            recurrence_anchor = CASE WHEN start_time = $3 AND recurrence = $8::VARCHAR THEN recurrence_anchor ELSE $3 END
+        WHERE id = $10 AND status = $11
+        RETURNING recurrence_anchor
+        "#,
+        details.title,
+        details.description,
+        details.start_time,
+        details.end_time,
+        details.entity_type,
+        details.channel_id,
+        details.location,
+        details.recurrence,
+        if status == EVENT_STATUS_COMPLETED { &before.status } else { &status },
+        event_id,
+        before.status
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let recurrence_anchor = updated.ok_or(StatusCode::CONFLICT)?;
+
+    if status != before.status && status == EVENT_STATUS_COMPLETED {
+        let finished = finish_event(&mut *tx, event_id, &details.recurrence, recurrence_anchor, details.start_time, details.end_time)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if !finished {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let after = load_event(&state.db, guild_id, event_id, Some(user_id))
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "scheduled_event_update",
+        Some("scheduled_event"),
+        Some(event_id),
+        Some(serde_json::json!({
+            "before": {
+                "title": before.title,
+                "start_time": before.start_time.to_rfc3339(),
+                "end_time": before.end_time.map(|dt| dt.to_rfc3339()),
+                "entity_type": before.entity_type,
+                "recurrence": before.recurrence,
+                "status": before.status,
+            },
+            "after": {
+                "title": after.title,
+                "start_time": after.start_time.to_rfc3339(),
+                "end_time": after.end_time.map(|dt| dt.to_rfc3339()),
+                "entity_type": after.entity_type,
+                "recurrence": after.recurrence,
+                "status": after.status,
+            },
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    broadcast_event(&state, guild_id, event_id, "guild_scheduled_event_update").await;
+
+    Ok(Json(after.into_response()))
+}
+
+// Cancel an event (and, for recurring events, the rest of the series)
+pub async fn cancel_scheduled_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_EVENTS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let event = sqlx::query!(
+        r#"
+        UPDATE guild_scheduled_events SET status = $1, updated_at = NOW()
+        WHERE id = $2 AND guild_id = $3 AND status IN ('scheduled', 'active')
+        RETURNING title
+        "#,
+        EVENT_STATUS_CANCELED,
+        event_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "scheduled_event_cancel",
+        Some("scheduled_event"),
+        Some(event_id),
+        Some(serde_json::json!({ "title": event.title })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    broadcast_event(&state, guild_id, event_id, "guild_scheduled_event_update").await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// List who is going to or interested in an event
+pub async fn get_event_rsvps(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<RsvpResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    ensure_member(&state.db, guild_id, user_id).await?;
+
+    let rsvps = sqlx::query!(
+        r#"
+        SELECT r.user_id, u.username, r.status, r.created_at
+        FROM guild_scheduled_event_rsvps r
+        JOIN guild_scheduled_events e ON e.id = r.event_id
+        JOIN users u ON u.id = r.user_id
+        WHERE r.event_id = $1 AND e.guild_id = $2
+        ORDER BY r.status, r.created_at
+        "#,
+        event_id,
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rsvps.into_iter().map(|r| RsvpResponse {
+        user_id: r.user_id,
+        username: r.username,
+        status: r.status,
+        created_at: r.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+    }).collect()))
+}
+
+// RSVP to an event as "going" or "interested" (the default)
+pub async fn rsvp_scheduled_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<RsvpRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    ensure_member(&state.db, guild_id, user_id).await?;
+
+    let status = payload.status.unwrap_or_else(|| "interested".to_string());
+    if !RSVP_STATUSES.contains(&status.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let event = load_event(&state.db, guild_id, event_id, None)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if event.status != EVENT_STATUS_SCHEDULED && event.status != EVENT_STATUS_ACTIVE {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO guild_scheduled_event_rsvps (event_id, user_id, status)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (event_id, user_id) DO UPDATE SET status = EXCLUDED.status
+        "#,
+        event_id,
+        user_id,
+        status
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    notify_guild(&state, guild_id, serde_json::json!({
+        "type": "guild_scheduled_event_rsvp_add",
+        "guild_id": guild_id,
+        "event_id": event_id,
+        "user_id": user_id,
+        "status": status,
+    })).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Withdraw an RSVP
+pub async fn remove_event_rsvp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, event_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM guild_scheduled_event_rsvps r
+        USING guild_scheduled_events e
+        WHERE r.event_id = e.id AND r.event_id = $1 AND e.guild_id = $2 AND r.user_id = $3
+        "#,
+        event_id,
+        guild_id,
+        user_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    notify_guild(&state, guild_id, serde_json::json!({
+        "type": "guild_scheduled_event_rsvp_remove",
+        "guild_id": guild_id,
+        "event_id": event_id,
+        "user_id": user_id,
+    })).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monthly_occurrences_keep_their_day_of_month() {
+        let anchor = "2027-01-31T18:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let occurrences: Vec<String> = (1..=3)
+            .map(|n| nth_occurrence(anchor, "monthly", n).unwrap().format("%Y-%m-%d").to_string())
+            .collect();
+        assert_eq!(occurrences, ["2027-02-28", "2027-03-31", "2027-04-30"]);
+    }
+
+    #[test]
+    fn one_off_events_have_no_next_occurrence() {
+        let anchor = "2027-01-31T18:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(nth_occurrence(anchor, "none", 1), None);
+        assert_eq!(nth_occurrence(anchor, "biweekly", 2), Some(anchor + chrono::Duration::weeks(4)));
+    }
+}
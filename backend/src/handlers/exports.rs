@@ -0,0 +1,1562 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Multipart, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, ADMINISTRATOR};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+pub const ARCHIVE_FORMAT: &str = "wryft-guild-export";
+// Bump when the archive layout changes; imports accept this version and older ones
+pub const ARCHIVE_VERSION: i32 = 1;
+
+// Upload limit for imports (compressed), and how far an archive may inflate
+pub const MAX_ARCHIVE_BYTES: usize = 50 * 1024 * 1024;
+const MAX_INFLATED_ARCHIVE_BYTES: u64 = 500 * 1024 * 1024;
+
+const MAX_EXPORT_MESSAGES_PER_CHANNEL: i64 = 50_000;
+const MAX_IMPORT_ROLES: usize = 250;
+const MAX_IMPORT_CHANNELS: usize = 500;
+const MAX_IMPORT_EMOJI: usize = 200;
+// Imports take at most what an export of one guild can contain
+const MAX_IMPORT_MESSAGES: usize = 250_000;
+const MAX_IMPORT_ATTACHMENTS_PER_MESSAGE: usize = 10;
+const MAX_GUILD_NAME_LENGTH: usize = 100;
+// Imported messages are inserted this many per transaction
+const IMPORT_MESSAGE_CHUNK: usize = 1000;
+
+// Export and import jobs
+const JOB_PENDING: &str = "pending";
+const JOB_RUNNING: &str = "running";
+const JOB_COMPLETED: &str = "completed";
+const JOB_FAILED: &str = "failed";
+
+// Everything needed to recreate a guild. Ids are the source guild's; imports remap all of
+// them. Attachment and emoji URLs are kept as-is and only resolve where the files live.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GuildArchive {
+    pub format: String,
+    pub version: i32,
+    pub exported_at: String,
+    pub source_guild_id: Uuid,
+    pub guild: ArchiveGuild,
+    pub roles: Vec<ArchiveRole>,
+    pub categories: Vec<ArchiveCategory>,
+    pub channels: Vec<ArchiveChannel>,
+    pub emoji: Vec<ArchiveEmoji>,
+    #[serde(default)]
+    pub messages: Option<Vec<ArchiveMessage>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveGuild {
+    pub name: String,
+    pub icon: String,
+    pub icon_url: Option<String>,
+    pub banner_url: Option<String>,
+    pub description: Option<String>,
+    pub verification_level: i16,
+    pub system_channel_id: Option<Uuid>,
+    pub join_notifications: bool,
+    pub leave_notifications: bool,
+    pub boost_notifications: bool,
+    pub pin_notifications: bool,
+    pub welcome_message: Option<String>,
+    pub discovery_category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveRole {
+    pub id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub position: i32,
+    pub permissions: i64,
+    pub mentionable: bool,
+    pub hoist: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveCategory {
+    pub id: Uuid,
+    pub name: String,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveChannel {
+    pub id: Uuid,
+    pub name: String,
+    pub channel_type: String,
+    pub position: i32,
+    pub category_id: Option<Uuid>,
+    pub topic: Option<String>,
+    pub nsfw: bool,
+    pub slowmode_seconds: i32,
+    pub public_preview: bool,
+    pub permission_overwrites: Vec<ArchiveOverwrite>,
+    // Older messages left out because the channel had more than an export holds
+    #[serde(default)]
+    pub omitted_messages: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveOverwrite {
+    pub role_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub allow_view: Option<bool>,
+    pub allow_send_messages: Option<bool>,
+    pub allow_manage_messages: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveEmoji {
+    pub name: String,
+    pub image_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub author_id: Option<Uuid>,
+    pub author: String,
+    pub author_discriminator: Option<String>,
+    pub text: String,
+    pub timestamp: String,
+    pub created_at: Option<String>,
+    pub edited_at: Option<String>,
+    pub message_type: String,
+    pub reference_message_id: Option<Uuid>,
+    pub pinned_at: Option<String>,
+    pub pinned_by: Option<Uuid>,
+    pub attachments: Vec<ArchiveAttachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveAttachment {
+    pub filename: String,
+    pub file_url: String,
+    pub file_type: Option<String>,
+    pub file_size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateExportRequest {
+    #[serde(default)]
+    pub include_messages: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuildExportResponse {
+    pub id: Uuid,
+    pub guild_id: Uuid,
+    pub requested_by: Uuid,
+    pub include_messages: bool,
+    pub format_version: i32,
+    pub status: String,
+    pub size_bytes: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuildImportResponse {
+    pub id: Uuid,
+    pub status: String,
+    // The new guild, once the import has completed
+    pub guild_id: Option<Uuid>,
+    pub messages_imported: i32,
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+struct ExportRow {
+    id: Uuid,
+    guild_id: Uuid,
+    requested_by: Uuid,
+    include_messages: bool,
+    format_version: i32,
+    status: String,
+    storage_key: Option<String>,
+    size_bytes: Option<i64>,
+    error: Option<String>,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ExportRow {
+    fn into_response(self) -> GuildExportResponse {
+        GuildExportResponse {
+            id: self.id,
+            guild_id: self.guild_id,
+            requested_by: self.requested_by,
+            include_messages: self.include_messages,
+            format_version: self.format_version,
+            status: self.status,
+            size_bytes: self.size_bytes,
+            error: self.error,
+            created_at: self.created_at.map(|dt| dt.to_rfc3339()),
+            completed_at: self.completed_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+struct ImportRow {
+    id: Uuid,
+    status: String,
+    guild_id: Option<Uuid>,
+    messages_imported: i32,
+    error: Option<String>,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ImportRow {
+    fn into_response(self) -> GuildImportResponse {
+        GuildImportResponse {
+            id: self.id,
+            // Imports that are still running already have a guild, but it isn't complete yet
+            guild_id: self.guild_id.filter(|_| self.status == JOB_COMPLETED),
+            status: self.status,
+            messages_imported: self.messages_imported,
+            error: self.error,
+            created_at: self.created_at.map(|dt| dt.to_rfc3339()),
+            completed_at: self.completed_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+// Exports contain the whole guild layout; message history additionally needs the owner
+async fn check_export_access(state: &AppState, user_id: Uuid, guild_id: Uuid, include_messages: bool) -> Result<(), StatusCode> {
+    if !has_permission(state, user_id, guild_id, ADMINISTRATOR).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if include_messages {
+        let owner_id = sqlx::query_scalar!("SELECT owner_id FROM guilds WHERE id = $1", guild_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+        if owner_id != user_id {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_export(db: &sqlx::PgPool, guild_id: Uuid, export_id: Uuid) -> Result<ExportRow, StatusCode> {
+    sqlx::query_as!(
+        ExportRow,
+        r#"
+        SELECT id, guild_id, requested_by, include_messages, format_version, status, storage_key, size_bytes, error, created_at, completed_at
+        FROM guild_exports
+        WHERE id = $1 AND guild_id = $2
+        "#,
+        export_id,
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Serialise a guild into an archive
+async fn build_archive(db: &sqlx::PgPool, guild_id: Uuid, include_messages: bool) -> Result<GuildArchive, Box<dyn std::error::Error>> {
+    let guild = sqlx::query!(
+        r#"
+        SELECT name, icon, icon_url, banner_url, description, verification_level, system_channel_id,
+               join_notifications, leave_notifications, boost_notifications, pin_notifications,
+               welcome_message, discovery_category, tags
+        FROM guilds
+        WHERE id = $1
+        "#,
+        guild_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    let roles = sqlx::query!(
+        "SELECT id, name, color, position, permissions, mentionable, hoist FROM roles WHERE guild_id = $1 ORDER BY position, created_at",
+        guild_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|r| ArchiveRole {
+        id: r.id,
+        name: r.name,
+        color: r.color,
+        position: r.position,
+        permissions: r.permissions,
+        mentionable: r.mentionable.unwrap_or(true),
+        hoist: r.hoist.unwrap_or(false),
+    })
+    .collect();
+
+    let categories = sqlx::query!(
+        "SELECT id, name, position FROM channel_categories WHERE guild_id = $1 ORDER BY position, created_at",
+        guild_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|c| ArchiveCategory {
+        id: c.id,
+        name: c.name,
+        position: c.position,
+    })
+    .collect();
+
+    let channels = sqlx::query!(
+        r#"
+        SELECT id, name, channel_type, position, category_id, topic, nsfw, slowmode_seconds, public_preview
+        FROM channels
+        WHERE guild_id = $1
+        ORDER BY position, created_at
+        "#,
+        guild_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let channel_ids: Vec<Uuid> = channels.iter().map(|c| c.id).collect();
+
+    let overwrites = sqlx::query!(
+        r#"
+        SELECT channel_id, role_id, user_id, allow_view, allow_send_messages, allow_manage_messages
+        FROM channel_permissions
+        WHERE channel_id = ANY($1)
+        "#,
+        &channel_ids
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut channels: Vec<ArchiveChannel> = channels
+        .into_iter()
+        .map(|c| ArchiveChannel {
+            permission_overwrites: overwrites
+                .iter()
+                .filter(|o| o.channel_id == c.id)
+                .map(|o| ArchiveOverwrite {
+                    role_id: o.role_id,
+                    user_id: o.user_id,
+                    allow_view: o.allow_view,
+                    allow_send_messages: o.allow_send_messages,
+                    allow_manage_messages: o.allow_manage_messages,
+                })
+                .collect(),
+            id: c.id,
+            name: c.name,
+            channel_type: c.channel_type.unwrap_or_else(|| "text".to_string()),
+            position: c.position.unwrap_or(0),
+            category_id: c.category_id,
+            topic: c.topic,
+            nsfw: c.nsfw.unwrap_or(false),
+            slowmode_seconds: c.slowmode_seconds.unwrap_or(0),
+            public_preview: c.public_preview,
+            omitted_messages: 0,
+        })
+        .collect();
+
+    let emoji = sqlx::query!(
+        "SELECT name, image_url FROM custom_emoji WHERE guild_id = $1 ORDER BY created_at",
+        guild_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|e| ArchiveEmoji {
+        name: e.name,
+        image_url: e.image_url,
+    })
+    .collect();
+
+    let messages = if include_messages {
+        let mut messages = Vec::new();
+
+        for channel in channels.iter_mut() {
+            let channel_id = &channel.id;
+
+            // The most recent messages, oldest first
+            let mut rows = sqlx::query!(
+                r#"
+                SELECT id, author_id, author, author_discriminator, text, timestamp, created_at, edited_at,
+                       message_type, reference_message_id, pinned_at, pinned_by
+                FROM messages
+                WHERE channel = $1 AND deleted = false
+                ORDER BY created_at DESC
+                LIMIT $2
+                "#,
+                channel_id.to_string(),
+                MAX_EXPORT_MESSAGES_PER_CHANNEL
+            )
+            .fetch_all(db)
+            .await?;
+            rows.reverse();
+
+            if messages.len() + rows.len() > MAX_IMPORT_MESSAGES {
+                return Err(format!(
+                    "The guild has more than {} messages, more than an import accepts; export it without messages",
+                    MAX_IMPORT_MESSAGES
+                )
+                .into());
+            }
+
+            if rows.len() as i64 == MAX_EXPORT_MESSAGES_PER_CHANNEL {
+                let total = sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) AS "count!" FROM messages WHERE channel = $1 AND deleted = false"#,
+                    channel_id.to_string()
+                )
+                .fetch_one(db)
+                .await?;
+                channel.omitted_messages = total - MAX_EXPORT_MESSAGES_PER_CHANNEL;
+            }
+
+            let message_ids: Vec<Uuid> = rows.iter().map(|m| m.id).collect();
+            let mut attachments: HashMap<Uuid, Vec<ArchiveAttachment>> = HashMap::new();
+            for a in sqlx::query!(
+                "SELECT message_id, filename, file_url, file_type, file_size FROM message_attachments WHERE message_id = ANY($1) ORDER BY created_at",
+                &message_ids
+            )
+            .fetch_all(db)
+            .await?
+            {
+                attachments.entry(a.message_id).or_default().push(ArchiveAttachment {
+                    filename: a.filename,
+                    file_url: a.file_url,
+                    file_type: a.file_type,
+                    file_size: a.file_size,
+                });
+            }
+
+            messages.extend(rows.into_iter().map(|m| ArchiveMessage {
+                attachments: attachments.remove(&m.id).unwrap_or_default(),
+                id: m.id,
+                channel_id: *channel_id,
+                author_id: m.author_id,
+                author: m.author,
+                author_discriminator: m.author_discriminator,
+                text: m.text,
+                timestamp: m.timestamp,
+                created_at: m.created_at.map(|dt| dt.to_rfc3339()),
+                edited_at: m.edited_at.map(|dt| dt.to_rfc3339()),
+                message_type: m.message_type,
+                reference_message_id: m.reference_message_id,
+                pinned_at: m.pinned_at.map(|dt| dt.to_rfc3339()),
+                pinned_by: m.pinned_by,
+            }));
+        }
+
+        Some(messages)
+    } else {
+        None
+    };
+
+    Ok(GuildArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        source_guild_id: guild_id,
+        guild: ArchiveGuild {
+            name: guild.name,
+            icon: guild.icon,
+            icon_url: guild.icon_url,
+            banner_url: guild.banner_url,
+            description: guild.description,
+            verification_level: guild.verification_level,
+            system_channel_id: guild.system_channel_id,
+            join_notifications: guild.join_notifications,
+            leave_notifications: guild.leave_notifications,
+            boost_notifications: guild.boost_notifications,
+            pin_notifications: guild.pin_notifications,
+            welcome_message: guild.welcome_message,
+            discovery_category: guild.discovery_category,
+            tags: guild.tags,
+        },
+        roles,
+        categories,
+        channels,
+        emoji,
+        messages,
+    })
+}
+
+// Counts what goes through it, so exports can tell how far they would inflate on import
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Build the archive, gzip it and put it in storage. Archives an import would reject fail
+// the export instead.
+async fn run_export(state: &AppState, export_id: Uuid, guild_id: Uuid, include_messages: bool) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let archive = build_archive(&state.db, guild_id, include_messages).await?;
+
+    let mut writer = CountingWriter {
+        inner: GzEncoder::new(Vec::new(), Compression::default()),
+        written: 0,
+    };
+    serde_json::to_writer(&mut writer, &archive)?;
+    if writer.written > MAX_INFLATED_ARCHIVE_BYTES {
+        return Err(format!(
+            "The archive would be {} MB uncompressed, more than the {} MB an import accepts",
+            writer.written / (1024 * 1024),
+            MAX_INFLATED_ARCHIVE_BYTES / (1024 * 1024)
+        )
+        .into());
+    }
+
+    let data = writer.inner.finish()?;
+    let size = data.len();
+    if size > MAX_ARCHIVE_BYTES {
+        return Err(format!(
+            "The archive would be {} MB, more than the {} MB an import accepts",
+            size / (1024 * 1024),
+            MAX_ARCHIVE_BYTES / (1024 * 1024)
+        )
+        .into());
+    }
+
+    let key = format!("exports/{}/{}.json.gz", guild_id, export_id);
+    let key = state.storage.upload_file(&key, data.into(), "application/gzip").await?;
+
+    Ok((key, size))
+}
+
+fn spawn_export(state: AppState, export_id: Uuid, guild_id: Uuid, include_messages: bool) {
+    tokio::spawn(async move {
+        let _ = sqlx::query!(
+            "UPDATE guild_exports SET status = $1 WHERE id = $2",
+            JOB_RUNNING,
+            export_id
+        )
+        .execute(&state.db)
+        .await;
+
+        // Storage errors aren't Send; keep only the message
+        let result = run_export(&state, export_id, guild_id, include_messages).await.map_err(|e| e.to_string());
+
+        match result {
+            Ok((key, size)) => {
+                println!("📦 Guild {} exported to {} ({} bytes)", guild_id, key, size);
+                let _ = sqlx::query!(
+                    "UPDATE guild_exports SET status = $1, storage_key = $2, size_bytes = $3, completed_at = NOW() WHERE id = $4",
+                    JOB_COMPLETED,
+                    key,
+                    size as i64,
+                    export_id
+                )
+                .execute(&state.db)
+                .await;
+            }
+            Err(e) => {
+                eprintln!("❌ Export of guild {} failed: {}", guild_id, e);
+                let _ = sqlx::query!(
+                    "UPDATE guild_exports SET status = $1, error = $2, completed_at = NOW() WHERE id = $3",
+                    JOB_FAILED,
+                    e,
+                    export_id
+                )
+                .execute(&state.db)
+                .await;
+            }
+        }
+    });
+}
+
+// Jobs don't survive a restart; mark the ones that were in flight as failed, and remove
+// the guilds that interrupted imports had started to create
+pub async fn fail_interrupted_jobs(db: &sqlx::PgPool) {
+    let _ = sqlx::query!(
+        "UPDATE guild_exports SET status = $1, error = 'Interrupted by server restart', completed_at = NOW() WHERE status IN ($2, $3)",
+        JOB_FAILED,
+        JOB_PENDING,
+        JOB_RUNNING
+    )
+    .execute(db)
+    .await;
+
+    let interrupted = sqlx::query_scalar!(
+        "SELECT guild_id FROM guild_imports WHERE status IN ($1, $2) AND guild_id IS NOT NULL",
+        JOB_PENDING,
+        JOB_RUNNING
+    )
+    .fetch_all(db)
+    .await
+    .unwrap_or_default();
+
+    for guild_id in interrupted.into_iter().flatten() {
+        discard_imported_guild(db, guild_id).await;
+    }
+
+    let _ = sqlx::query!(
+        "UPDATE guild_imports SET status = $1, guild_id = NULL, error = 'Interrupted by server restart', completed_at = NOW() WHERE status IN ($2, $3)",
+        JOB_FAILED,
+        JOB_PENDING,
+        JOB_RUNNING
+    )
+    .execute(db)
+    .await;
+}
+
+// Start an export job for a guild
+pub async fn create_guild_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateExportRequest>,
+) -> Result<(StatusCode, Json<GuildExportResponse>), StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_export_access(&state, user_id, guild_id, payload.include_messages).await?;
+
+    // One export at a time per guild
+    let in_progress = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM guild_exports WHERE guild_id = $1 AND status IN ($2, $3)) as "exists!""#,
+        guild_id,
+        JOB_PENDING,
+        JOB_RUNNING
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if in_progress {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let export = sqlx::query_as!(
+        ExportRow,
+        r#"
+        INSERT INTO guild_exports (id, guild_id, requested_by, include_messages, format_version, status)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, guild_id, requested_by, include_messages, format_version, status, storage_key, size_bytes, error, created_at, completed_at
+        "#,
+        Uuid::new_v4(),
+        guild_id,
+        user_id,
+        payload.include_messages,
+        ARCHIVE_VERSION,
+        JOB_PENDING
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "guild_export",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "export_id": export.id,
+            "include_messages": payload.include_messages,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    spawn_export(state.clone(), export.id, guild_id, payload.include_messages);
+
+    Ok((StatusCode::ACCEPTED, Json(export.into_response())))
+}
+
+// List a guild's exports
+pub async fn get_guild_exports(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<GuildExportResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_export_access(&state, user_id, guild_id, false).await?;
+
+    let exports = sqlx::query_as!(
+        ExportRow,
+        r#"
+        SELECT id, guild_id, requested_by, include_messages, format_version, status, storage_key, size_bytes, error, created_at, completed_at
+        FROM guild_exports
+        WHERE guild_id = $1
+        ORDER BY created_at DESC
+        "#,
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(exports.into_iter().map(|e| e.into_response()).collect()))
+}
+
+// Poll an export job
+pub async fn get_guild_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, export_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<Json<GuildExportResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_export_access(&state, user_id, guild_id, false).await?;
+
+    Ok(Json(fetch_export(&state.db, guild_id, export_id).await?.into_response()))
+}
+
+// Download a finished archive
+pub async fn download_guild_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, export_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<Response, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let export = fetch_export(&state.db, guild_id, export_id).await?;
+
+    check_export_access(&state, user_id, guild_id, export.include_messages).await?;
+
+    let key = match (export.status.as_str(), export.storage_key) {
+        (JOB_COMPLETED, Some(key)) => key,
+        _ => return Err(StatusCode::CONFLICT),
+    };
+
+    let data = state.storage
+        .get_file(&key)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to get export from S3: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/gzip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"guild-{}-{}.json.gz\"", guild_id, export_id),
+        )
+        .body(Body::from(data))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Archives may be uploaded gzipped (as exported) or as plain JSON
+fn archive_reader(data: &[u8]) -> Box<dyn Read + '_> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(InflateLimit {
+            inner: GzDecoder::new(data),
+            remaining: MAX_INFLATED_ARCHIVE_BYTES,
+        })
+    } else {
+        Box::new(data)
+    }
+}
+
+// Fails the read once an archive inflates further than imports accept
+struct InflateLimit<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for InflateLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.remaining = self.remaining.checked_sub(read as u64).ok_or_else(|| {
+            std::io::Error::other(format!(
+                "The archive is more than {} MB uncompressed",
+                MAX_INFLATED_ARCHIVE_BYTES / (1024 * 1024)
+            ))
+        })?;
+        Ok(read)
+    }
+}
+
+// Read an archive without keeping its messages: each one is handed to `on_message` as it is
+// parsed, and the rest of the archive is returned with `messages` left empty
+fn read_archive(data: &[u8], on_message: impl FnMut(ArchiveMessage) -> Result<(), String>) -> Result<GuildArchive, String> {
+    let mut deserializer = serde_json::Deserializer::from_reader(archive_reader(data));
+    let layout = ArchiveSeed { on_message }
+        .deserialize(&mut deserializer)
+        .and_then(|layout| deserializer.end().map(|_| layout))
+        .map_err(|e| if e.is_io() { e.to_string() } else { format!("The archive isn't a valid guild export: {}", e) })?;
+
+    let archive: GuildArchive = serde_json::from_value(serde_json::Value::Object(layout))
+        .map_err(|e| format!("The archive isn't a valid guild export: {}", e))?;
+
+    if archive.format != ARCHIVE_FORMAT || archive.version < 1 || archive.version > ARCHIVE_VERSION {
+        return Err("The archive isn't a guild export this server can import".to_string());
+    }
+
+    Ok(archive)
+}
+
+// Collects the top-level fields of an archive, except for messages which go to `on_message`
+struct ArchiveSeed<F> {
+    on_message: F,
+}
+
+impl<'de, F: FnMut(ArchiveMessage) -> Result<(), String>> DeserializeSeed<'de> for ArchiveSeed<F> {
+    type Value = serde_json::Map<String, serde_json::Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(ArchiveMessage) -> Result<(), String>> Visitor<'de> for ArchiveSeed<F> {
+    type Value = serde_json::Map<String, serde_json::Value>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a guild archive")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut layout = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "messages" {
+                map.next_value_seed(MessagesSeed { on_message: &mut self.on_message })?;
+            } else {
+                layout.insert(key, map.next_value()?);
+            }
+        }
+        Ok(layout)
+    }
+}
+
+struct MessagesSeed<'a, F> {
+    on_message: &'a mut F,
+}
+
+impl<'de, F: FnMut(ArchiveMessage) -> Result<(), String>> DeserializeSeed<'de> for MessagesSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de, F: FnMut(ArchiveMessage) -> Result<(), String>> Visitor<'de> for MessagesSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a list of messages")
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(message) = seq.next_element::<ArchiveMessage>()? {
+            (self.on_message)(message).map_err(serde::de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+// Reject archives that couldn't have come out of a guild on this server, holding them to
+// the rules of the endpoints that set the same things. Tags are normalised like discovery
+// settings are.
+fn validate_archive(archive: &mut GuildArchive) -> Result<(), String> {
+    use crate::handlers::channels::{CHANNEL_TYPES, MAX_SLOWMODE_SECONDS, MAX_TOPIC_LENGTH};
+    use crate::handlers::guilds::{MAX_DESCRIPTION_LENGTH, MAX_IMAGE_URL_LENGTH};
+    use crate::handlers::screening::{VERIFICATION_NONE, VERIFICATION_MEMBER_AGE};
+
+    let guild = &archive.guild;
+    let valid = archive.roles.len() <= MAX_IMPORT_ROLES
+        && archive.channels.len() <= MAX_IMPORT_CHANNELS
+        && archive.emoji.len() <= MAX_IMPORT_EMOJI
+        && (VERIFICATION_NONE..=VERIFICATION_MEMBER_AGE).contains(&guild.verification_level)
+        && guild.icon.chars().count() <= 10
+        && guild.description.as_ref().is_none_or(|d| d.chars().count() <= MAX_DESCRIPTION_LENGTH)
+        && guild.icon_url.as_ref().is_none_or(|url| url.len() <= MAX_IMAGE_URL_LENGTH)
+        && guild.banner_url.as_ref().is_none_or(|url| url.len() <= MAX_IMAGE_URL_LENGTH)
+        && guild.welcome_message.as_ref().is_none_or(|m| {
+            m.chars().count() <= crate::handlers::system_messages::MAX_WELCOME_MESSAGE_LENGTH
+        })
+        && guild.discovery_category.as_ref().is_none_or(|c| {
+            crate::handlers::discovery::DISCOVERY_CATEGORIES.contains(&c.as_str())
+        })
+        && archive.roles.iter().all(|r| !r.name.is_empty() && r.name.len() <= 100 && r.color.as_ref().is_none_or(|c| c.len() <= 7))
+        && archive.categories.iter().all(|c| !c.name.is_empty() && c.name.len() <= 255)
+        && archive.channels.iter().all(|c| {
+            !c.name.is_empty()
+                && c.name.len() <= 255
+                && CHANNEL_TYPES.contains(&c.channel_type.as_str())
+                && c.topic.as_ref().is_none_or(|t| t.len() <= MAX_TOPIC_LENGTH)
+                && (0..=MAX_SLOWMODE_SECONDS).contains(&c.slowmode_seconds)
+        })
+        && archive.emoji.iter().all(|e| {
+            crate::handlers::emoji::is_valid_emoji_name(&e.name) && e.image_url.len() <= MAX_IMAGE_URL_LENGTH
+        });
+
+    if !valid {
+        return Err("The archive has guild settings, roles, channels or emoji this server doesn't accept".to_string());
+    }
+
+    archive.guild.tags = crate::handlers::discovery::normalize_tags(&archive.guild.tags)
+        .ok_or_else(|| "The archive has discovery tags this server doesn't accept".to_string())?;
+
+    // Ids are remapped through maps keyed by them, so each has to be unique. Only the
+    // @everyone role may use the guild's id.
+    let unique = has_unique_ids(archive.roles.iter().map(|r| r.id))
+        && has_unique_ids(archive.categories.iter().map(|c| c.id))
+        && has_unique_ids(archive.channels.iter().map(|c| c.id))
+        && !archive.categories.iter().any(|c| c.id == archive.source_guild_id)
+        && !archive.channels.iter().any(|c| c.id == archive.source_guild_id);
+
+    if !unique {
+        return Err("The archive has duplicate ids".to_string());
+    }
+
+    Ok(())
+}
+
+// Messages are checked one at a time as the archive streams past
+#[derive(Default)]
+struct MessageCheck {
+    ids: HashSet<Uuid>,
+    per_channel: HashMap<Uuid, i64>,
+}
+
+impl MessageCheck {
+    fn check(&mut self, message: &ArchiveMessage) -> Result<(), String> {
+        if self.ids.len() == MAX_IMPORT_MESSAGES {
+            return Err(format!("The archive has more than {} messages", MAX_IMPORT_MESSAGES));
+        }
+        if !is_valid_message(message) {
+            return Err(format!("Message {} has values this server doesn't accept", message.id));
+        }
+        if !self.ids.insert(message.id) {
+            return Err("The archive has duplicate ids".to_string());
+        }
+
+        let count = self.per_channel.entry(message.channel_id).or_default();
+        *count += 1;
+        if *count > MAX_EXPORT_MESSAGES_PER_CHANNEL {
+            return Err(format!("Channel {} has more than {} messages", message.channel_id, MAX_EXPORT_MESSAGES_PER_CHANNEL));
+        }
+
+        Ok(())
+    }
+}
+
+// Messages are held to the same limits as when they are sent
+fn is_valid_message(message: &ArchiveMessage) -> bool {
+    use crate::handlers::system_messages::*;
+
+    const MESSAGE_TYPES: &[&str] = &[
+        MESSAGE_TYPE_DEFAULT,
+        MESSAGE_TYPE_MEMBER_JOIN,
+        MESSAGE_TYPE_MEMBER_LEAVE,
+        MESSAGE_TYPE_GUILD_BOOST,
+        MESSAGE_TYPE_CHANNEL_PIN,
+        MESSAGE_TYPE_AUTOMOD_ALERT,
+    ];
+
+    message.text.chars().count() <= crate::handlers::messages::MAX_MESSAGE_LENGTH
+        && !message.author.is_empty()
+        && message.author.len() <= 255
+        && message.author_discriminator.as_ref().is_none_or(|d| d.len() <= 4)
+        && message.timestamp.len() <= 50
+        && MESSAGE_TYPES.contains(&message.message_type.as_str())
+        && message.attachments.len() <= MAX_IMPORT_ATTACHMENTS_PER_MESSAGE
+        && message.attachments.iter().all(|a| {
+            !a.filename.is_empty()
+                && a.filename.len() <= 255
+                && a.file_url.len() <= 2048
+                && a.file_type.as_ref().is_none_or(|t| t.len() <= 100)
+        })
+}
+
+fn has_unique_ids(ids: impl Iterator<Item = Uuid>) -> bool {
+    let mut seen = HashSet::new();
+    ids.into_iter().all(|id| seen.insert(id))
+}
+
+fn parse_timestamp(value: &Option<String>) -> Option<chrono::DateTime<chrono::Utc>> {
+    value
+        .as_deref()
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+// Start an import job for an uploaded archive. The job creates a new guild from it; every
+// id is remapped and the importing user owns the new guild.
+pub async fn import_guild(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<GuildImportResponse>), StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let mut data = None;
+    let mut name = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        match field.name() {
+            Some("archive") => data = Some(field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?),
+            Some("name") => name = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?),
+            _ => {}
+        }
+    }
+
+    let data = data.ok_or(StatusCode::BAD_REQUEST)?;
+    if data.len() > MAX_ARCHIVE_BYTES {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if name.as_ref().is_some_and(|n| n.len() > MAX_GUILD_NAME_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // One import at a time per user
+    let in_progress = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM guild_imports WHERE requested_by = $1 AND status IN ($2, $3)) as "exists!""#,
+        user_id,
+        JOB_PENDING,
+        JOB_RUNNING
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if in_progress {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let import = sqlx::query_as!(
+        ImportRow,
+        r#"
+        INSERT INTO guild_imports (id, requested_by, status)
+        VALUES ($1, $2, $3)
+        RETURNING id, status, guild_id, messages_imported, error, created_at, completed_at
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        JOB_PENDING
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let reason = crate::handlers::audit_logs::audit_log_reason(&headers);
+    spawn_import(state.clone(), import.id, user_id, name, data, reason);
+
+    Ok((StatusCode::ACCEPTED, Json(import.into_response())))
+}
+
+// Poll an import job; only the user who started it can see it
+pub async fn get_guild_import(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(import_id): axum::extract::Path<Uuid>,
+) -> Result<Json<GuildImportResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let import = sqlx::query_as!(
+        ImportRow,
+        r#"
+        SELECT id, status, guild_id, messages_imported, error, created_at, completed_at
+        FROM guild_imports
+        WHERE id = $1 AND requested_by = $2
+        "#,
+        import_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(import.into_response()))
+}
+
+fn spawn_import(state: AppState, import_id: Uuid, user_id: Uuid, name: Option<String>, data: Bytes, reason: Option<String>) {
+    tokio::spawn(async move {
+        let _ = sqlx::query!(
+            "UPDATE guild_imports SET status = $1 WHERE id = $2",
+            JOB_RUNNING,
+            import_id
+        )
+        .execute(&state.db)
+        .await;
+
+        match run_import(&state, import_id, user_id, name, data, reason).await {
+            Ok(guild_id) => {
+                println!("📦 Archive imported into guild {} for user {}", guild_id, user_id);
+                let _ = sqlx::query!(
+                    "UPDATE guild_imports SET status = $1, completed_at = NOW() WHERE id = $2",
+                    JOB_COMPLETED,
+                    import_id
+                )
+                .execute(&state.db)
+                .await;
+            }
+            Err(e) => {
+                eprintln!("❌ Import {} failed: {}", import_id, e);
+                let _ = sqlx::query!(
+                    "UPDATE guild_imports SET status = $1, guild_id = NULL, error = $2, completed_at = NOW() WHERE id = $3",
+                    JOB_FAILED,
+                    e,
+                    import_id
+                )
+                .execute(&state.db)
+                .await;
+            }
+        }
+    });
+}
+
+// The archive is read twice, so its messages are never all in memory: once to check all of
+// it, then again to insert the messages in chunks after the guild itself is created. A
+// failed import leaves nothing behind.
+async fn run_import(
+    state: &AppState,
+    import_id: Uuid,
+    user_id: Uuid,
+    name: Option<String>,
+    data: Bytes,
+    reason: Option<String>,
+) -> Result<Uuid, String> {
+    let checked = tokio::task::spawn_blocking({
+        let data = data.clone();
+        move || {
+            let mut check = MessageCheck::default();
+            let archive = read_archive(&data, |message| check.check(&message))?;
+            Ok::<_, String>((archive, check.ids.len()))
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let (mut archive, message_count) = checked?;
+
+    validate_archive(&mut archive)?;
+
+    let name = name.unwrap_or_else(|| archive.guild.name.clone());
+    if name.len() > MAX_GUILD_NAME_LENGTH {
+        return Err("The guild name in the archive is too long".to_string());
+    }
+
+    let (guild_id, channel_ids) = import_layout(state, user_id, &name, &archive)
+        .await
+        .map_err(|e| format!("Failed to create the guild: {}", e))?;
+
+    // Recorded right away so an import interrupted by a restart can be cleaned up
+    let _ = sqlx::query!("UPDATE guild_imports SET guild_id = $1 WHERE id = $2", guild_id, import_id)
+        .execute(&state.db)
+        .await;
+
+    if message_count > 0 {
+        if let Err(e) = import_messages(state, import_id, user_id, &channel_ids, data).await {
+            discard_imported_guild(&state.db, guild_id).await;
+            return Err(e);
+        }
+    }
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "guild_import",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "source_guild_id": archive.source_guild_id,
+            "archive_version": archive.version,
+            "exported_at": archive.exported_at,
+        })),
+        reason
+    ).await;
+
+    Ok(guild_id)
+}
+
+// Create the guild, its roles, channels and emoji in one transaction. Overwrites that refer
+// to users who don't exist here are dropped. Returns the new guild id and the channel id map.
+async fn import_layout(
+    state: &AppState,
+    user_id: Uuid,
+    name: &str,
+    archive: &GuildArchive,
+) -> Result<(Uuid, HashMap<Uuid, Uuid>), sqlx::Error> {
+    // Users referenced by overwrites that exist on this server
+    let referenced: Vec<Uuid> = archive
+        .channels
+        .iter()
+        .flat_map(|c| c.permission_overwrites.iter().filter_map(|o| o.user_id))
+        .collect::<HashSet<Uuid>>()
+        .into_iter()
+        .collect();
+    let known_users: HashSet<Uuid> = sqlx::query_scalar!("SELECT id FROM users WHERE id = ANY($1)", &referenced)
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let guild_id = Uuid::new_v4();
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO guilds (id, name, owner_id, icon, created_at, icon_url, banner_url, description, verification_level,
+                            join_notifications, leave_notifications, boost_notifications, pin_notifications,
+                            welcome_message, discovery_category, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+        "#,
+        guild_id,
+        name,
+        user_id,
+        archive.guild.icon,
+        chrono::Utc::now(),
+        archive.guild.icon_url,
+        archive.guild.banner_url,
+        archive.guild.description,
+        archive.guild.verification_level,
+        archive.guild.join_notifications,
+        archive.guild.leave_notifications,
+        archive.guild.boost_notifications,
+        archive.guild.pin_notifications,
+        archive.guild.welcome_message,
+        archive.guild.discovery_category,
+        &archive.guild.tags
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO guild_members (guild_id, user_id) VALUES ($1, $2)",
+        guild_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Roles (the source @everyone maps onto the new guild id, as in create_guild)
+    let mut role_ids: HashMap<Uuid, Uuid> = HashMap::new();
+
+    if !archive.roles.iter().any(|r| r.id == archive.source_guild_id) {
+        sqlx::query!(
+            "INSERT INTO roles (id, guild_id, name, color, position, permissions) VALUES ($1, $2, $3, $4, $5, $6)",
+            guild_id,
+            guild_id,
+            "@everyone",
+            "#99aab5",
+            0,
+            0
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for role in &archive.roles {
+        let role_id = if role.id == archive.source_guild_id { guild_id } else { Uuid::new_v4() };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO roles (id, guild_id, name, color, position, permissions, mentionable, hoist)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            role_id,
+            guild_id,
+            role.name,
+            role.color,
+            role.position,
+            role.permissions,
+            role.mentionable,
+            role.hoist
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        role_ids.insert(role.id, role_id);
+    }
+
+    let mut category_ids: HashMap<Uuid, Uuid> = HashMap::new();
+
+    for category in &archive.categories {
+        let category_id = Uuid::new_v4();
+
+        sqlx::query!(
+            "INSERT INTO channel_categories (id, guild_id, name, position) VALUES ($1, $2, $3, $4)",
+            category_id,
+            guild_id,
+            category.name,
+            category.position
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        category_ids.insert(category.id, category_id);
+    }
+
+    let mut channel_ids: HashMap<Uuid, Uuid> = HashMap::new();
+
+    for channel in &archive.channels {
+        let channel_id = Uuid::new_v4();
+        let category_id = channel.category_id.and_then(|id| category_ids.get(&id).copied());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO channels (id, guild_id, name, channel_type, category_id, position, topic, nsfw, slowmode_seconds, public_preview)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+            channel_id,
+            guild_id,
+            channel.name,
+            channel.channel_type,
+            category_id,
+            channel.position,
+            channel.topic,
+            channel.nsfw,
+            channel.slowmode_seconds,
+            channel.public_preview
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        channel_ids.insert(channel.id, channel_id);
+
+        for overwrite in &channel.permission_overwrites {
+            let role_id = overwrite.role_id.and_then(|id| role_ids.get(&id).copied());
+            let overwrite_user_id = overwrite.user_id.filter(|id| known_users.contains(id));
+            if role_id.is_none() && overwrite_user_id.is_none() {
+                continue;
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO channel_permissions
+                (id, channel_id, role_id, user_id, allow_view, allow_send_messages, allow_manage_messages)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                Uuid::new_v4(),
+                channel_id,
+                role_id,
+                overwrite_user_id,
+                overwrite.allow_view,
+                overwrite.allow_send_messages,
+                overwrite.allow_manage_messages
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    if let Some(system_channel_id) = archive.guild.system_channel_id.and_then(|id| channel_ids.get(&id)) {
+        sqlx::query!(
+            "UPDATE guilds SET system_channel_id = $1 WHERE id = $2",
+            system_channel_id,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let mut emoji_names: HashSet<&str> = HashSet::new();
+    for emoji in &archive.emoji {
+        if !emoji_names.insert(emoji.name.as_str()) {
+            continue;
+        }
+
+        sqlx::query!(
+            "INSERT INTO custom_emoji (guild_id, name, image_url, created_by) VALUES ($1, $2, $3, $4)",
+            guild_id,
+            emoji.name,
+            emoji.image_url,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok((guild_id, channel_ids))
+}
+
+// Insert the archive's messages as they are read, a chunk per transaction. They keep the
+// archive's order (oldest first in each channel), so replies can point at messages that are
+// already imported. Archives are written by whoever uploads them, so messages and pins are
+// only attributed to the importer; everyone else keeps just their display name.
+async fn import_messages(
+    state: &AppState,
+    import_id: Uuid,
+    user_id: Uuid,
+    channel_ids: &HashMap<Uuid, Uuid>,
+    data: Bytes,
+) -> Result<(), String> {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Vec<ArchiveMessage>>(2);
+
+    let reader = tokio::task::spawn_blocking(move || {
+        let mut chunk = Vec::with_capacity(IMPORT_MESSAGE_CHUNK);
+        read_archive(&data, |message| {
+            chunk.push(message);
+            if chunk.len() == IMPORT_MESSAGE_CHUNK {
+                sender
+                    .blocking_send(std::mem::take(&mut chunk))
+                    .map_err(|_| "The import was stopped".to_string())?;
+            }
+            Ok(())
+        })?;
+        if !chunk.is_empty() {
+            sender.blocking_send(chunk).map_err(|_| "The import was stopped".to_string())?;
+        }
+        Ok::<_, String>(())
+    });
+
+    let mut message_ids: HashMap<Uuid, Uuid> = HashMap::new();
+
+    while let Some(chunk) = receiver.recv().await {
+        insert_message_chunk(&state.db, user_id, channel_ids, &mut message_ids, &chunk)
+            .await
+            .map_err(|e| format!("Failed to import messages: {}", e))?;
+
+        let _ = sqlx::query!(
+            "UPDATE guild_imports SET messages_imported = $1 WHERE id = $2",
+            message_ids.len() as i32,
+            import_id
+        )
+        .execute(&state.db)
+        .await;
+    }
+
+    reader.await.map_err(|e| e.to_string())?
+}
+
+async fn insert_message_chunk(
+    db: &sqlx::PgPool,
+    user_id: Uuid,
+    channel_ids: &HashMap<Uuid, Uuid>,
+    message_ids: &mut HashMap<Uuid, Uuid>,
+    chunk: &[ArchiveMessage],
+) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    for message in chunk.iter().filter(|m| channel_ids.contains_key(&m.channel_id)) {
+        let message_id = Uuid::new_v4();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO messages (id, channel, author, author_discriminator, author_id, text, timestamp, created_at,
+                                  edited_at, message_type, reference_message_id, pinned_at, pinned_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, COALESCE($8, NOW()), $9, $10, $11, $12, $13)
+            "#,
+            message_id,
+            channel_ids[&message.channel_id].to_string(),
+            message.author,
+            message.author_discriminator,
+            message.author_id.filter(|id| *id == user_id),
+            message.text,
+            message.timestamp,
+            parse_timestamp(&message.created_at),
+            parse_timestamp(&message.edited_at),
+            message.message_type,
+            message.reference_message_id.and_then(|id| message_ids.get(&id).copied()),
+            parse_timestamp(&message.pinned_at),
+            message.pinned_by.filter(|id| *id == user_id)
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        message_ids.insert(message.id, message_id);
+
+        for attachment in &message.attachments {
+            sqlx::query!(
+                "INSERT INTO message_attachments (id, message_id, filename, file_url, file_type, file_size) VALUES ($1, $2, $3, $4, $5, $6)",
+                Uuid::new_v4(),
+                message_id,
+                attachment.filename,
+                attachment.file_url,
+                attachment.file_type,
+                attachment.file_size
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await
+}
+
+// Remove a guild whose import failed. Messages only refer to their channel by id, so they
+// have to go first.
+async fn discard_imported_guild(db: &sqlx::PgPool, guild_id: Uuid) {
+    let _ = sqlx::query!(
+        "DELETE FROM messages WHERE channel IN (SELECT id::text FROM channels WHERE guild_id = $1)",
+        guild_id
+    )
+    .execute(db)
+    .await;
+
+    let _ = sqlx::query!("DELETE FROM guilds WHERE id = $1", guild_id)
+        .execute(db)
+        .await;
+}
@@ -0,0 +1,257 @@
+// Stored objects shared between identical uploads, and cleanup of uploads nothing refers to
+// any more: attachments never posted or whose messages were deleted, and avatars, banners,
+// icons and emoji that were replaced.
+use bytes::Bytes;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+
+// How long an unreferenced upload is kept after it was last uploaded or fetched
+const DEFAULT_ORPHAN_GRACE_DAYS: i32 = 7;
+const FILE_GC_INTERVAL_SECS: u64 = 60 * 60;
+const FILE_GC_BATCH_SIZE: i64 = 500;
+
+fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Take a reference to the object already holding this content, if there is one
+async fn acquire_existing(state: &AppState, hash: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        UPDATE stored_objects
+        SET ref_count = ref_count + 1, last_acquired_at = NOW()
+        WHERE content_hash = $1
+        RETURNING file_key
+        "#,
+        hash
+    )
+    .fetch_optional(&state.db)
+    .await
+}
+
+// Register a freshly stored object. If identical content was registered meanwhile, that
+// object is used instead and ours is deleted.
+async fn register_object(state: &AppState, key: &str, hash: &str, size: u64) -> Result<String, sqlx::Error> {
+    let stored_key = sqlx::query_scalar!(
+        r#"
+        INSERT INTO stored_objects (file_key, content_hash, file_size)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (content_hash) DO UPDATE
+        SET ref_count = stored_objects.ref_count + 1, last_acquired_at = NOW()
+        RETURNING file_key
+        "#,
+        key,
+        hash,
+        size as i64
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    if stored_key != key {
+        let _ = state.storage.delete_file(key).await;
+    }
+    Ok(stored_key)
+}
+
+// Store `data`, or reuse the object that already has the same content. Returns the key it
+// lives under, which is `key` unless it was a duplicate. Every call takes a reference that
+// `release_object` gives back.
+pub async fn store_object(state: &AppState, key: &str, data: Bytes, content_type: &str) -> Result<String, String> {
+    let hash = content_hash(&data);
+
+    if let Some(existing) = acquire_existing(state, &hash).await.map_err(|e| e.to_string())? {
+        return Ok(existing);
+    }
+
+    let size = data.len() as u64;
+    let key = state.storage
+        .upload_file(key, data, content_type)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    register_object(state, &key, &hash, size).await.map_err(|e| e.to_string())
+}
+
+// Take ownership of an object a client put in storage directly. Hashing means reading it
+// back once; duplicates are deleted in favour of the existing object.
+pub async fn adopt_object(state: &AppState, key: &str, size: u64) -> Result<String, String> {
+    let mut stream = state.storage.get_file_stream(key, None).await.map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = stream.next().await {
+        hasher.update(chunk.map_err(|e| e.to_string())?);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+
+    if let Some(existing) = acquire_existing(state, &hash).await.map_err(|e| e.to_string())? {
+        if existing != key {
+            let _ = state.storage.delete_file(key).await;
+        }
+        return Ok(existing);
+    }
+
+    register_object(state, key, &hash, size).await.map_err(|e| e.to_string())
+}
+
+// Give back a reference taken by `store_object` or `adopt_object`, deleting the object once
+// nothing uses it. Objects from before deduplication have a single owner and go right away.
+pub async fn release_object(state: &AppState, key: &str) {
+    let remaining = sqlx::query_scalar!(
+        "UPDATE stored_objects SET ref_count = ref_count - 1 WHERE file_key = $1 RETURNING ref_count",
+        key
+    )
+    .fetch_optional(&state.db)
+    .await;
+
+    let unused = match remaining {
+        Ok(None) => true,
+        // Only delete if nobody took a new reference in the meantime
+        Ok(Some(count)) if count <= 0 => sqlx::query!(
+            "DELETE FROM stored_objects WHERE file_key = $1 AND ref_count <= 0",
+            key
+        )
+        .execute(&state.db)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .unwrap_or(false),
+        Ok(Some(_)) => false,
+        Err(e) => {
+            eprintln!("Failed to release stored object {}: {:?}", key, e);
+            false
+        }
+    };
+
+    if unused {
+        if let Err(e) = state.storage.delete_file(key).await {
+            eprintln!("Failed to delete stored object {}: {}", key, e);
+        }
+    }
+}
+
+// Delete up to a batch of uploads nothing refers to, with their variants. Returns how many
+// uploads went.
+async fn sweep_orphaned_uploads(state: &AppState, grace_days: i32) -> Result<usize, sqlx::Error> {
+    // An upload is referenced by a message that wasn't deleted, or by a profile, guild or
+    // emoji URL. The grace period runs from its last upload or download.
+    let deleted = sqlx::query!(
+        r#"
+        WITH referenced AS (
+            SELECT substring(url from '/api/files/([0-9a-fA-F-]{36})') AS id
+            FROM (
+                SELECT avatar_url AS url FROM users
+                UNION ALL SELECT banner_url FROM users
+                UNION ALL SELECT icon_url FROM guilds
+                UNION ALL SELECT banner_url FROM guilds
+                UNION ALL SELECT image_url FROM custom_emoji
+            ) urls
+            WHERE url LIKE '%/api/files/%'
+        ),
+        orphaned AS (
+            SELECT f.id
+            FROM file_uploads f
+            WHERE GREATEST(f.created_at, f.accessed_at) < (NOW() AT TIME ZONE 'UTC') - make_interval(days => $1)
+              AND NOT EXISTS (
+                  SELECT 1 FROM message_attachments ma
+                  INNER JOIN messages m ON m.id = ma.message_id
+                  WHERE ma.file_id = f.id AND m.deleted = false
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM dm_message_attachments dma
+                  INNER JOIN dm_messages dm ON dm.id = dma.message_id
+                  WHERE dma.file_id = f.id AND dm.deleted = false
+              )
+              AND f.id::text NOT IN (SELECT id FROM referenced WHERE id IS NOT NULL)
+            ORDER BY f.created_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        ),
+        deleted AS (
+            DELETE FROM file_uploads f
+            USING orphaned o
+            WHERE f.id = o.id
+            RETURNING f.id, f.file_key
+        )
+        SELECT d.file_key AS "file_key!", FALSE AS "is_variant!" FROM deleted d
+        UNION ALL
+        SELECT v.file_key, TRUE FROM file_variants v INNER JOIN deleted d ON v.file_id = d.id
+        "#,
+        grace_days,
+        FILE_GC_BATCH_SIZE
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    for row in &deleted {
+        release_object(state, &row.file_key).await;
+    }
+
+    Ok(deleted.iter().filter(|row| !row.is_variant).count())
+}
+
+// Objects whose uploads were removed without releasing them, e.g. when a user account was
+// deleted and its uploads went with it
+async fn sweep_unreferenced_objects(state: &AppState, grace_days: i32) -> Result<usize, sqlx::Error> {
+    let keys = sqlx::query_scalar!(
+        r#"
+        DELETE FROM stored_objects s
+        WHERE s.last_acquired_at < NOW() - make_interval(days => $1)
+          AND NOT EXISTS (SELECT 1 FROM file_uploads f WHERE f.file_key = s.file_key)
+          AND NOT EXISTS (SELECT 1 FROM file_variants v WHERE v.file_key = s.file_key)
+        RETURNING s.file_key
+        "#,
+        grace_days
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    for key in &keys {
+        let _ = state.storage.delete_file(key).await;
+    }
+    Ok(keys.len())
+}
+
+// Periodically delete unreferenced uploads older than ORPHANED_FILE_GRACE_DAYS
+pub fn spawn_file_gc(state: AppState) {
+    let grace_days = std::env::var("ORPHANED_FILE_GRACE_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(DEFAULT_ORPHAN_GRACE_DAYS);
+
+    if grace_days <= 0 {
+        println!("ℹ️  Orphaned file cleanup disabled, keeping uploads forever");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(FILE_GC_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let mut swept = 0;
+            loop {
+                match sweep_orphaned_uploads(&state, grace_days).await {
+                    Ok(count) => {
+                        swept += count;
+                        if (count as i64) < FILE_GC_BATCH_SIZE {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Orphaned file sweep failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+
+            match sweep_unreferenced_objects(&state, grace_days).await {
+                Ok(objects) if swept > 0 || objects > 0 => {
+                    println!("🧹 Swept {} orphaned files and {} unreferenced objects", swept, objects);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Stored object sweep failed: {:?}", e),
+            }
+        }
+    });
+}
@@ -1,15 +1,304 @@
 use axum::{
     body::Body,
-    extract::{Path, State, Request},
-    http::{StatusCode, header},
+    extract::{Path, Query, State, Request},
+    http::{HeaderMap, StatusCode, header},
     response::{Response, IntoResponse},
+    Extension,
+    Json,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use uuid::Uuid;
+
 use crate::AppState;
+use crate::storage::{ByteRange, StorageError};
+
+// Public files never change under the same id, so caches can keep them forever. Private
+// ones are revalidated on every use, which is a cheap 304 thanks to the ETag.
+const PUBLIC_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+const PRIVATE_CACHE_CONTROL: &str = "private, no-cache";
+
+// Signed URLs let <img> and <video> tags load private files without an Authorization header.
+// Expiry is rounded up so the same file gets the same URL for a while and stays cacheable.
+const SIGNED_URL_TTL_SECS: i64 = 60 * 60;
+const SIGNED_URL_EXPIRY_GRANULARITY_SECS: i64 = 5 * 60;
+const MAX_SIGNED_URLS_PER_REQUEST: usize = 100;
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+// Characters escaped in the RFC 5987 `filename*` parameter
+const FILENAME_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_');
+
+#[derive(Debug, Deserialize)]
+pub struct FileAccessQuery {
+    pub expires: Option<i64>,
+    pub signature: Option<String>,
+    // Longest edge wanted, for images stored in several sizes
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignedUrlsRequest {
+    pub file_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignedUrlsResponse {
+    pub urls: HashMap<Uuid, String>,
+    pub expires_at: Option<String>,
+}
+
+enum RangeRequest {
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+fn is_public_file_type(file_type: &str) -> bool {
+    matches!(file_type, "avatar" | "icon" | "emoji" | "banner")
+}
+
+fn storage_error_status(error: StorageError) -> StatusCode {
+    match error {
+        StorageError::NotFound => StatusCode::NOT_FOUND,
+        error => {
+            eprintln!("Failed to get file from storage: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn url_signature(file_id: Uuid, expires: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&crate::jwt_secret()).expect("HMAC accepts keys of any length");
+    mac.update(format!("file:{}:{}", file_id, expires).as_bytes());
+    mac
+}
+
+fn signed_url_with_expiry(file_id: Uuid) -> (String, i64) {
+    let now = chrono::Utc::now().timestamp();
+    let deadline = now + SIGNED_URL_TTL_SECS;
+    let expires = deadline + (SIGNED_URL_EXPIRY_GRANULARITY_SECS - deadline.rem_euclid(SIGNED_URL_EXPIRY_GRANULARITY_SECS)) % SIGNED_URL_EXPIRY_GRANULARITY_SECS;
+    let signature = URL_SAFE_NO_PAD.encode(url_signature(file_id, expires).finalize().into_bytes());
+
+    (format!("/api/files/{}?expires={}&signature={}", file_id, expires, signature), expires)
+}
+
+fn verify_url_signature(file_id: Uuid, expires: i64, signature: &str) -> bool {
+    if expires < chrono::Utc::now().timestamp() {
+        return false;
+    }
+
+    match URL_SAFE_NO_PAD.decode(signature) {
+        Ok(signature) => url_signature(file_id, expires).verify_slice(&signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// The id of an uploaded file from its "/api/files/<id>" URL
+pub fn file_id_from_url(file_url: &str) -> Option<Uuid> {
+    let (_, rest) = file_url.rsplit_once("/api/files/")?;
+    let id = rest.split(['?', '#', '/']).next()?;
+    Uuid::parse_str(id).ok()
+}
+
+// Layout details of an uploaded attachment, copied onto message attachments when posted
+#[derive(Debug, Default)]
+pub struct AttachmentMedia {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_secs: Option<f64>,
+    pub placeholder: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+// Media metadata for an attachment the uploader is posting. URLs that aren't their uploads
+// have none.
+pub async fn attachment_media(
+    conn: &mut sqlx::PgConnection,
+    file_url: &str,
+    uploader_id: Uuid,
+) -> Result<AttachmentMedia, sqlx::Error> {
+    let Some(file_id) = file_id_from_url(file_url) else {
+        return Ok(AttachmentMedia::default());
+    };
+
+    let media = sqlx::query!(
+        r#"
+        SELECT f.width, f.height, f.duration_secs, f.placeholder,
+               (SELECT MAX(v.size) FROM file_variants v WHERE v.file_id = f.id) AS thumbnail_size
+        FROM file_uploads f
+        WHERE f.id = $1 AND f.user_id = $2 AND f.file_type = 'attachment'
+        "#,
+        file_id,
+        uploader_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(media
+        .map(|m| AttachmentMedia {
+            width: m.width,
+            height: m.height,
+            duration_secs: m.duration_secs,
+            placeholder: m.placeholder,
+            // The largest thumbnail; clients can ask for the others with ?size=
+            thumbnail_url: m.thumbnail_size.map(|size| format!("/api/files/{}?size={}", file_id, size)),
+        })
+        .unwrap_or_default())
+}
+
+// Record where an attachment was posted, which decides who may download it. Only the
+// uploader's own, not yet posted attachments are linked.
+pub async fn link_attachment(
+    conn: &mut sqlx::PgConnection,
+    file_url: &str,
+    uploader_id: Uuid,
+    message_id: Uuid,
+    channel_id: Option<Uuid>,
+    dm_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    let Some(file_id) = file_id_from_url(file_url) else {
+        return Ok(());
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE file_uploads
+        SET message_id = $1, channel_id = $2, dm_id = $3
+        WHERE id = $4 AND user_id = $5 AND file_type = 'attachment' AND message_id IS NULL
+        "#,
+        message_id,
+        channel_id,
+        dm_id,
+        file_id,
+        uploader_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// Compare against an If-None-Match / If-Range list. Weak comparison is fine for both uses
+// here since our ETags are never weak.
+fn etag_matches(header_value: &str, etag: &str) -> bool {
+    header_value
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn parse_http_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<chrono::DateTime<chrono::Utc>>) -> bool {
+    // If-None-Match wins over If-Modified-Since when both are sent
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return etag_matches(if_none_match, etag);
+    }
+
+    match (
+        headers.get(header::IF_MODIFIED_SINCE).and_then(|v| v.to_str().ok()).and_then(parse_http_date),
+        last_modified,
+    ) {
+        (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+// Only single ranges are supported; anything else is answered with the whole file
+fn parse_range(headers: &HeaderMap, size: u64, etag: &str, last_modified: Option<chrono::DateTime<chrono::Utc>>) -> RangeRequest {
+    let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return RangeRequest::Full;
+    };
+
+    // A stale If-Range means the client's partial copy is outdated; send everything
+    if let Some(if_range) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        let still_valid = if if_range.starts_with('"') {
+            if_range == etag
+        } else {
+            match (parse_http_date(if_range), last_modified) {
+                (Some(date), Some(modified)) => modified.timestamp() <= date.timestamp(),
+                _ => false,
+            }
+        };
+        if !still_valid {
+            return RangeRequest::Full;
+        }
+    }
+
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        // bytes=-N: the last N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => (size.saturating_sub(suffix), size.saturating_sub(1)),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, size.saturating_sub(1)),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+            _ => return RangeRequest::Full,
+        },
+    };
 
-// Serve file with permission check
+    if size == 0 || start >= size {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    RangeRequest::Partial(ByteRange { start, end })
+}
+
+// Name the download after the uploaded file; active content is always an attachment
+fn content_disposition(original_filename: Option<&str>, file_key: &str, content_type: &str) -> String {
+    let from_key = file_key.rsplit('/').next().unwrap_or(file_key);
+    // Keys are "<uuid>-<name>", or "<uuid>.<ext>" for processed images
+    let from_key = from_key
+        .get(36..)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .filter(|name| !name.is_empty())
+        .unwrap_or(from_key);
+    let filename = original_filename.filter(|name| !name.is_empty()).unwrap_or(from_key);
+
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+    let encoded = percent_encoding::utf8_percent_encode(filename, FILENAME_ENCODE_SET);
+
+    let disposition = if crate::content_type::is_active_content(content_type) { "attachment" } else { "inline" };
+
+    format!("{}; filename=\"{}\"; filename*=UTF-8''{}", disposition, fallback, encoded)
+}
+
+// Serve file with permission check. Bodies are streamed from storage, with support for
+// single byte ranges and conditional requests. A valid signature in the query string
+// stands in for authentication.
 pub async fn serve_file(
     State(state): State<AppState>,
     Path(file_id): Path<String>,
+    Query(access): Query<FileAccessQuery>,
     request: Request,
 ) -> Result<Response, StatusCode> {
     // Get user_id from request (optional - some files might be public)
@@ -20,7 +309,7 @@ pub async fn serve_file(
     // Get file info from database
     let file = sqlx::query!(
         r#"
-        SELECT file_key, content_type, file_type, user_id
+        SELECT file_key, content_type, file_type, user_id, original_filename, channel_id, dm_id
         FROM file_uploads
         WHERE id = $1
         "#,
@@ -31,8 +320,14 @@ pub async fn serve_file(
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .ok_or(StatusCode::NOT_FOUND)?;
 
-    // Check permissions based on file type
-    let has_permission = check_file_permission(&state, &file.file_type, &file.user_id.to_string(), user_id.as_deref()).await?;
+    let has_permission = match (access.expires, access.signature.as_deref()) {
+        (Some(expires), Some(signature)) => verify_url_signature(file_uuid, expires, signature),
+        // Check permissions based on file type
+        _ => {
+            let user_uuid = user_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+            check_file_permission(&state, &file.file_type, file.user_id, file.channel_id, file.dm_id, user_uuid).await?
+        }
+    };
     
     if !has_permission {
         eprintln!("🚫 Access denied to file {} for user {:?}", file_id, user_id);
@@ -47,32 +342,152 @@ pub async fn serve_file(
     .execute(&state.db)
     .await;
 
-    // Get file from S3
-    let file_data = state.storage
-        .get_file(&file.file_key)
+    // Serve the nearest stored size: the smallest one at least as large as asked for, or
+    // the image itself. Animated images only have stills as variants, so asking for a size
+    // always gets a still.
+    let mut file_key = file.file_key;
+    let mut content_type = file.content_type;
+    let mut variant_size = None;
+    if let Some(size) = access.size {
+        let variants = sqlx::query!(
+            "SELECT size, file_key, content_type FROM file_variants WHERE file_id = $1 ORDER BY size",
+            file_uuid
+        )
+        .fetch_all(&state.db)
         .await
-        .map_err(|e| {
-            eprintln!("Failed to get file from S3: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        let animated = content_type == "image/gif";
+        let nearest = match variants.iter().position(|v| v.size as u32 >= size) {
+            Some(index) => variants.into_iter().nth(index),
+            None if animated => variants.into_iter().last(),
+            None => None,
+        };
+
+        if let Some(variant) = nearest {
+            file_key = variant.file_key;
+            content_type = variant.content_type;
+            variant_size = Some(variant.size);
+        }
+    }
+
+    let meta = state.storage
+        .head_file(&file_key)
+        .await
+        .map_err(storage_error_status)?;
 
-    println!("✅ Serving file {} to user {:?}", file_id, user_id);
+    // Stored objects are never rewritten, so the file id (and size) identifies the content
+    let etag = match variant_size {
+        Some(size) => format!("\"{}-{}\"", file_uuid, size),
+        None => format!("\"{}\"", file_uuid),
+    };
+    let cache_control = if is_public_file_type(&file.file_type) { PUBLIC_CACHE_CONTROL } else { PRIVATE_CACHE_CONTROL };
 
-    // Return file with appropriate headers
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, file.content_type)
-        .header(header::CACHE_CONTROL, "public, max-age=31536000") // Cache for 1 year
-        .body(Body::from(file_data))
-        .unwrap())
+    let mut builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(last_modified) = meta.last_modified {
+        builder = builder.header(header::LAST_MODIFIED, last_modified.format(HTTP_DATE_FORMAT).to_string());
+    }
+
+    let headers = request.headers();
+
+    if is_not_modified(headers, &etag, meta.last_modified) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let range = match parse_range(headers, meta.size, &etag, meta.last_modified) {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", meta.size))
+                .body(Body::empty())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let stream = state.storage
+        .get_file_stream(&file_key, range)
+        .await
+        .map_err(storage_error_status)?;
+
+    builder = builder
+        .header(header::CONTENT_TYPE, &content_type)
+        // Browsers must go by the type we detected at upload, not guess their own
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+
+    // A variant can be in a different format than the image itself
+    let original_filename = match (file.original_filename, variant_size) {
+        (Some(name), Some(_)) => file_key
+            .rsplit_once('.')
+            .map(|(_, extension)| crate::handlers::uploads::replace_extension(&name, extension)),
+        (name, _) => name,
+    };
+    builder = builder.header(header::CONTENT_DISPOSITION, content_disposition(original_filename.as_deref(), &file_key, &content_type));
+
+    builder = match range {
+        Some(range) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, meta.size))
+            .header(header::CONTENT_LENGTH, range.length()),
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, meta.size),
+    };
+
+    builder
+        .body(Body::from_stream(stream))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Short-lived URLs that load files without an Authorization header. Files the user can't
+// access are left out.
+pub async fn get_signed_urls(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<SignedUrlsRequest>,
+) -> Result<Json<SignedUrlsResponse>, StatusCode> {
+    let user_id = Uuid::parse_str(&user_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if payload.file_ids.len() > MAX_SIGNED_URLS_PER_REQUEST {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let files = sqlx::query!(
+        "SELECT id, file_type, user_id, channel_id, dm_id FROM file_uploads WHERE id = ANY($1)",
+        &payload.file_ids
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut urls = HashMap::new();
+    let mut expires_at = None;
+    for file in files {
+        if check_file_permission(&state, &file.file_type, file.user_id, file.channel_id, file.dm_id, Some(user_id)).await? {
+            let (url, expires) = signed_url_with_expiry(file.id);
+            urls.insert(file.id, url);
+            expires_at = chrono::DateTime::from_timestamp(expires, 0).map(|dt| dt.to_rfc3339());
+        }
+    }
+
+    Ok(Json(SignedUrlsResponse { urls, expires_at }))
 }
 
 // Check if user has permission to access file
 async fn check_file_permission(
     state: &AppState,
     file_type: &str,
-    owner_id: &str,
-    user_id: Option<&str>,
+    owner_id: Uuid,
+    channel_id: Option<Uuid>,
+    dm_id: Option<Uuid>,
+    user_id: Option<Uuid>,
 ) -> Result<bool, StatusCode> {
     match file_type {
         // Public files - anyone can access
@@ -81,22 +496,104 @@ async fn check_file_permission(
         // Banners - public for now (could be restricted to friends/guild members)
         "banner" => Ok(true),
         
-        // Attachments - only owner and message recipients can access
+        // Attachments - the uploader, and whoever can see where they were posted
         "attachment" => {
-            if let Some(uid) = user_id {
-                // Owner can always access
-                if uid == owner_id {
-                    return Ok(true);
-                }
-                
-                // TODO: Check if user is in the same DM/channel as the message
-                // For now, allow if authenticated
-                Ok(true)
-            } else {
-                Ok(false)
+            let Some(uid) = user_id else {
+                return Ok(false);
+            };
+
+            // Owner can always access
+            if uid == owner_id {
+                return Ok(true);
+            }
+
+            if let Some(channel_id) = channel_id {
+                // A channel that vanished mid-check just means no access
+                return Ok(crate::permissions::check_channel_permission(&state.db, uid, channel_id, "view")
+                    .await
+                    .unwrap_or(false));
+            }
+
+            if let Some(dm_id) = dm_id {
+                let is_participant = sqlx::query_scalar!(
+                    "SELECT EXISTS(SELECT 1 FROM direct_messages WHERE id = $1 AND (user1_id = $2 OR user2_id = $2))",
+                    dm_id,
+                    uid
+                )
+                .fetch_one(&state.db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .unwrap_or(false);
+
+                return Ok(is_participant);
             }
+
+            // Not posted anywhere yet
+            Ok(false)
         }
         
         _ => Ok(false),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(value: &str, if_range: Option<&str>, size: u64) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, value.parse().unwrap());
+        if let Some(if_range) = if_range {
+            headers.insert(header::IF_RANGE, if_range.parse().unwrap());
+        }
+        let last_modified = chrono::DateTime::parse_from_rfc2822("Tue, 01 Sep 2026 10:00:00 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        match parse_range(&headers, size, "\"abc\"", Some(last_modified)) {
+            RangeRequest::Full => "full".to_string(),
+            RangeRequest::Unsatisfiable => "unsatisfiable".to_string(),
+            RangeRequest::Partial(r) => format!("{}-{}", r.start, r.end),
+        }
+    }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(range("bytes=0-99", None, 1000), "0-99");
+        assert_eq!(range("bytes=900-", None, 1000), "900-999");
+        assert_eq!(range("bytes=-100", None, 1000), "900-999");
+        assert_eq!(range("bytes=-5000", None, 1000), "0-999");
+        assert_eq!(range("bytes=990-2000", None, 1000), "990-999");
+    }
+
+    #[test]
+    fn answers_unsupported_ranges_with_the_whole_file() {
+        assert_eq!(range("bytes=0-1,5-9", None, 1000), "full");
+        assert_eq!(range("items=0-9", None, 1000), "full");
+        assert_eq!(range("bytes=9-0", None, 1000), "full");
+        assert_eq!(range("bytes=abc", None, 1000), "full");
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_end() {
+        assert_eq!(range("bytes=1000-", None, 1000), "unsatisfiable");
+        assert_eq!(range("bytes=-0", None, 1000), "unsatisfiable");
+        assert_eq!(range("bytes=0-9", None, 0), "unsatisfiable");
+    }
+
+    #[test]
+    fn stale_if_range_gets_the_whole_file() {
+        assert_eq!(range("bytes=0-9", Some("\"abc\""), 1000), "0-9");
+        assert_eq!(range("bytes=0-9", Some("\"old\""), 1000), "full");
+        assert_eq!(range("bytes=0-9", Some("Tue, 01 Sep 2026 10:00:00 GMT"), 1000), "0-9");
+        assert_eq!(range("bytes=0-9", Some("Mon, 31 Aug 2026 10:00:00 GMT"), 1000), "full");
+    }
+
+    #[test]
+    fn matches_etag_lists() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"x\", W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abcd\", \"ab\"", "\"abc\""));
+    }
+}
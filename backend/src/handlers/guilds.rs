@@ -195,23 +195,96 @@ pub async fn get_user_guilds(
     Ok(Json(response))
 }
 
+const DEFAULT_MEMBER_PAGE_SIZE: i64 = 100;
+const MAX_MEMBER_PAGE_SIZE: i64 = 1000;
+const MAX_NICKNAME_LENGTH: usize = 32;
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+// Icon, banner and emoji image URLs
+pub const MAX_IMAGE_URL_LENGTH: usize = 2048;
+
+#[derive(Debug, Deserialize)]
+pub struct MemberListQuery {
+    pub limit: Option<i64>,
+    // Return members sorted after this user
+    pub after: Option<Uuid>,
+    // Case-insensitive prefix of the username or nickname
+    pub query: Option<String>,
+    pub role_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    // Empty or null clears the nickname
+    pub nickname: Option<String>,
+}
+
+fn prefix_pattern(q: &str) -> String {
+    let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("{}%", escaped.to_lowercase())
+}
+
+// Members sorted by display name, paginated with an `after` cursor
 pub async fn get_guild_members(
     State(state): State<AppState>,
     headers: HeaderMap,
     axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<MemberListQuery>,
 ) -> Result<Json<Vec<MemberResponse>>, StatusCode> {
-    let _user_id = extract_user_id(&headers)?;
-    
+    let user_id = extract_user_id(&headers)?;
+
+    let is_member = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2)",
+        guild_id,
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(false);
+
+    if !is_member {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_MEMBER_PAGE_SIZE).clamp(1, MAX_MEMBER_PAGE_SIZE);
+    let pattern = query
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(prefix_pattern);
+    // Everyone has the @everyone role, so filtering by it is a no-op
+    let role_id = query.role_id.filter(|id| *id != guild_id);
+
     let members = sqlx::query!(
         r#"
-        SELECT u.id, u.username, u.email, up.status, up.last_seen
+        SELECT u.id, u.username, gm.nickname, up.status, up.last_seen,
+               COALESCE(
+                   (SELECT array_agg(rm.role_id) FROM role_members rm WHERE rm.guild_id = gm.guild_id AND rm.user_id = u.id),
+                   '{}'
+               ) AS "roles!"
         FROM users u
         INNER JOIN guild_members gm ON u.id = gm.user_id
         LEFT JOIN user_presence up ON u.id = up.user_id
         WHERE gm.guild_id = $1
-        ORDER BY u.username
+          AND ($2::text IS NULL OR LOWER(u.username) LIKE $2 OR LOWER(gm.nickname) LIKE $2)
+          AND ($3::uuid IS NULL OR EXISTS(
+              SELECT 1 FROM role_members rm WHERE rm.guild_id = gm.guild_id AND rm.user_id = u.id AND rm.role_id = $3
+          ))
+          AND ($4::uuid IS NULL OR (LOWER(COALESCE(gm.nickname, u.username)), u.id) > (
+              SELECT LOWER(COALESCE(cgm.nickname, cu.username)), cu.id
+              FROM guild_members cgm
+              INNER JOIN users cu ON cu.id = cgm.user_id
+              WHERE cgm.guild_id = $1 AND cgm.user_id = $4
+          ))
+        ORDER BY LOWER(COALESCE(gm.nickname, u.username)), u.id
+        LIMIT $5
         "#,
-        guild_id
+        guild_id,
+        pattern,
+        role_id,
+        query.after,
+        limit
     )
     .fetch_all(&state.db)
     .await
@@ -234,6 +307,8 @@ pub async fn get_guild_members(
             MemberResponse {
                 id: m.id,
                 username: m.username.clone(),
+                nickname: m.nickname,
+                roles: m.roles,
                 online: status == "online" || status == "focus" || status == "dnd" || status == "idle",
                 status,
             }
@@ -243,6 +318,99 @@ pub async fn get_guild_members(
     Ok(Json(response))
 }
 
+// Set or clear a member's nickname. Members can change their own; changing someone else's
+// needs MANAGE_NICKNAMES and a role above theirs.
+pub async fn update_member(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, target_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let nickname = payload
+        .nickname
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    if nickname.as_ref().map(|n| n.chars().count() > MAX_NICKNAME_LENGTH).unwrap_or(false) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let owner_id = sqlx::query_scalar!("SELECT owner_id FROM guilds WHERE id = $1", guild_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if target_id != user_id {
+        if !crate::handlers::roles::has_permission(&state, user_id, guild_id, crate::handlers::roles::MANAGE_NICKNAMES).await? {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        // Only the owner can rename the owner
+        if target_id == owner_id {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let target_position = sqlx::query_scalar!(
+            r#"
+            SELECT MAX(r.position)
+            FROM roles r
+            INNER JOIN role_members rm ON r.id = rm.role_id
+            WHERE rm.user_id = $1 AND rm.guild_id = $2
+            "#,
+            target_id,
+            guild_id
+        )
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if let Some(position) = target_position {
+            if !crate::handlers::roles::outranks_role(&state, user_id, guild_id, position).await? {
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+    }
+
+    let previous = sqlx::query_scalar!(
+        "SELECT nickname FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+        guild_id,
+        target_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    sqlx::query!(
+        "UPDATE guild_members SET nickname = $1 WHERE guild_id = $2 AND user_id = $3",
+        nickname,
+        guild_id,
+        target_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "member_update",
+        Some("user"),
+        Some(target_id),
+        Some(serde_json::json!({ "nickname": { "old": previous, "new": nickname } })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    )
+    .await;
+
+    crate::handlers::member_list::member_changed(&state, guild_id, target_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn update_guild(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -253,7 +421,7 @@ pub async fn update_guild(
     
     // Check if user is owner
     let guild = sqlx::query!(
-        "SELECT owner_id FROM guilds WHERE id = $1",
+        "SELECT owner_id, name FROM guilds WHERE id = $1",
         guild_id
     )
     .fetch_one(&state.db)
@@ -284,6 +452,20 @@ pub async fn update_guild(
     .await
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "guild_update",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "before": { "name": guild.name },
+            "after": { "name": updated.name }
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(GuildResponse {
         id: updated.id,
         name: updated.name,
@@ -326,47 +508,120 @@ pub async fn delete_guild(
     Ok(StatusCode::NO_CONTENT)
 }
 
-
-// Get public guilds for discovery
-pub async fn get_public_guilds(
+// Transfer guild ownership to another member (requires the current owner's password)
+pub async fn transfer_ownership(
     State(state): State<AppState>,
-) -> Result<Json<Vec<PublicGuildResponse>>, StatusCode> {
-    let guilds = sqlx::query!(
-        r#"
-        SELECT 
-            g.id, 
-            g.name, 
-            g.description, 
-            g.icon, 
-            g.icon_url,
-            g.is_verified,
-            COUNT(gm.user_id) as member_count
-        FROM guilds g
-        LEFT JOIN guild_members gm ON g.id = gm.guild_id
-        WHERE g.is_public = TRUE
-        GROUP BY g.id, g.name, g.description, g.icon, g.icon_url, g.is_verified
-        ORDER BY g.is_verified DESC, member_count DESC
-        LIMIT 50
-        "#
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<TransferOwnershipRequest>,
+) -> Result<Json<GuildResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    // Check if user is owner
+    let guild = sqlx::query!(
+        "SELECT owner_id FROM guilds WHERE id = $1",
+        guild_id
     )
-    .fetch_all(&state.db)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    if guild.owner_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if payload.new_owner_id == user_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Re-confirm the owner's password before handing over the server
+    let owner = sqlx::query!(
+        "SELECT password_hash FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let password_ok = bcrypt::verify(&payload.password, &owner.password_hash)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if !password_ok {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // New owner must already be a member of the guild
+    let is_member = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2)",
+        guild_id,
+        payload.new_owner_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(false);
+
+    if !is_member {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Only swap if the owner hasn't changed since we checked
+    let result = sqlx::query!(
+        "UPDATE guilds SET owner_id = $1 WHERE id = $2 AND owner_id = $3",
+        payload.new_owner_id,
+        guild_id,
+        user_id
+    )
+    .execute(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let response: Vec<PublicGuildResponse> = guilds
-        .into_iter()
-        .map(|g| PublicGuildResponse {
-            id: g.id.to_string(),
-            name: g.name,
-            description: g.description,
-            member_count: g.member_count.map(|c| c as i32),
-            icon: g.icon,
-            icon_url: g.icon_url,
-            is_verified: g.is_verified.unwrap_or(false),
-        })
-        .collect();
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::CONFLICT);
+    }
 
-    Ok(Json(response))
+    // Create audit log entry
+    let details = serde_json::json!({
+        "before": { "owner_id": user_id },
+        "after": { "owner_id": payload.new_owner_id }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "guild_owner_transfer",
+        Some("user"),
+        Some(payload.new_owner_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    // Let the new owner's clients know right away
+    state.ws_state.send_to_user(&payload.new_owner_id.to_string(), serde_json::json!({
+        "type": "guild_owner_changed",
+        "guild_id": guild_id,
+        "owner_id": payload.new_owner_id,
+        "previous_owner_id": user_id,
+    })).await;
+
+    let updated = sqlx::query!(
+        "SELECT id, name, owner_id, icon, created_at, banner_url, icon_url FROM guilds WHERE id = $1",
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(GuildResponse {
+        id: updated.id,
+        name: updated.name,
+        owner_id: updated.owner_id,
+        icon: updated.icon,
+        created_at: updated.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+        banner_url: updated.banner_url,
+        icon_url: updated.icon_url,
+    }))
 }
 
 // Update guild settings (make public, add description)
@@ -381,7 +636,7 @@ pub async fn update_guild_settings(
     // Check if user is owner
     let guild_uuid = Uuid::parse_str(&guild_id).map_err(|_| StatusCode::BAD_REQUEST)?;
     let guild = sqlx::query!(
-        "SELECT owner_id FROM guilds WHERE id = $1",
+        "SELECT owner_id, is_public, description, banner_url, icon_url, verification_level FROM guilds WHERE id = $1",
         guild_uuid
     )
     .fetch_one(&state.db)
@@ -392,6 +647,13 @@ pub async fn update_guild_settings(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    if payload.description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH)
+        || payload.banner_url.as_ref().is_some_and(|url| url.len() > MAX_IMAGE_URL_LENGTH)
+        || payload.icon_url.as_ref().is_some_and(|url| url.len() > MAX_IMAGE_URL_LENGTH)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
     // Build update query dynamically based on what fields are provided
     if let Some(is_public) = payload.is_public {
         sqlx::query!(
@@ -437,18 +699,59 @@ pub async fn update_guild_settings(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
-    Ok(StatusCode::OK)
-}
+    if let Some(verification_level) = payload.verification_level {
+        use crate::handlers::screening::{VERIFICATION_NONE, VERIFICATION_MEMBER_AGE};
+        if !(VERIFICATION_NONE..=VERIFICATION_MEMBER_AGE).contains(&verification_level) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
 
-#[derive(Serialize)]
-pub struct PublicGuildResponse {
-    pub id: String,
-    pub name: String,
-    pub description: Option<String>,
-    pub member_count: Option<i32>,
-    pub icon: String,
-    pub icon_url: Option<String>,
-    pub is_verified: bool,
+        sqlx::query!(
+            "UPDATE guilds SET verification_level = $1 WHERE id = $2",
+            verification_level,
+            guild_uuid
+        )
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let updated = sqlx::query!(
+        "SELECT is_public, description, banner_url, icon_url, verification_level FROM guilds WHERE id = $1",
+        guild_uuid
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let details = serde_json::json!({
+        "before": {
+            "is_public": guild.is_public,
+            "description": guild.description,
+            "banner_url": guild.banner_url,
+            "icon_url": guild.icon_url,
+            "verification_level": guild.verification_level,
+        },
+        "after": {
+            "is_public": updated.is_public,
+            "description": updated.description,
+            "banner_url": updated.banner_url,
+            "icon_url": updated.icon_url,
+            "verification_level": updated.verification_level,
+        }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_uuid,
+        Some(user_id),
+        "guild_update",
+        Some("guild"),
+        Some(guild_uuid),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::OK)
 }
 
 #[derive(Deserialize)]
@@ -457,4 +760,11 @@ pub struct UpdateGuildSettingsRequest {
     pub description: Option<String>,
     pub banner_url: Option<String>,
     pub icon_url: Option<String>,
+    pub verification_level: Option<i16>,
+}
+
+#[derive(Deserialize)]
+pub struct TransferOwnershipRequest {
+    pub new_owner_id: Uuid,
+    pub password: String,
 }
@@ -64,6 +64,17 @@ pub async fn create_invite(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "invite_create",
+        Some("invite"),
+        Some(invite_id),
+        Some(serde_json::json!({ "code": code })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(InviteResponse {
         code,
         guild_id,
@@ -163,15 +174,26 @@ pub async fn join_guild(
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     if existing.is_none() {
+        // New members start out pending if the guild has a rules screen
+        let pending = crate::handlers::screening::is_screening_enabled(&state.db, invite.guild_id).await?;
+
         // Add user to guild
         sqlx::query!(
-            "INSERT INTO guild_members (guild_id, user_id) VALUES ($1, $2)",
+            "INSERT INTO guild_members (guild_id, user_id, pending) VALUES ($1, $2, $3)",
             invite.guild_id,
-            user_id
+            user_id,
+            pending
         )
         .execute(&state.db)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        crate::handlers::system_messages::announce_member_event(
+            &state,
+            invite.guild_id,
+            user_id,
+            crate::handlers::system_messages::MemberEvent::Join,
+        ).await;
     }
 
     // Increment uses
@@ -220,11 +242,11 @@ pub async fn leave_guild(
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
     if guild.owner_id == user_id {
-        return Err(StatusCode::FORBIDDEN); // Owner can't leave, must delete server
+        return Err(StatusCode::FORBIDDEN); // Owner can't leave, must transfer ownership or delete server
     }
 
     // Remove user from guild
-    sqlx::query!(
+    let result = sqlx::query!(
         "DELETE FROM guild_members WHERE guild_id = $1 AND user_id = $2",
         guild_id,
         user_id
@@ -233,5 +255,14 @@ pub async fn leave_guild(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        crate::handlers::system_messages::announce_member_event(
+            &state,
+            guild_id,
+            user_id,
+            crate::handlers::system_messages::MemberEvent::Leave,
+        ).await;
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
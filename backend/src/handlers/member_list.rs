@@ -0,0 +1,706 @@
+// Lazy member list. Instead of fetching every member, clients subscribe over the gateway to
+// the ranges of a guild's member sidebar they can see (after identifying the socket):
+//
+//   {"type": "member_list_subscribe", "guild_id": "...", "ranges": [[0, 99]]}
+//
+// and receive `guild_member_list_update` with the items in those ranges, re-sent whenever
+// they change. The list is grouped like the sidebar: online members under their highest
+// hoisted role, then the remaining online members, then everyone offline. Every group
+// header takes up one slot in the list. Subscribing with no ranges unsubscribes.
+//
+// Lists of watched guilds are kept sorted in memory. Presence and per-member changes are
+// applied to them directly; only changes to the roles themselves reload a list.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::AppState;
+
+pub const ONLINE_GROUP: &str = "online";
+pub const OFFLINE_GROUP: &str = "offline";
+
+const MAX_RANGES: usize = 5;
+const MAX_RANGE_SIZE: i64 = 100;
+// Changes are collected and sent at most this often
+const DISPATCH_INTERVAL_MS: u64 = 1000;
+// Members going stale (no heartbeat) produce no event, so lists are re-checked periodically
+const REFRESH_INTERVAL_SECS: u64 = 30;
+// Without a heartbeat for this long a member counts as offline
+const ONLINE_TIMEOUT_SECS: i64 = 60;
+
+#[derive(Debug, Deserialize)]
+struct SubscribeRequest {
+    guild_id: Uuid,
+    #[serde(default)]
+    ranges: Vec<(i64, i64)>,
+}
+
+struct Subscription {
+    ranges: Vec<(i64, i64)>,
+    // Last payload sent, so unchanged lists aren't re-sent
+    last_sent: Option<String>,
+}
+
+// Sidebar groups in display order: hoisted roles by position (highest first), then online, then offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum GroupKey {
+    Role(Reverse<i32>, Uuid),
+    Online,
+    Offline,
+}
+
+impl GroupKey {
+    fn id(&self) -> String {
+        match self {
+            GroupKey::Role(_, role_id) => role_id.to_string(),
+            GroupKey::Online => ONLINE_GROUP.to_string(),
+            GroupKey::Offline => OFFLINE_GROUP.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ListMember {
+    username: String,
+    nickname: Option<String>,
+    status: Option<String>,
+    last_seen: Option<DateTime<Utc>>,
+    // Highest hoisted role as (position, id), whether or not the member is online
+    hoisted_role: Option<(i32, Uuid)>,
+    // Where the member is currently placed in the list
+    group: GroupKey,
+    sort_name: String,
+}
+
+impl ListMember {
+    fn online(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.status.as_deref(), Some("online" | "idle" | "dnd" | "focus"))
+            && self
+                .last_seen
+                .is_some_and(|seen| seen > now - chrono::Duration::seconds(ONLINE_TIMEOUT_SECS))
+    }
+
+    fn current_group(&self, now: DateTime<Utc>) -> GroupKey {
+        match (self.online(now), self.hoisted_role) {
+            (false, _) => GroupKey::Offline,
+            (true, Some((position, role_id))) => GroupKey::Role(Reverse(position), role_id),
+            (true, None) => GroupKey::Online,
+        }
+    }
+
+    fn to_item(&self, user_id: Uuid) -> MemberListMember {
+        let online = self.group != GroupKey::Offline;
+        MemberListMember {
+            user_id,
+            username: self.username.clone(),
+            nickname: self.nickname.clone(),
+            status: if online { self.status.clone().unwrap_or_else(|| "online".to_string()) } else { "offline".to_string() },
+            online,
+            hoisted_role_id: match self.group {
+                GroupKey::Role(_, role_id) => Some(role_id),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct GuildList {
+    members: HashMap<Uuid, ListMember>,
+    // Each group's members, sorted by lowercased display name then user id
+    groups: BTreeMap<GroupKey, Vec<(String, Uuid)>>,
+}
+
+impl GuildList {
+    fn from_members(rows: Vec<(Uuid, ListMember)>, now: DateTime<Utc>) -> Self {
+        let mut list = GuildList::default();
+        for (user_id, mut member) in rows {
+            member.group = member.current_group(now);
+            list.groups.entry(member.group).or_default().push((member.sort_name.clone(), user_id));
+            list.members.insert(user_id, member);
+        }
+        for group in list.groups.values_mut() {
+            group.sort_unstable();
+        }
+        list
+    }
+
+    fn remove(&mut self, user_id: Uuid) -> Option<ListMember> {
+        let member = self.members.remove(&user_id)?;
+        if let Some(group) = self.groups.get_mut(&member.group) {
+            if let Ok(index) = group.binary_search(&(member.sort_name.clone(), user_id)) {
+                group.remove(index);
+            }
+            if group.is_empty() {
+                self.groups.remove(&member.group);
+            }
+        }
+        Some(member)
+    }
+
+    fn insert(&mut self, user_id: Uuid, mut member: ListMember, now: DateTime<Utc>) {
+        self.remove(user_id);
+        member.group = member.current_group(now);
+        let entry = (member.sort_name.clone(), user_id);
+        let group = self.groups.entry(member.group).or_default();
+        let index = group.binary_search(&entry).unwrap_or_else(|index| index);
+        group.insert(index, entry);
+        self.members.insert(user_id, member);
+    }
+
+    // Apply a presence change; returns whether anything shown in the list changed
+    fn update_presence(&mut self, user_id: Uuid, status: Option<&str>, seen_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let Some(member) = self.members.get_mut(&user_id) else {
+            return false;
+        };
+        let previous_status = member.status.clone();
+        match status {
+            Some(status) => member.status = Some(status.to_string()),
+            // A heartbeat without a presence row creates one as online
+            None => {
+                member.status.get_or_insert_with(|| "online".to_string());
+            }
+        }
+        member.last_seen = Some(seen_at);
+
+        if member.current_group(now) != member.group {
+            let member = member.clone();
+            self.insert(user_id, member, now);
+            return true;
+        }
+        member.status != previous_status && member.group != GroupKey::Offline
+    }
+
+    // Move members whose heartbeat expired; returns whether any moved
+    fn regroup(&mut self, now: DateTime<Utc>) -> bool {
+        let moved: Vec<Uuid> = self
+            .members
+            .iter()
+            .filter(|(_, member)| member.current_group(now) != member.group)
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        for user_id in &moved {
+            if let Some(member) = self.remove(*user_id) {
+                self.insert(*user_id, member, now);
+            }
+        }
+        !moved.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct MemberListsInner {
+    // guild -> user -> subscription
+    subscriptions: HashMap<Uuid, HashMap<Uuid, Subscription>>,
+    // Lists of the guilds someone is subscribed to
+    lists: HashMap<Uuid, GuildList>,
+    // Lists that changed and need sending
+    dirty: HashSet<Uuid>,
+    // Lists that need reloading from the database
+    stale: HashSet<Uuid>,
+}
+
+#[derive(Clone, Default)]
+pub struct MemberLists {
+    inner: Arc<RwLock<MemberListsInner>>,
+}
+
+impl MemberLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Something that affects the whole list changed (role order or hoisting, prunes): reload it
+    pub async fn reload(&self, guild_id: Uuid) {
+        let mut inner = self.inner.write().await;
+        if inner.subscriptions.contains_key(&guild_id) {
+            inner.stale.insert(guild_id);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberListGroup {
+    pub id: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberListMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub status: String,
+    pub online: bool,
+    pub hoisted_role_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberListItem {
+    Group(MemberListGroup),
+    Member(MemberListMember),
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberListRange {
+    pub range: (i64, i64),
+    pub items: Vec<MemberListItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberListUpdate {
+    #[serde(rename = "type")]
+    pub event_type: &'static str,
+    pub guild_id: Uuid,
+    pub member_count: i64,
+    pub online_count: i64,
+    pub groups: Vec<MemberListGroup>,
+    pub ranges: Vec<MemberListRange>,
+}
+
+fn valid_ranges(ranges: &[(i64, i64)]) -> bool {
+    ranges.len() <= MAX_RANGES
+        && ranges
+            .iter()
+            .all(|(start, end)| *start >= 0 && end >= start && end - start < MAX_RANGE_SIZE)
+}
+
+// A guild's members with presence and highest hoisted role, or just one member's
+async fn load_members(db: &sqlx::PgPool, guild_id: Uuid, user_id: Option<Uuid>) -> Result<Vec<(Uuid, ListMember)>, sqlx::Error> {
+    let members = sqlx::query!(
+        r#"
+        SELECT gm.user_id as "user_id!", u.username as "username!", gm.nickname,
+               up.status as "status?", up.last_seen as "last_seen?",
+               h.role_id as "hoisted_role_id?", h.position as "hoisted_position?"
+        FROM guild_members gm
+        JOIN users u ON u.id = gm.user_id
+        LEFT JOIN user_presence up ON up.user_id = gm.user_id
+        LEFT JOIN (
+            SELECT DISTINCT ON (rm.user_id) rm.user_id, r.id as role_id, r.position
+            FROM role_members rm JOIN roles r ON r.id = rm.role_id
+            WHERE rm.guild_id = $1 AND r.hoist = true
+            ORDER BY rm.user_id, r.position DESC, r.id
+        ) h ON h.user_id = gm.user_id
+        WHERE gm.guild_id = $1 AND ($2::uuid IS NULL OR gm.user_id = $2)
+        "#,
+        guild_id,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(members
+        .into_iter()
+        .map(|m| {
+            let sort_name = m.nickname.as_deref().unwrap_or(&m.username).to_lowercase();
+            (
+                m.user_id,
+                ListMember {
+                    username: m.username,
+                    nickname: m.nickname,
+                    status: m.status,
+                    last_seen: m.last_seen,
+                    hoisted_role: m.hoisted_role_id.zip(m.hoisted_position).map(|(role_id, position)| (position, role_id)),
+                    group: GroupKey::Offline,
+                    sort_name,
+                },
+            )
+        })
+        .collect())
+}
+
+// Build the items for a set of ranges over the flattened list
+fn build_update(list: &GuildList, guild_id: Uuid, ranges: &[(i64, i64)]) -> MemberListUpdate {
+    let groups: Vec<MemberListGroup> = list
+        .groups
+        .iter()
+        .map(|(key, members)| MemberListGroup {
+            id: key.id(),
+            count: members.len() as i64,
+        })
+        .collect();
+
+    let item_at = |index: i64| -> Option<MemberListItem> {
+        let mut start = 0;
+        for (group, members) in groups.iter().zip(list.groups.values()) {
+            if index == start {
+                return Some(MemberListItem::Group(group.clone()));
+            }
+            if index <= start + group.count {
+                let (_, user_id) = &members[(index - start - 1) as usize];
+                return list.members.get(user_id).map(|m| MemberListItem::Member(m.to_item(*user_id)));
+            }
+            start += group.count + 1;
+        }
+        None
+    };
+
+    let ranges = ranges
+        .iter()
+        .map(|(range_start, range_end)| MemberListRange {
+            range: (*range_start, *range_end),
+            items: (*range_start..=*range_end).map_while(item_at).collect(),
+        })
+        .collect();
+
+    let member_count = list.members.len() as i64;
+    let online_count = member_count - list.groups.get(&GroupKey::Offline).map_or(0, |g| g.len() as i64);
+
+    MemberListUpdate {
+        event_type: "guild_member_list_update",
+        guild_id,
+        member_count,
+        online_count,
+        groups,
+        ranges,
+    }
+}
+
+// Gateway op: subscribe to (or, with no ranges, unsubscribe from) a guild's member list
+pub async fn handle_subscribe(state: &AppState, user_id: Uuid, message: &serde_json::Value) {
+    let Ok(request) = serde_json::from_value::<SubscribeRequest>(message.clone()) else {
+        return;
+    };
+    if !valid_ranges(&request.ranges) {
+        return;
+    }
+
+    let is_member = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2) as "is_member!""#,
+        request.guild_id,
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(false);
+    if !is_member {
+        return;
+    }
+
+    {
+        let mut inner = state.member_lists.inner.write().await;
+        if request.ranges.is_empty() {
+            unsubscribe(&mut inner, request.guild_id, user_id);
+            return;
+        }
+
+        inner.subscriptions.entry(request.guild_id).or_default().insert(
+            user_id,
+            Subscription {
+                ranges: request.ranges.clone(),
+                last_sent: None,
+            },
+        );
+    }
+
+    // Send the initial state right away rather than on the next tick
+    dispatch_guild(state, request.guild_id).await;
+}
+
+fn forget_guild(inner: &mut MemberListsInner, guild_id: Uuid) {
+    inner.subscriptions.remove(&guild_id);
+    inner.lists.remove(&guild_id);
+    inner.dirty.remove(&guild_id);
+    inner.stale.remove(&guild_id);
+}
+
+fn unsubscribe(inner: &mut MemberListsInner, guild_id: Uuid, user_id: Uuid) {
+    if let Some(subscribers) = inner.subscriptions.get_mut(&guild_id) {
+        subscribers.remove(&user_id);
+        if subscribers.is_empty() {
+            forget_guild(inner, guild_id);
+        }
+    }
+}
+
+// Drop subscribers who aren't in the guild's loaded list any more (they left or were
+// removed); returns whether anyone is still subscribed
+fn drop_former_members(inner: &mut MemberListsInner, guild_id: Uuid) -> bool {
+    let (Some(list), Some(subscribers)) = (inner.lists.get(&guild_id), inner.subscriptions.get_mut(&guild_id)) else {
+        return inner.subscriptions.contains_key(&guild_id);
+    };
+    subscribers.retain(|user_id, _| list.members.contains_key(user_id));
+    if subscribers.is_empty() {
+        forget_guild(inner, guild_id);
+        return false;
+    }
+    true
+}
+
+// A user's presence changed (`status` is None for a heartbeat): update the watched lists they're in
+pub async fn presence_changed(state: &AppState, user_id: Uuid, status: Option<&str>, seen_at: DateTime<Utc>) {
+    let now = Utc::now();
+    let mut guard = state.member_lists.inner.write().await;
+    let inner = &mut *guard;
+    for (guild_id, list) in inner.lists.iter_mut() {
+        if list.update_presence(user_id, status, seen_at, now) {
+            inner.dirty.insert(*guild_id);
+        }
+    }
+}
+
+// A member joined, left, or had their nickname or roles changed: re-read just that member
+pub async fn member_changed(state: &AppState, guild_id: Uuid, user_id: Uuid) {
+    if !state.member_lists.inner.read().await.subscriptions.contains_key(&guild_id) {
+        return;
+    }
+
+    let member = match load_members(&state.db, guild_id, Some(user_id)).await {
+        Ok(rows) => rows.into_iter().next(),
+        Err(e) => {
+            eprintln!("Failed to load member {} of guild {}: {:?}", user_id, guild_id, e);
+            state.member_lists.reload(guild_id).await;
+            return;
+        }
+    };
+
+    let mut guard = state.member_lists.inner.write().await;
+    let inner = &mut *guard;
+    if member.is_none() {
+        // No longer a member: stop sending them this guild's list
+        unsubscribe(inner, guild_id, user_id);
+    }
+    let Some(list) = inner.lists.get_mut(&guild_id) else {
+        return;
+    };
+    match member {
+        Some((user_id, member)) => list.insert(user_id, member, Utc::now()),
+        None => {
+            list.remove(user_id);
+        }
+    }
+    inner.dirty.insert(guild_id);
+}
+
+// Load a guild's list from the database, replacing the one in memory
+async fn load_list(state: &AppState, guild_id: Uuid) -> bool {
+    state.member_lists.inner.write().await.stale.remove(&guild_id);
+
+    let rows = match load_members(&state.db, guild_id, None).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to load member list for guild {}: {:?}", guild_id, e);
+            state.member_lists.reload(guild_id).await;
+            return false;
+        }
+    };
+    let list = GuildList::from_members(rows, Utc::now());
+
+    let mut inner = state.member_lists.inner.write().await;
+    if !inner.subscriptions.contains_key(&guild_id) {
+        return false;
+    }
+    inner.lists.insert(guild_id, list);
+    // Membership may have changed since they subscribed
+    drop_former_members(&mut inner, guild_id)
+}
+
+// Send every subscriber of a guild their ranges if they changed since the last send
+async fn dispatch_guild(state: &AppState, guild_id: Uuid) {
+    let (subscribers, loaded) = {
+        let inner = state.member_lists.inner.read().await;
+        match inner.subscriptions.get(&guild_id) {
+            Some(subscribers) => (
+                subscribers.keys().copied().collect::<Vec<Uuid>>(),
+                inner.lists.contains_key(&guild_id) && !inner.stale.contains(&guild_id),
+            ),
+            None => return,
+        }
+    };
+
+    if !loaded && !load_list(state, guild_id).await {
+        return;
+    }
+
+    let mut connected = Vec::new();
+    let mut disconnected = Vec::new();
+    for user_id in subscribers {
+        if state.ws_state.is_user_connected(&user_id.to_string()).await {
+            connected.push(user_id);
+        } else {
+            disconnected.push(user_id);
+        }
+    }
+
+    let mut outgoing = Vec::new();
+    {
+        let mut guard = state.member_lists.inner.write().await;
+        let inner = &mut *guard;
+
+        if let (Some(list), Some(subscribers)) = (inner.lists.get(&guild_id), inner.subscriptions.get_mut(&guild_id)) {
+            // Most clients look at the same ranges; build each distinct set once
+            let mut built: HashMap<Vec<(i64, i64)>, Option<String>> = HashMap::new();
+            for user_id in &connected {
+                if !list.members.contains_key(user_id) {
+                    continue;
+                }
+                let Some(subscription) = subscribers.get_mut(user_id) else {
+                    continue;
+                };
+                let payload = built
+                    .entry(subscription.ranges.clone())
+                    .or_insert_with(|| serde_json::to_string(&build_update(list, guild_id, &subscription.ranges)).ok());
+                let Some(payload) = payload else {
+                    continue;
+                };
+                if subscription.last_sent.as_ref() != Some(payload) {
+                    subscription.last_sent = Some(payload.clone());
+                    outgoing.push((*user_id, payload.clone()));
+                }
+            }
+        }
+
+        for user_id in &disconnected {
+            unsubscribe(inner, guild_id, *user_id);
+        }
+        drop_former_members(inner, guild_id);
+    }
+
+    for (user_id, payload) in outgoing {
+        if let Ok(value) = serde_json::from_str(&payload) {
+            state.ws_state.send_to_user(&user_id.to_string(), value).await;
+        }
+    }
+}
+
+pub fn spawn_member_list_dispatcher(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(DISPATCH_INTERVAL_MS));
+        let refresh_every = REFRESH_INTERVAL_SECS * 1000 / DISPATCH_INTERVAL_MS;
+        let mut ticks: u64 = 0;
+
+        loop {
+            interval.tick().await;
+            ticks += 1;
+
+            let guilds: HashSet<Uuid> = {
+                let mut guard = state.member_lists.inner.write().await;
+                let inner = &mut *guard;
+                if ticks.is_multiple_of(refresh_every) {
+                    let now = Utc::now();
+                    for (guild_id, list) in inner.lists.iter_mut() {
+                        if list.regroup(now) {
+                            inner.dirty.insert(*guild_id);
+                        }
+                    }
+                }
+                inner.dirty.drain().chain(inner.stale.iter().copied()).collect()
+            };
+
+            for guild_id in guilds {
+                dispatch_guild(&state, guild_id).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, status: Option<&str>, hoisted_role: Option<(i32, Uuid)>, now: DateTime<Utc>) -> ListMember {
+        ListMember {
+            username: name.to_string(),
+            nickname: None,
+            status: status.map(str::to_string),
+            last_seen: Some(now),
+            hoisted_role,
+            group: GroupKey::Offline,
+            sort_name: name.to_lowercase(),
+        }
+    }
+
+    fn names(update: &MemberListUpdate) -> Vec<String> {
+        update.ranges[0]
+            .items
+            .iter()
+            .map(|item| match item {
+                MemberListItem::Group(group) => format!("[{}]", group.id),
+                MemberListItem::Member(member) => member.username.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_members_under_groups_in_sidebar_order() {
+        let now = Utc::now();
+        let (low, high) = (Uuid::new_v4(), Uuid::new_v4());
+        let list = GuildList::from_members(
+            vec![
+                (Uuid::new_v4(), member("zed", Some("online"), None, now)),
+                (Uuid::new_v4(), member("Amy", Some("idle"), None, now)),
+                (Uuid::new_v4(), member("mod", Some("dnd"), Some((1, low)), now)),
+                (Uuid::new_v4(), member("admin", Some("online"), Some((5, high)), now)),
+                (Uuid::new_v4(), member("gone", Some("offline"), Some((5, high)), now)),
+            ],
+            now,
+        );
+
+        let update = build_update(&list, Uuid::new_v4(), &[(0, 99)]);
+        assert_eq!(
+            names(&update),
+            vec![
+                format!("[{}]", high), "admin".to_string(),
+                format!("[{}]", low), "mod".to_string(),
+                "[online]".to_string(), "Amy".to_string(), "zed".to_string(),
+                "[offline]".to_string(), "gone".to_string(),
+            ]
+        );
+        assert_eq!((update.member_count, update.online_count), (5, 4));
+
+        let window = build_update(&list, Uuid::new_v4(), &[(2, 4)]);
+        assert_eq!(names(&window), vec![format!("[{}]", low), "mod".to_string(), "[online]".to_string()]);
+    }
+
+    #[test]
+    fn presence_changes_move_members_between_groups() {
+        let now = Utc::now();
+        let user_id = Uuid::new_v4();
+        let mut list = GuildList::from_members(
+            vec![
+                (user_id, member("amy", Some("offline"), None, now)),
+                (Uuid::new_v4(), member("bob", Some("online"), None, now)),
+            ],
+            now,
+        );
+        assert_eq!(build_update(&list, Uuid::new_v4(), &[(0, 9)]).online_count, 1);
+
+        assert!(list.update_presence(user_id, Some("online"), now, now));
+        assert_eq!(names(&build_update(&list, Uuid::new_v4(), &[(0, 9)])), vec!["[online]", "amy", "bob"]);
+
+        // A heartbeat alone changes nothing visible
+        assert!(!list.update_presence(user_id, None, now, now));
+
+        // Without heartbeats everyone expires into the offline group
+        assert!(list.regroup(now + chrono::Duration::seconds(ONLINE_TIMEOUT_SECS + 1)));
+        assert_eq!(names(&build_update(&list, Uuid::new_v4(), &[(0, 9)])), vec!["[offline]", "amy", "bob"]);
+
+        list.remove(user_id);
+        assert_eq!(names(&build_update(&list, Uuid::new_v4(), &[(0, 9)])), vec!["[offline]", "bob"]);
+    }
+
+    #[test]
+    fn subscribers_who_left_are_dropped() {
+        let now = Utc::now();
+        let (guild_id, stays, leaves) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let subscription = || Subscription { ranges: vec![(0, 99)], last_sent: None };
+        let mut inner = MemberListsInner::default();
+        inner.subscriptions.insert(guild_id, HashMap::from([(stays, subscription()), (leaves, subscription())]));
+        inner.lists.insert(guild_id, GuildList::from_members(vec![(stays, member("amy", Some("online"), None, now))], now));
+
+        assert!(drop_former_members(&mut inner, guild_id));
+        assert!(inner.subscriptions[&guild_id].contains_key(&stays));
+        assert!(!inner.subscriptions[&guild_id].contains_key(&leaves));
+
+        // Once nobody who's still a member is watching, the list is let go
+        inner.lists.get_mut(&guild_id).unwrap().remove(stays);
+        assert!(!drop_former_members(&mut inner, guild_id));
+        assert!(!inner.lists.contains_key(&guild_id));
+    }
+}
@@ -1,30 +1,44 @@
-use axum::{extract::{Path, State}, http::StatusCode, Json};
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Extension, Json};
 use uuid::Uuid;
 use serde::Deserialize;
 
 use crate::{models::*, AppState};
 
+// Longest message text, in characters
+pub const MAX_MESSAGE_LENGTH: usize = 4000;
+
 pub async fn get_messages(
     State(state): State<AppState>,
     Path(channel): Path<String>,
 ) -> Result<Json<Vec<Message>>, StatusCode> {
-    let messages = sqlx::query!(
-        "SELECT id, channel, author, author_id, text, timestamp, edited_at FROM messages WHERE channel = $1 AND deleted = false ORDER BY created_at ASC",
-        channel
+    Ok(Json(load_channel_messages(&state.db, &channel, None).await?))
+}
+
+// Load a channel's messages oldest first, optionally only the most recent `limit` of them
+pub async fn load_channel_messages(
+    db: &sqlx::PgPool,
+    channel: &str,
+    limit: Option<i64>,
+) -> Result<Vec<Message>, StatusCode> {
+    let mut messages = sqlx::query!(
+        "SELECT id, channel, author, author_id, text, timestamp, edited_at, message_type, reference_message_id, pinned_at FROM messages WHERE channel = $1 AND deleted = false ORDER BY created_at DESC LIMIT $2",
+        channel,
+        limit
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    messages.reverse();
     
     // Fetch all attachments for these messages
     let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
     
     let attachments = if !message_ids.is_empty() {
         sqlx::query!(
-            "SELECT id, message_id, filename, file_url, file_type, file_size FROM message_attachments WHERE message_id = ANY($1)",
+            "SELECT id, message_id, filename, file_url, file_type, file_size, width, height, duration_secs, placeholder, thumbnail_url FROM message_attachments WHERE message_id = ANY($1)",
             &message_ids
         )
-        .fetch_all(&state.db)
+        .fetch_all(db)
         .await
         .unwrap_or_default()
     } else {
@@ -42,6 +56,11 @@ pub async fn get_messages(
                 file_url: a.file_url.clone(),
                 file_type: a.file_type.clone(),
                 file_size: a.file_size,
+                width: a.width,
+                height: a.height,
+                duration: a.duration_secs,
+                placeholder: a.placeholder.clone(),
+                thumbnail_url: a.thumbnail_url.clone(),
             })
             .collect();
             
@@ -54,30 +73,42 @@ pub async fn get_messages(
             timestamp: m.timestamp,
             edited: m.edited_at.is_some(),
             attachments: if msg_attachments.is_empty() { None } else { Some(msg_attachments) },
+            message_type: m.message_type,
+            reference_message_id: m.reference_message_id,
+            pinned: m.pinned_at.is_some(),
         });
     }
     
-    Ok(Json(result))
+    Ok(result)
 }
 
 pub async fn send_message(
     State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
     Path(channel): Path<String>,
     Json(payload): Json<SendMessageRequest>,
 ) -> Result<Json<Message>, StatusCode> {
+    if payload.text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
     let message_id = Uuid::new_v4();
     let timestamp = chrono::Utc::now().format("%I:%M %p").to_string();
 
-    // Get author_id from username
-    let author_id = sqlx::query_scalar!(
-        "SELECT id FROM users WHERE username = $1",
-        payload.author
+    // Messages are always sent as the signed-in user, so the author has to be their username
+    let user_id = Uuid::parse_str(&user_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let username = sqlx::query_scalar!(
+        "SELECT username FROM users WHERE id = $1",
+        user_id
     )
     .fetch_optional(&state.db)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    let user_id = author_id.ok_or(StatusCode::UNAUTHORIZED)?;
+    if username != payload.author {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     // Parse channel ID and check permissions
     let channel_uuid = Uuid::parse_str(&channel).map_err(|_| StatusCode::BAD_REQUEST)?;
@@ -96,6 +127,59 @@ pub async fn send_message(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // Enforce per-channel slowmode (members with MANAGE_MESSAGES are exempt)
+    let channel_settings = sqlx::query!(
+        "SELECT guild_id, slowmode_seconds FROM channels WHERE id = $1",
+        channel_uuid
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    // Rules screen and verification level must be satisfied before posting
+    crate::handlers::screening::check_member_can_participate(&state, channel_settings.guild_id, user_id).await?;
+
+    // Members timed out by AutoMod can't send until the timeout expires
+    if crate::handlers::automod::is_timed_out(&state.db, channel_settings.guild_id, user_id).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let slowmode_seconds = channel_settings.slowmode_seconds.unwrap_or(0);
+    if slowmode_seconds > 0
+        && !crate::handlers::roles::has_permission(&state, user_id, channel_settings.guild_id, crate::handlers::roles::MANAGE_MESSAGES).await?
+    {
+        let last_sent = sqlx::query_scalar!(
+            "SELECT MAX(created_at) FROM messages WHERE channel = $1 AND author_id = $2 AND message_type = $3",
+            channel,
+            user_id,
+            crate::handlers::system_messages::MESSAGE_TYPE_DEFAULT
+        )
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if let Some(last_sent) = last_sent {
+            let elapsed = chrono::Utc::now().signed_duration_since(last_sent).num_seconds();
+            if elapsed < slowmode_seconds as i64 {
+                return Err(StatusCode::TOO_MANY_REQUESTS);
+            }
+        }
+    }
+
+    let automod_context = crate::handlers::automod::AutoModContext {
+        guild_id: channel_settings.guild_id,
+        channel_id: channel_uuid,
+        author_id: user_id,
+        author: &payload.author,
+        message_id,
+        text: &payload.text,
+    };
+    let automod_verdict = crate::handlers::automod::evaluate_message(&state, &automod_context).await?;
+    if automod_verdict.blocked {
+        automod_verdict.carry_out(&state, &automod_context).await;
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
     let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     sqlx::query!(
@@ -116,18 +200,35 @@ pub async fn send_message(
     if let Some(attachments) = payload.attachments {
         for attachment in attachments {
             let attachment_id = Uuid::new_v4();
+            let media = crate::handlers::files::attachment_media(&mut tx, &attachment.file_url, user_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
             sqlx::query!(
-                "INSERT INTO message_attachments (id, message_id, filename, file_url, file_type, file_size) VALUES ($1, $2, $3, $4, $5, $6)",
+                r#"
+                INSERT INTO message_attachments (id, message_id, filename, file_url, file_id, file_type, file_size, width, height, duration_secs, placeholder, thumbnail_url)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                "#,
                 attachment_id,
                 message_id,
                 attachment.filename,
                 attachment.file_url,
+                crate::handlers::files::file_id_from_url(&attachment.file_url),
                 attachment.file_type,
-                attachment.file_size
+                attachment.file_size,
+                media.width,
+                media.height,
+                media.duration_secs,
+                media.placeholder,
+                media.thumbnail_url
             )
             .execute(&mut *tx)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            crate::handlers::files::link_attachment(&mut tx, &attachment.file_url, user_id, message_id, Some(channel_uuid), None)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             
             saved_attachments.push(Attachment {
                 id: attachment_id,
@@ -135,12 +236,44 @@ pub async fn send_message(
                 file_url: attachment.file_url.clone(),
                 file_type: attachment.file_type.clone(),
                 file_size: attachment.file_size,
+                width: media.width,
+                height: media.height,
+                duration: media.duration_secs,
+                placeholder: media.placeholder,
+                thumbnail_url: media.thumbnail_url,
             });
         }
     }
 
     tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Alerts and timeouts for flagged messages only once the message actually exists
+    automod_verdict.carry_out(&state, &automod_context).await;
+
+    // Keep the guild's activity timestamp fresh for discovery ranking, at most every few minutes
+    let _ = sqlx::query!(
+        "UPDATE guilds SET last_activity_at = NOW() WHERE id = $1 AND (last_activity_at IS NULL OR last_activity_at < NOW() - INTERVAL '5 minutes')",
+        channel_settings.guild_id
+    )
+    .execute(&state.db)
+    .await;
+
+    // And the member's, so active members are never pruned
+    crate::handlers::prune::touch_member_activity(&state.db, channel_settings.guild_id, user_id).await;
+
+    // Mentions and notification settings decide who hears about the message
+    {
+        let state = state.clone();
+        let guild_id = channel_settings.guild_id;
+        let author = payload.author.clone();
+        let text = payload.text.clone();
+        tokio::spawn(async move {
+            let _ = crate::handlers::notifications::dispatch_message_notifications(
+                &state, guild_id, channel_uuid, message_id, user_id, &author, &text,
+            ).await;
+        });
+    }
+
     // Track stats and award badges
     let new_count = crate::stats::increment_stat(&state.db, &user_id, "messages_sent", 1).await.unwrap_or(0);
     let awarded_badges = crate::handlers::badges::check_and_award_badges(&state, &user_id, "messages_sent", new_count).await.unwrap_or_default();
@@ -166,6 +299,9 @@ pub async fn send_message(
         timestamp,
         edited: false,
         attachments: if saved_attachments.is_empty() { None } else { Some(saved_attachments) },
+        message_type: crate::handlers::system_messages::MESSAGE_TYPE_DEFAULT.to_string(),
+        reference_message_id: None,
+        pinned: false,
     }))
 }
 
@@ -174,18 +310,66 @@ pub struct EditMessageRequest {
     pub text: String,
 }
 
-// Edit a message (simplified - no auth for now)
+// Edit a message; only its author can
 pub async fn edit_message(
     State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
     Path((channel, message_id)): Path<(String, String)>,
     Json(payload): Json<EditMessageRequest>,
 ) -> Result<StatusCode, StatusCode> {
+    let user_id = Uuid::parse_str(&user_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
     let message_uuid = Uuid::parse_str(&message_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if payload.text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Edits go through AutoMod just like new messages
+    let message = sqlx::query!(
+        "SELECT channel, author, author_id FROM messages WHERE id = $1 AND deleted = false",
+        message_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if message.channel != channel {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if message.author_id != Some(user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut automod = None;
+    if let Ok(channel_id) = Uuid::parse_str(&message.channel) {
+        let guild_id = sqlx::query_scalar!("SELECT guild_id FROM channels WHERE id = $1", channel_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if let Some(guild_id) = guild_id {
+            let automod_context = crate::handlers::automod::AutoModContext {
+                guild_id,
+                channel_id,
+                author_id: user_id,
+                author: &message.author,
+                message_id: message_uuid,
+                text: &payload.text,
+            };
+            let automod_verdict = crate::handlers::automod::evaluate_message(&state, &automod_context).await?;
+            if automod_verdict.blocked {
+                automod_verdict.carry_out(&state, &automod_context).await;
+                return Err(StatusCode::UNPROCESSABLE_ENTITY);
+            }
+            automod = Some((automod_context, automod_verdict));
+        }
+    }
 
     let result = sqlx::query!(
-        "UPDATE messages SET text = $1, edited_at = NOW() WHERE id = $2 AND deleted = false",
+        "UPDATE messages SET text = $1, edited_at = NOW() WHERE id = $2 AND author_id = $3 AND deleted = false",
         payload.text,
-        message_uuid
+        message_uuid,
+        user_id
     )
     .execute(&state.db)
     .await
@@ -195,12 +379,17 @@ pub async fn edit_message(
         return Err(StatusCode::NOT_FOUND);
     }
 
+    if let Some((automod_context, automod_verdict)) = automod {
+        automod_verdict.carry_out(&state, &automod_context).await;
+    }
+
     Ok(StatusCode::OK)
 }
 
 // Delete a message
 pub async fn delete_message(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path((channel, message_id)): Path<(String, String)>,
 ) -> Result<StatusCode, StatusCode> {
     let message_uuid = Uuid::parse_str(&message_id).map_err(|e| {
@@ -265,7 +454,8 @@ pub async fn delete_message(
                 "message_delete",
                 Some("message"),
                 Some(message_uuid),
-                Some(details)
+                Some(details),
+                crate::handlers::audit_logs::audit_log_reason(&headers)
             ).await;
         }
     }
@@ -17,5 +17,18 @@ pub mod audit_logs;
 pub mod emoji;
 pub mod uploads;
 pub mod files;
+pub mod file_store;
 pub mod admin;
 pub mod webrtc;
+pub mod templates;
+pub mod automod;
+pub mod screening;
+pub mod system_messages;
+pub mod discovery;
+pub mod events;
+pub mod onboarding;
+pub mod notifications;
+pub mod push;
+pub mod exports;
+pub mod member_list;
+pub mod prune;
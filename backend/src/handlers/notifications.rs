@@ -0,0 +1,608 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, MENTION_EVERYONE};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+pub const LEVEL_ALL: &str = "all";
+pub const LEVEL_MENTIONS: &str = "mentions";
+pub const LEVEL_NOTHING: &str = "nothing";
+
+pub const SCOPE_GUILD: &str = "guild";
+pub const SCOPE_CHANNEL: &str = "channel";
+pub const SCOPE_DM: &str = "dm";
+
+// Used when neither the channel nor the guild has a level set
+const DEFAULT_GUILD_LEVEL: &str = LEVEL_MENTIONS;
+const DEFAULT_DM_LEVEL: &str = LEVEL_ALL;
+
+pub const NOTIFICATION_PREVIEW_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MentionKind {
+    None,
+    // @everyone / @here
+    Everyone,
+    // @username
+    Direct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationDecision {
+    // Whether the message should alert the user (gateway notification, push)
+    pub notify: bool,
+    // Whether the message counts towards the channel's unread mention count
+    pub count_mention: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ScopeSettings {
+    pub level: Option<String>,
+    pub muted: bool,
+    pub muted_until: Option<DateTime<Utc>>,
+    pub suppress_everyone: bool,
+}
+
+impl ScopeSettings {
+    fn is_muted(&self, now: DateTime<Utc>) -> bool {
+        self.muted && self.muted_until.is_none_or(|until| until > now)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationSettingsResponse {
+    pub scope_type: String,
+    pub scope_id: Uuid,
+    pub level: Option<String>,
+    pub muted: bool,
+    pub muted_until: Option<String>,
+    pub suppress_everyone: bool,
+}
+
+// Omitted fields keep their current value
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationSettingsRequest {
+    // null inherits from the guild (for channels) or the default
+    #[serde(default, deserialize_with = "crate::models::double_option")]
+    pub level: Option<Option<String>>,
+    pub muted: Option<bool>,
+    // A time mutes until then; null makes a mute indefinite
+    #[serde(default, deserialize_with = "crate::models::double_option")]
+    pub muted_until: Option<Option<DateTime<Utc>>>,
+    pub suppress_everyone: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MentionCountResponse {
+    pub channel_id: Uuid,
+    pub guild_id: Uuid,
+    pub mention_count: i32,
+    pub last_read_at: Option<String>,
+}
+
+// Combine channel and guild settings into a decision for one message. Channel settings win
+// over guild settings; a mute on either silences notifications, but direct mentions still
+// count towards the unread badge unless the level is "nothing".
+pub fn resolve_decision(
+    guild: &ScopeSettings,
+    channel: &ScopeSettings,
+    mention: MentionKind,
+    now: DateTime<Utc>,
+) -> NotificationDecision {
+    let level = channel
+        .level
+        .as_deref()
+        .or(guild.level.as_deref())
+        .unwrap_or(DEFAULT_GUILD_LEVEL);
+
+    let mention = if mention == MentionKind::Everyone && (guild.suppress_everyone || channel.suppress_everyone) {
+        MentionKind::None
+    } else {
+        mention
+    };
+    let mentioned = mention != MentionKind::None;
+
+    if level == LEVEL_NOTHING {
+        return NotificationDecision { notify: false, count_mention: false };
+    }
+
+    let muted = guild.is_muted(now) || channel.is_muted(now);
+    let wants = level == LEVEL_ALL || mentioned;
+
+    NotificationDecision {
+        notify: wants && !muted,
+        count_mention: mentioned && (!muted || mention == MentionKind::Direct),
+    }
+}
+
+// Pull `@everyone`/`@here` and `@username` mentions out of a message
+pub fn parse_mentions(text: &str) -> (bool, Vec<String>) {
+    let mut everyone = false;
+    let mut usernames: Vec<String> = Vec::new();
+
+    for (index, _) in text.match_indices('@') {
+        // Ignore e-mail addresses and the like
+        if text[..index].chars().next_back().is_some_and(|c| c.is_alphanumeric()) {
+            continue;
+        }
+
+        let name: String = text[index + 1..]
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '.' || *c == '_' || *c == '-')
+            .collect();
+        let name = name.trim_end_matches('.').to_lowercase();
+
+        match name.as_str() {
+            "" => {}
+            "everyone" | "here" => everyone = true,
+            _ => {
+                if !usernames.contains(&name) {
+                    usernames.push(name);
+                }
+            }
+        }
+    }
+
+    (everyone, usernames)
+}
+
+fn settings_from_row(
+    level: Option<String>,
+    muted: Option<bool>,
+    muted_until: Option<DateTime<Utc>>,
+    suppress_everyone: Option<bool>,
+) -> ScopeSettings {
+    ScopeSettings {
+        level,
+        muted: muted.unwrap_or(false),
+        muted_until,
+        suppress_everyone: suppress_everyone.unwrap_or(false),
+    }
+}
+
+// Decide whether a new guild message notifies the members it concerns, bump their unread
+// mention counts and tell them over the gateway. Returns the users that were notified.
+pub async fn dispatch_message_notifications(
+    state: &AppState,
+    guild_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    author_id: Uuid,
+    author: &str,
+    text: &str,
+) -> Result<Vec<Uuid>, StatusCode> {
+    let (everyone, usernames) = parse_mentions(text);
+
+    // Only members allowed to mention everyone can ping the whole guild
+    let everyone = everyone && has_permission(state, author_id, guild_id, MENTION_EVERYONE).await?;
+
+    let mentioned_ids = if usernames.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_scalar!(
+            "SELECT id FROM users WHERE LOWER(username) = ANY($1)",
+            &usernames
+        )
+        .fetch_all(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+
+    // Candidates: members who were mentioned, or who asked to hear about every message
+    let candidates = sqlx::query!(
+        r#"
+        SELECT gm.user_id,
+               gs.level as "guild_level?", gs.muted as "guild_muted?", gs.muted_until as "guild_muted_until?",
+               gs.suppress_everyone as "guild_suppress_everyone?",
+               cs.level as "channel_level?", cs.muted as "channel_muted?", cs.muted_until as "channel_muted_until?",
+               cs.suppress_everyone as "channel_suppress_everyone?"
+        FROM guild_members gm
+        LEFT JOIN notification_settings gs
+            ON gs.user_id = gm.user_id AND gs.scope_type = 'guild' AND gs.scope_id = $1
+        LEFT JOIN notification_settings cs
+            ON cs.user_id = gm.user_id AND cs.scope_type = 'channel' AND cs.scope_id = $2
+        WHERE gm.guild_id = $1
+          AND gm.user_id <> $3
+          AND (gm.user_id = ANY($4) OR $5 OR gs.level = 'all' OR cs.level = 'all')
+        "#,
+        guild_id,
+        channel_id,
+        author_id,
+        &mentioned_ids,
+        everyone
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let now = Utc::now();
+    let preview: String = text.chars().take(NOTIFICATION_PREVIEW_LENGTH).collect();
+    let mut notified = Vec::new();
+
+    for candidate in candidates {
+        let mention = if mentioned_ids.contains(&candidate.user_id) {
+            MentionKind::Direct
+        } else if everyone {
+            MentionKind::Everyone
+        } else {
+            MentionKind::None
+        };
+
+        let guild = settings_from_row(candidate.guild_level, candidate.guild_muted, candidate.guild_muted_until, candidate.guild_suppress_everyone);
+        let channel = settings_from_row(candidate.channel_level, candidate.channel_muted, candidate.channel_muted_until, candidate.channel_suppress_everyone);
+        let decision = resolve_decision(&guild, &channel, mention, now);
+
+        if !decision.notify && !decision.count_mention {
+            continue;
+        }
+
+        // Nobody hears about messages in channels they can't see
+        let can_view = crate::permissions::check_channel_permission(&state.db, candidate.user_id, channel_id, "view")
+            .await
+            .unwrap_or(false);
+        if !can_view {
+            continue;
+        }
+
+        if decision.count_mention {
+            let _ = sqlx::query!(
+                r#"
+                INSERT INTO channel_read_states (user_id, channel_id, mention_count)
+                VALUES ($1, $2, 1)
+                ON CONFLICT (user_id, channel_id) DO UPDATE SET mention_count = channel_read_states.mention_count + 1
+                "#,
+                candidate.user_id,
+                channel_id
+            )
+            .execute(&state.db)
+            .await;
+        }
+
+        if decision.notify {
+            let notification = serde_json::json!({
+                "type": "message_notification",
+                "guild_id": guild_id,
+                "channel_id": channel_id,
+                "message_id": message_id,
+                "author_id": author_id,
+                "author": author,
+                "content": preview,
+                "mentioned": mention != MentionKind::None,
+            });
+
+            // Users without an open gateway connection get it on their devices instead
+            let user_key = candidate.user_id.to_string();
+            if state.ws_state.is_user_connected(&user_key).await {
+                state.ws_state.send_to_user(&user_key, notification).await;
+            } else if state.push.is_enabled() {
+                let _ = crate::push::enqueue(&state.db, candidate.user_id, notification).await;
+            }
+            notified.push(candidate.user_id);
+        }
+    }
+
+    Ok(notified)
+}
+
+// Whether a DM should notify its recipient; every DM counts as a direct mention
+pub async fn should_notify_dm(db: &sqlx::PgPool, user_id: Uuid, dm_id: Uuid) -> Result<bool, StatusCode> {
+    let settings = sqlx::query!(
+        "SELECT level, muted, muted_until FROM notification_settings WHERE user_id = $1 AND scope_type = 'dm' AND scope_id = $2",
+        user_id,
+        dm_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let Some(settings) = settings else {
+        return Ok(DEFAULT_DM_LEVEL != LEVEL_NOTHING);
+    };
+
+    let scope = ScopeSettings {
+        level: settings.level,
+        muted: settings.muted,
+        muted_until: settings.muted_until,
+        suppress_everyone: false,
+    };
+
+    Ok(scope.level.as_deref().unwrap_or(DEFAULT_DM_LEVEL) != LEVEL_NOTHING && !scope.is_muted(Utc::now()))
+}
+
+// Make sure the user actually belongs to what they are configuring
+async fn check_scope_access(db: &sqlx::PgPool, user_id: Uuid, scope_type: &str, scope_id: Uuid) -> Result<(), StatusCode> {
+    let allowed = match scope_type {
+        SCOPE_GUILD => sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2) as "allowed!""#,
+            scope_id,
+            user_id
+        )
+        .fetch_one(db)
+        .await,
+        SCOPE_CHANNEL => sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM channels c JOIN guild_members gm ON gm.guild_id = c.guild_id WHERE c.id = $1 AND gm.user_id = $2) as "allowed!""#,
+            scope_id,
+            user_id
+        )
+        .fetch_one(db)
+        .await,
+        SCOPE_DM => sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM direct_messages WHERE id = $1 AND (user1_id = $2 OR user2_id = $2)) as "allowed!""#,
+            scope_id,
+            user_id
+        )
+        .fetch_one(db)
+        .await,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !allowed {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(())
+}
+
+// List all of the user's notification settings
+pub async fn get_notification_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<NotificationSettingsResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let settings = sqlx::query!(
+        "SELECT scope_type, scope_id, level, muted, muted_until, suppress_everyone FROM notification_settings WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(settings.into_iter().map(|s| NotificationSettingsResponse {
+        scope_type: s.scope_type,
+        scope_id: s.scope_id,
+        level: s.level,
+        muted: s.muted,
+        muted_until: s.muted_until.map(|dt| dt.to_rfc3339()),
+        suppress_everyone: s.suppress_everyone,
+    }).collect()))
+}
+
+// Set the notification settings of a guild, channel or DM
+pub async fn update_notification_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((scope_type, scope_id)): axum::extract::Path<(String, Uuid)>,
+    Json(payload): Json<UpdateNotificationSettingsRequest>,
+) -> Result<Json<NotificationSettingsResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_scope_access(&state.db, user_id, &scope_type, scope_id).await?;
+
+    if let Some(Some(level)) = &payload.level {
+        if level != LEVEL_ALL && level != LEVEL_MENTIONS && level != LEVEL_NOTHING {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    // A mute-until in the past would be a no-op that looks like a mute
+    if payload.muted_until.flatten().is_some_and(|until| until <= Utc::now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Setting a mute-until mutes; unmuting drops the mute-until
+    let muted = payload.muted.or(payload.muted_until.flatten().map(|_| true));
+    let muted_until = if muted == Some(false) { Some(None) } else { payload.muted_until };
+
+    let settings = sqlx::query!(
+        r#"
+        INSERT INTO notification_settings (user_id, scope_type, scope_id, level, muted, muted_until, suppress_everyone, updated_at)
+        VALUES ($1, $2, $3, $5, COALESCE($6, false), $8, COALESCE($9, false), NOW())
+        ON CONFLICT (user_id, scope_type, scope_id) DO UPDATE
+        SET level = CASE WHEN $4 THEN EXCLUDED.level ELSE notification_settings.level END,
+            muted = COALESCE($6, notification_settings.muted),
+            muted_until = CASE WHEN $7 THEN EXCLUDED.muted_until ELSE notification_settings.muted_until END,
+            suppress_everyone = COALESCE($9, notification_settings.suppress_everyone),
+            updated_at = NOW()
+        RETURNING level, muted, muted_until, suppress_everyone
+        "#,
+        user_id,
+        scope_type,
+        scope_id,
+        payload.level.is_some(),
+        payload.level.flatten(),
+        muted,
+        muted_until.is_some(),
+        muted_until.flatten(),
+        payload.suppress_everyone
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(NotificationSettingsResponse {
+        scope_type,
+        scope_id,
+        level: settings.level,
+        muted: settings.muted,
+        muted_until: settings.muted_until.map(|dt| dt.to_rfc3339()),
+        suppress_everyone: settings.suppress_everyone,
+    }))
+}
+
+// Go back to the inherited settings
+pub async fn reset_notification_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((scope_type, scope_id)): axum::extract::Path<(String, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    sqlx::query!(
+        "DELETE FROM notification_settings WHERE user_id = $1 AND scope_type = $2 AND scope_id = $3",
+        user_id,
+        scope_type,
+        scope_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Unread mention counts for every channel that has any
+pub async fn get_mention_counts(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<MentionCountResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let counts = sqlx::query!(
+        r#"
+        SELECT rs.channel_id, c.guild_id, rs.mention_count, rs.last_read_at
+        FROM channel_read_states rs
+        JOIN channels c ON c.id = rs.channel_id
+        WHERE rs.user_id = $1 AND rs.mention_count > 0
+        "#,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(counts.into_iter().map(|c| MentionCountResponse {
+        channel_id: c.channel_id,
+        guild_id: c.guild_id,
+        mention_count: c.mention_count,
+        last_read_at: c.last_read_at.map(|dt| dt.to_rfc3339()),
+    }).collect()))
+}
+
+// Mark a channel as read, clearing its unread mentions
+pub async fn ack_channel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(channel_id): axum::extract::Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_scope_access(&state.db, user_id, SCOPE_CHANNEL, channel_id).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO channel_read_states (user_id, channel_id, mention_count, last_read_at)
+        VALUES ($1, $2, 0, NOW())
+        ON CONFLICT (user_id, channel_id) DO UPDATE SET mention_count = 0, last_read_at = NOW()
+        "#,
+        user_id,
+        channel_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(level: Option<&str>, muted: bool, suppress_everyone: bool) -> ScopeSettings {
+        ScopeSettings {
+            level: level.map(str::to_string),
+            muted,
+            muted_until: None,
+            suppress_everyone,
+        }
+    }
+
+    fn decision(notify: bool, count_mention: bool) -> NotificationDecision {
+        NotificationDecision { notify, count_mention }
+    }
+
+    #[test]
+    fn guild_default_notifies_on_mentions_only() {
+        let now = Utc::now();
+        let unset = ScopeSettings::default();
+        assert_eq!(resolve_decision(&unset, &unset, MentionKind::None, now), decision(false, false));
+        assert_eq!(resolve_decision(&unset, &unset, MentionKind::Direct, now), decision(true, true));
+        assert_eq!(resolve_decision(&unset, &unset, MentionKind::Everyone, now), decision(true, true));
+    }
+
+    #[test]
+    fn channel_level_overrides_guild_level() {
+        let now = Utc::now();
+        let guild = scope(Some(LEVEL_NOTHING), false, false);
+        let channel = scope(Some(LEVEL_ALL), false, false);
+        assert_eq!(resolve_decision(&guild, &channel, MentionKind::None, now), decision(true, false));
+        assert_eq!(resolve_decision(&channel, &guild, MentionKind::Direct, now), decision(false, false));
+    }
+
+    #[test]
+    fn mutes_silence_but_direct_mentions_still_count() {
+        let now = Utc::now();
+        let muted = scope(Some(LEVEL_ALL), true, false);
+        let unset = ScopeSettings::default();
+        assert_eq!(resolve_decision(&muted, &unset, MentionKind::Direct, now), decision(false, true));
+        assert_eq!(resolve_decision(&unset, &muted, MentionKind::Everyone, now), decision(false, false));
+
+        // Expired mutes no longer apply
+        let expired = ScopeSettings {
+            muted_until: Some(now - chrono::Duration::minutes(1)),
+            ..muted
+        };
+        assert_eq!(resolve_decision(&expired, &unset, MentionKind::None, now), decision(true, false));
+    }
+
+    #[test]
+    fn suppressed_everyone_is_not_a_mention() {
+        let now = Utc::now();
+        let suppressed = scope(None, false, true);
+        let unset = ScopeSettings::default();
+        assert_eq!(resolve_decision(&suppressed, &unset, MentionKind::Everyone, now), decision(false, false));
+        assert_eq!(resolve_decision(&unset, &suppressed, MentionKind::Direct, now), decision(true, true));
+    }
+
+    #[test]
+    fn parses_mentions() {
+        assert_eq!(parse_mentions("hi @Alice and @bob_1, also @alice."), (false, vec!["alice".to_string(), "bob_1".to_string()]));
+        assert_eq!(parse_mentions("@here look"), (true, vec![]));
+        assert_eq!(parse_mentions("ping @everyone and @carol-x"), (true, vec!["carol-x".to_string()]));
+        // E-mail addresses and lone @ signs aren't mentions
+        assert_eq!(parse_mentions("mail me@example.com or @ someone"), (false, vec![]));
+    }
+}
@@ -0,0 +1,891 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{
+    has_permission, outranks_role, ADMINISTRATOR, BAN_MEMBERS, DEAFEN_MEMBERS, KICK_MEMBERS,
+    MANAGE_CHANNELS, MANAGE_EVENTS, MANAGE_GUILD, MANAGE_MESSAGES, MANAGE_NICKNAMES, MANAGE_ROLES,
+    MUTE_MEMBERS, VIEW_AUDIT_LOG,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+// Roles carrying any of these can never be handed out without a moderator
+const ELEVATED_PERMISSIONS: i64 = ADMINISTRATOR
+    | MANAGE_GUILD
+    | MANAGE_ROLES
+    | MANAGE_CHANNELS
+    | KICK_MEMBERS
+    | BAN_MEMBERS
+    | MANAGE_MESSAGES
+    | MANAGE_NICKNAMES
+    | MUTE_MEMBERS
+    | DEAFEN_MEMBERS
+    | VIEW_AUDIT_LOG
+    | MANAGE_EVENTS;
+
+const MAX_GROUPS_PER_GUILD: i64 = 25;
+const MAX_SELF_ROLES_PER_GUILD: i64 = 100;
+const MAX_REACTION_ROLES_PER_MESSAGE: i64 = 20;
+const MAX_GROUP_NAME_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct SelfRoleGroupResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub multiple_choice: bool,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfRoleResponse {
+    pub role_id: Uuid,
+    pub name: String,
+    pub color: String,
+    pub group_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub emoji: Option<String>,
+    // Whether the requesting member currently holds the role
+    pub assigned: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnboardingResponse {
+    pub groups: Vec<SelfRoleGroupResponse>,
+    pub roles: Vec<SelfRoleResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionRoleResponse {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub message_id: Uuid,
+    pub emoji: String,
+    pub role_id: Uuid,
+    pub creator_id: Option<Uuid>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSelfRoleGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub multiple_choice: Option<bool>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSelfRoleGroupRequest {
+    pub name: Option<String>,
+    // An empty string clears the description
+    pub description: Option<String>,
+    pub multiple_choice: Option<bool>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetSelfRoleRequest {
+    pub group_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub emoji: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReactionRoleRequest {
+    pub channel_id: Uuid,
+    pub message_id: Uuid,
+    pub emoji: String,
+    pub role_id: Uuid,
+}
+
+// A role can be self-assigned or bound to a reaction only by someone with MANAGE_ROLES who
+// outranks it, and only if it carries no moderation permissions
+async fn check_role_manageable(
+    state: &AppState,
+    user_id: Uuid,
+    guild_id: Uuid,
+    role_id: Uuid,
+) -> Result<(), StatusCode> {
+    if !has_permission(state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // The @everyone role shares the guild's id and is held by everyone anyway
+    if role_id == guild_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let role = sqlx::query!(
+        "SELECT position, permissions FROM roles WHERE id = $1 AND guild_id = $2",
+        role_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if role.permissions & ELEVATED_PERMISSIONS != 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if !outranks_role(state, user_id, guild_id, role.position).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+// Give a member a role on their own behalf. Picking a role from a single-choice group drops
+// the other roles of that group. Returns whether the role was newly added.
+async fn grant_role(state: &AppState, guild_id: Uuid, user_id: Uuid, role_id: Uuid) -> Result<bool, StatusCode> {
+    // Checked again here since the role may have been given more permissions since it was set up
+    let permissions = sqlx::query_scalar!(
+        "SELECT permissions FROM roles WHERE id = $1 AND guild_id = $2",
+        role_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if permissions & ELEVATED_PERMISSIONS != 0 {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM role_members
+        WHERE guild_id = $1 AND user_id = $2 AND role_id IN (
+            SELECT other.role_id
+            FROM self_assignable_roles picked
+            JOIN self_role_groups g ON g.id = picked.group_id AND NOT g.multiple_choice
+            JOIN self_assignable_roles other ON other.group_id = g.id AND other.role_id <> picked.role_id
+            WHERE picked.role_id = $3
+        )
+        "#,
+        guild_id,
+        user_id,
+        role_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO role_members (role_id, user_id, guild_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (role_id, user_id, guild_id) DO NOTHING
+        "#,
+        role_id,
+        user_id,
+        guild_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    crate::handlers::member_list::member_changed(state, guild_id, user_id).await;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn revoke_role(state: &AppState, guild_id: Uuid, user_id: Uuid, role_id: Uuid) -> Result<bool, StatusCode> {
+    let result = sqlx::query!(
+        "DELETE FROM role_members WHERE role_id = $1 AND user_id = $2 AND guild_id = $3",
+        role_id,
+        user_id,
+        guild_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() > 0 {
+        crate::handlers::member_list::member_changed(state, guild_id, user_id).await;
+    }
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Grant or remove the role bound to a reaction, if there is one. Called by the reaction
+// handlers after a reaction on a guild message was added or removed.
+pub async fn apply_reaction_role(
+    state: &AppState,
+    guild_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    emoji: &str,
+    added: bool,
+) -> Result<(), StatusCode> {
+    let binding = sqlx::query!(
+        "SELECT id, role_id FROM reaction_roles WHERE guild_id = $1 AND message_id = $2 AND emoji = $3",
+        guild_id,
+        message_id,
+        emoji
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let Some(binding) = binding else {
+        return Ok(());
+    };
+
+    let changed = if added {
+        grant_role(state, guild_id, user_id, binding.role_id).await?
+    } else {
+        revoke_role(state, guild_id, user_id, binding.role_id).await?
+    };
+
+    if changed {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            if added { "member_role_add" } else { "member_role_remove" },
+            Some("user"),
+            Some(user_id),
+            Some(serde_json::json!({ "role_id": binding.role_id, "reaction_role_id": binding.id })),
+            None
+        ).await;
+    }
+
+    Ok(())
+}
+
+async fn load_groups(db: &sqlx::PgPool, guild_id: Uuid) -> Result<Vec<SelfRoleGroupResponse>, StatusCode> {
+    let groups = sqlx::query!(
+        "SELECT id, name, description, multiple_choice, position FROM self_role_groups WHERE guild_id = $1 ORDER BY position, created_at",
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(groups.into_iter().map(|g| SelfRoleGroupResponse {
+        id: g.id,
+        name: g.name,
+        description: g.description,
+        multiple_choice: g.multiple_choice,
+        position: g.position,
+    }).collect())
+}
+
+// Get the roles members can pick for themselves, grouped
+pub async fn get_onboarding(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<OnboardingResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let is_member = sqlx::query_scalar!(
+        "SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+        guild_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if is_member.is_none() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let groups = load_groups(&state.db, guild_id).await?;
+
+    let roles = sqlx::query!(
+        r#"
+        SELECT s.role_id, r.name, r.color, s.group_id, s.description, s.emoji,
+               EXISTS(SELECT 1 FROM role_members rm WHERE rm.role_id = s.role_id AND rm.user_id = $2) as "assigned!"
+        FROM self_assignable_roles s
+        JOIN roles r ON r.id = s.role_id
+        WHERE s.guild_id = $1
+        ORDER BY r.position DESC
+        "#,
+        guild_id,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(OnboardingResponse {
+        groups,
+        roles: roles.into_iter().map(|r| SelfRoleResponse {
+            role_id: r.role_id,
+            name: r.name,
+            color: r.color.unwrap_or_else(|| "#99aab5".to_string()),
+            group_id: r.group_id,
+            description: r.description,
+            emoji: r.emoji,
+            assigned: r.assigned,
+        }).collect(),
+    }))
+}
+
+// Create a group of self-assignable roles
+pub async fn create_self_role_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateSelfRoleGroupRequest>,
+) -> Result<Json<SelfRoleGroupResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_GROUP_NAME_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let description = payload.description.filter(|d| !d.trim().is_empty());
+    if description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let group_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM self_role_groups WHERE guild_id = $1"#,
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if group_count >= MAX_GROUPS_PER_GUILD {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let group = SelfRoleGroupResponse {
+        id: Uuid::new_v4(),
+        name,
+        description,
+        multiple_choice: payload.multiple_choice.unwrap_or(true),
+        position: payload.position.unwrap_or(group_count as i32),
+    };
+
+    sqlx::query!(
+        "INSERT INTO self_role_groups (id, guild_id, name, description, multiple_choice, position) VALUES ($1, $2, $3, $4, $5, $6)",
+        group.id,
+        guild_id,
+        group.name,
+        group.description,
+        group.multiple_choice,
+        group.position
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "self_role_group_create",
+        Some("self_role_group"),
+        Some(group.id),
+        Some(serde_json::json!({ "name": group.name, "multiple_choice": group.multiple_choice })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(group))
+}
+
+// Update a group of self-assignable roles
+pub async fn update_self_role_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, group_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateSelfRoleGroupRequest>,
+) -> Result<Json<SelfRoleGroupResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let existing = sqlx::query!(
+        "SELECT name, description, multiple_choice, position FROM self_role_groups WHERE id = $1 AND guild_id = $2",
+        group_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let name = payload.name.map(|n| n.trim().to_string()).unwrap_or_else(|| existing.name.clone());
+    if name.is_empty() || name.chars().count() > MAX_GROUP_NAME_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let description = match payload.description {
+        Some(d) if d.trim().is_empty() => None,
+        Some(d) => Some(d),
+        None => existing.description.clone(),
+    };
+    if description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let group = SelfRoleGroupResponse {
+        id: group_id,
+        name,
+        description,
+        multiple_choice: payload.multiple_choice.unwrap_or(existing.multiple_choice),
+        position: payload.position.unwrap_or(existing.position),
+    };
+
+    sqlx::query!(
+        "UPDATE self_role_groups SET name = $1, description = $2, multiple_choice = $3, position = $4 WHERE id = $5",
+        group.name,
+        group.description,
+        group.multiple_choice,
+        group.position,
+        group_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "self_role_group_update",
+        Some("self_role_group"),
+        Some(group_id),
+        Some(serde_json::json!({
+            "before": { "name": existing.name, "multiple_choice": existing.multiple_choice, "position": existing.position },
+            "after": { "name": group.name, "multiple_choice": group.multiple_choice, "position": group.position },
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(group))
+}
+
+// Delete a group; its roles stay self-assignable, just ungrouped
+pub async fn delete_self_role_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, group_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let group = sqlx::query!(
+        "DELETE FROM self_role_groups WHERE id = $1 AND guild_id = $2 RETURNING name",
+        group_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "self_role_group_delete",
+        Some("self_role_group"),
+        Some(group_id),
+        Some(serde_json::json!({ "name": group.name })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Make a role self-assignable, or update how it is presented
+pub async fn set_self_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, role_id)): axum::extract::Path<(Uuid, Uuid)>,
+    Json(payload): Json<SetSelfRoleRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_role_manageable(&state, user_id, guild_id, role_id).await?;
+
+    let description = payload.description.filter(|d| !d.trim().is_empty());
+    if description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(group_id) = payload.group_id {
+        let group_exists = sqlx::query_scalar!(
+            "SELECT 1 FROM self_role_groups WHERE id = $1 AND guild_id = $2",
+            group_id,
+            guild_id
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if group_exists.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let role_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM self_assignable_roles WHERE guild_id = $1 AND role_id <> $2"#,
+        guild_id,
+        role_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if role_count >= MAX_SELF_ROLES_PER_GUILD {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO self_assignable_roles (role_id, guild_id, group_id, description, emoji)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (role_id) DO UPDATE
+        SET group_id = EXCLUDED.group_id, description = EXCLUDED.description, emoji = EXCLUDED.emoji
+        "#,
+        role_id,
+        guild_id,
+        payload.group_id,
+        description,
+        payload.emoji
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "self_role_update",
+        Some("role"),
+        Some(role_id),
+        Some(serde_json::json!({ "group_id": payload.group_id, "emoji": payload.emoji })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Stop a role from being self-assignable (members who picked it keep it)
+pub async fn remove_self_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, role_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let result = sqlx::query!(
+        "DELETE FROM self_assignable_roles WHERE role_id = $1 AND guild_id = $2",
+        role_id,
+        guild_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "self_role_remove",
+        Some("role"),
+        Some(role_id),
+        None,
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn ensure_self_assignable(db: &sqlx::PgPool, guild_id: Uuid, role_id: Uuid) -> Result<(), StatusCode> {
+    let assignable = sqlx::query_scalar!(
+        "SELECT 1 FROM self_assignable_roles WHERE role_id = $1 AND guild_id = $2",
+        role_id,
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if assignable.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(())
+}
+
+// Pick a self-assignable role
+pub async fn join_self_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, role_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    // Holding any role skips the verification level, so picking one must not be a way around it
+    crate::handlers::screening::check_member_can_participate(&state, guild_id, user_id).await?;
+
+    ensure_self_assignable(&state.db, guild_id, role_id).await?;
+
+    if grant_role(&state, guild_id, user_id, role_id).await? {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "member_role_add",
+            Some("user"),
+            Some(user_id),
+            Some(serde_json::json!({ "role_id": role_id, "self_assigned": true })),
+            None
+        ).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Drop a self-assignable role
+pub async fn leave_self_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, role_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    ensure_self_assignable(&state.db, guild_id, role_id).await?;
+
+    if revoke_role(&state, guild_id, user_id, role_id).await? {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "member_role_remove",
+            Some("user"),
+            Some(user_id),
+            Some(serde_json::json!({ "role_id": role_id, "self_assigned": true })),
+            None
+        ).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// List a guild's reaction-role bindings
+pub async fn get_reaction_roles(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<ReactionRoleResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let bindings = sqlx::query!(
+        "SELECT id, channel_id, message_id, emoji, role_id, creator_id, created_at FROM reaction_roles WHERE guild_id = $1 ORDER BY created_at",
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(bindings.into_iter().map(|b| ReactionRoleResponse {
+        id: b.id,
+        channel_id: b.channel_id,
+        message_id: b.message_id,
+        emoji: b.emoji,
+        role_id: b.role_id,
+        creator_id: b.creator_id,
+        created_at: b.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+    }).collect()))
+}
+
+// Bind a reaction on a message to a role
+pub async fn create_reaction_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateReactionRoleRequest>,
+) -> Result<Json<ReactionRoleResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    check_role_manageable(&state, user_id, guild_id, payload.role_id).await?;
+
+    let emoji = payload.emoji.trim().to_string();
+    if emoji.is_empty() || emoji.chars().count() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let message_in_guild = sqlx::query_scalar!(
+        r#"
+        SELECT 1 FROM messages m
+        JOIN channels c ON c.id::text = m.channel
+        WHERE m.id = $1 AND c.id = $2 AND c.guild_id = $3 AND m.deleted = false
+        "#,
+        payload.message_id,
+        payload.channel_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if message_in_guild.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let binding_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM reaction_roles WHERE message_id = $1"#,
+        payload.message_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if binding_count >= MAX_REACTION_ROLES_PER_MESSAGE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let binding = sqlx::query!(
+        r#"
+        INSERT INTO reaction_roles (id, guild_id, channel_id, message_id, emoji, role_id, creator_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (message_id, emoji) DO NOTHING
+        RETURNING id, created_at
+        "#,
+        Uuid::new_v4(),
+        guild_id,
+        payload.channel_id,
+        payload.message_id,
+        emoji,
+        payload.role_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::CONFLICT)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "reaction_role_create",
+        Some("reaction_role"),
+        Some(binding.id),
+        Some(serde_json::json!({
+            "message_id": payload.message_id,
+            "emoji": emoji,
+            "role_id": payload.role_id,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(ReactionRoleResponse {
+        id: binding.id,
+        channel_id: payload.channel_id,
+        message_id: payload.message_id,
+        emoji,
+        role_id: payload.role_id,
+        creator_id: Some(user_id),
+        created_at: binding.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+    }))
+}
+
+// Remove a reaction-role binding (members keep roles they already got from it)
+pub async fn delete_reaction_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, reaction_role_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_ROLES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let binding = sqlx::query!(
+        "DELETE FROM reaction_roles WHERE id = $1 AND guild_id = $2 RETURNING message_id, emoji, role_id",
+        reaction_role_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "reaction_role_delete",
+        Some("reaction_role"),
+        Some(reaction_role_id),
+        Some(serde_json::json!({
+            "message_id": binding.message_id,
+            "emoji": binding.emoji,
+            "role_id": binding.role_id,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -85,6 +85,7 @@ pub async fn update_presence(
         status: payload.status.clone(),
     };
     state.ws_state.broadcast_to_all(&serde_json::to_string(&broadcast_msg).unwrap()).await;
+    crate::handlers::member_list::presence_changed(&state, user_id, Some(&payload.status), now).await;
 
     Ok(Json(PresenceResponse {
         user_id: user_id.to_string(),
@@ -199,6 +200,8 @@ pub async fn heartbeat(
         }
     }
 
+    crate::handlers::member_list::presence_changed(&state, user_id, None, now).await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
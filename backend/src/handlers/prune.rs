@@ -0,0 +1,276 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, KICK_MEMBERS};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+const DEFAULT_PRUNE_DAYS: i32 = 30;
+const MIN_PRUNE_DAYS: i32 = 1;
+const MAX_PRUNE_DAYS: i32 = 365;
+// How many of the matching members a preview lists
+const MAX_PREVIEW_MEMBERS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct PruneQuery {
+    pub days: Option<i32>,
+    // Only members without any roles
+    #[serde(default)]
+    pub roleless_only: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PruneRequest {
+    pub days: Option<i32>,
+    #[serde(default)]
+    pub roleless_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PruneCandidate {
+    pub id: Uuid,
+    pub username: String,
+    pub last_active_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrunePreviewResponse {
+    pub pruned: usize,
+    pub members: Vec<PruneCandidate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PruneResponse {
+    pub pruned: u64,
+}
+
+// Record that a member did something in the guild. Written at most every few minutes.
+pub async fn touch_member_activity(db: &sqlx::PgPool, guild_id: Uuid, user_id: Uuid) {
+    let _ = sqlx::query!(
+        "UPDATE guild_members SET last_active_at = NOW() WHERE guild_id = $1 AND user_id = $2 AND last_active_at < NOW() - INTERVAL '5 minutes'",
+        guild_id,
+        user_id
+    )
+    .execute(db)
+    .await;
+}
+
+fn prune_days(days: Option<i32>) -> Result<i32, StatusCode> {
+    let days = days.unwrap_or(DEFAULT_PRUNE_DAYS);
+    if !(MIN_PRUNE_DAYS..=MAX_PRUNE_DAYS).contains(&days) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(days)
+}
+
+// Members inactive for `days` that the requester may remove: never the owner, and never
+// anyone whose highest role isn't below the requester's. Least recently active first.
+async fn find_prunable_members(
+    state: &AppState,
+    user_id: Uuid,
+    guild_id: Uuid,
+    days: i32,
+    roleless_only: bool,
+) -> Result<Vec<PruneCandidate>, StatusCode> {
+    if !has_permission(state, user_id, guild_id, KICK_MEMBERS).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let owner_id = sqlx::query_scalar!("SELECT owner_id FROM guilds WHERE id = $1", guild_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let requester_position = if owner_id == user_id {
+        i32::MAX
+    } else {
+        sqlx::query_scalar!(
+            r#"
+            SELECT MAX(r.position)
+            FROM roles r
+            INNER JOIN role_members rm ON r.id = rm.role_id
+            WHERE rm.user_id = $1 AND rm.guild_id = $2
+            "#,
+            user_id,
+            guild_id
+        )
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .unwrap_or(-1)
+    };
+
+    let members = sqlx::query!(
+        r#"
+        SELECT gm.user_id, u.username, gm.last_active_at
+        FROM guild_members gm
+        INNER JOIN users u ON u.id = gm.user_id
+        LEFT JOIN LATERAL (
+            SELECT COUNT(*) AS role_count, MAX(r.position) AS top_position
+            FROM role_members rm
+            INNER JOIN roles r ON r.id = rm.role_id
+            WHERE rm.guild_id = gm.guild_id AND rm.user_id = gm.user_id AND rm.role_id <> gm.guild_id
+        ) member_roles ON TRUE
+        WHERE gm.guild_id = $1
+          AND gm.user_id <> $2
+          AND gm.last_active_at < NOW() - make_interval(days => $3)
+          AND (NOT $4 OR member_roles.role_count = 0)
+          AND COALESCE(member_roles.top_position, -1) < $5
+        ORDER BY gm.last_active_at, gm.user_id
+        "#,
+        guild_id,
+        owner_id,
+        days,
+        roleless_only,
+        requester_position
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(members
+        .into_iter()
+        .map(|m| PruneCandidate {
+            id: m.user_id,
+            username: m.username,
+            last_active_at: m.last_active_at.to_rfc3339(),
+        })
+        .collect())
+}
+
+// Dry run: how many members a prune with these settings would remove, and who they are
+pub async fn preview_prune(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<PruneQuery>,
+) -> Result<Json<PrunePreviewResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+    let days = prune_days(query.days)?;
+
+    let mut members = find_prunable_members(&state, user_id, guild_id, days, query.roleless_only).await?;
+    let pruned = members.len();
+    members.truncate(MAX_PREVIEW_MEMBERS);
+
+    Ok(Json(PrunePreviewResponse { pruned, members }))
+}
+
+// Remove inactive members
+pub async fn prune_members(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<PruneRequest>,
+) -> Result<Json<PruneResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+    let days = prune_days(payload.days)?;
+
+    let candidates = find_prunable_members(&state, user_id, guild_id, days, payload.roleless_only).await?;
+    let candidate_ids: Vec<Uuid> = candidates.iter().map(|c| c.id).collect();
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Members who became active since they were selected are kept
+    let pruned_ids = sqlx::query_scalar!(
+        r#"
+        DELETE FROM guild_members
+        WHERE guild_id = $1 AND user_id = ANY($2) AND last_active_at < NOW() - make_interval(days => $3)
+        RETURNING user_id
+        "#,
+        guild_id,
+        &candidate_ids,
+        days
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        "DELETE FROM role_members WHERE guild_id = $1 AND user_id = ANY($2)",
+        guild_id,
+        &pruned_ids
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let pruned = pruned_ids.len() as u64;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "member_prune",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "days": days,
+            "roleless_only": payload.roleless_only,
+            "pruned": pruned,
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    )
+    .await;
+
+    if pruned > 0 {
+        state.member_lists.reload(guild_id).await;
+
+        // Let the removed members' clients drop the guild
+        let event = serde_json::json!({
+            "type": "guild_removed",
+            "guild_id": guild_id,
+        });
+        for pruned_id in &pruned_ids {
+            state.ws_state.send_to_user(&pruned_id.to_string(), event.clone()).await;
+        }
+    }
+
+    Ok(Json(PruneResponse { pruned }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_days_defaults_and_stays_in_bounds() {
+        assert_eq!(prune_days(None), Ok(DEFAULT_PRUNE_DAYS));
+        assert_eq!(prune_days(Some(MIN_PRUNE_DAYS)), Ok(MIN_PRUNE_DAYS));
+        assert_eq!(prune_days(Some(MAX_PRUNE_DAYS)), Ok(MAX_PRUNE_DAYS));
+        assert_eq!(prune_days(Some(0)), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(prune_days(Some(-7)), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(prune_days(Some(MAX_PRUNE_DAYS + 1)), Err(StatusCode::BAD_REQUEST));
+    }
+}
@@ -0,0 +1,249 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+const MAX_SUBSCRIPTIONS_PER_USER: i64 = 20;
+const MAX_ENDPOINT_LENGTH: usize = 2048;
+const MAX_DEVICE_TOKEN_LENGTH: usize = 4096;
+
+#[derive(Debug, Deserialize)]
+pub struct WebPushKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+// Web push: the browser's PushSubscription JSON plus `provider: "webpush"`.
+// HTTP provider: `provider: "http"` with the device token from wryft-mobile.
+#[derive(Debug, Deserialize)]
+pub struct CreatePushSubscriptionRequest {
+    pub provider: String,
+    pub endpoint: Option<String>,
+    pub keys: Option<WebPushKeys>,
+    pub device_token: Option<String>,
+    pub platform: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushSubscriptionResponse {
+    pub id: Uuid,
+    pub provider: String,
+    pub endpoint: Option<String>,
+    pub platform: Option<String>,
+    pub created_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+// Public VAPID key browsers need to subscribe
+pub async fn get_vapid_public_key(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let key = state.push.vapid_public_key().ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({ "public_key": key })))
+}
+
+// List the user's registered devices
+pub async fn get_push_subscriptions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<PushSubscriptionResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let subscriptions = sqlx::query!(
+        "SELECT id, provider, endpoint, platform, created_at, last_used_at FROM push_subscriptions WHERE user_id = $1 ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(subscriptions.into_iter().map(|s| PushSubscriptionResponse {
+        id: s.id,
+        provider: s.provider,
+        endpoint: s.endpoint,
+        platform: s.platform,
+        created_at: s.created_at.map(|dt| dt.to_rfc3339()),
+        last_used_at: s.last_used_at.map(|dt| dt.to_rfc3339()),
+    }).collect()))
+}
+
+// Register a device. Registering the same endpoint or token again refreshes it (and moves
+// it over if another account was logged in on that device before).
+pub async fn create_push_subscription(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreatePushSubscriptionRequest>,
+) -> Result<Json<PushSubscriptionResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !state.push.supports(&payload.provider) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM push_subscriptions WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let subscription_id = Uuid::new_v4();
+
+    let subscription = match payload.provider.as_str() {
+        "webpush" => {
+            let endpoint = payload.endpoint.ok_or(StatusCode::BAD_REQUEST)?;
+            let keys = payload.keys.ok_or(StatusCode::BAD_REQUEST)?;
+
+            if endpoint.len() > MAX_ENDPOINT_LENGTH {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if let Err(e) = crate::push::webpush::check_endpoint(&endpoint).await {
+                eprintln!("🚫 Rejected push endpoint: {}", e);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if !crate::push::webpush::validate_keys(&keys.p256dh, &keys.auth) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            let existing = sqlx::query_scalar!(
+                "SELECT user_id FROM push_subscriptions WHERE endpoint = $1",
+                endpoint
+            )
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if existing != Some(user_id) && count >= MAX_SUBSCRIPTIONS_PER_USER {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO push_subscriptions (id, user_id, provider, endpoint, p256dh, auth)
+                VALUES ($1, $2, 'webpush', $3, $4, $5)
+                ON CONFLICT (endpoint) WHERE endpoint IS NOT NULL DO UPDATE
+                SET user_id = EXCLUDED.user_id, p256dh = EXCLUDED.p256dh, auth = EXCLUDED.auth, failure_count = 0
+                RETURNING id, provider, endpoint, platform, created_at, last_used_at
+                "#,
+                subscription_id,
+                user_id,
+                endpoint,
+                keys.p256dh,
+                keys.auth
+            )
+            .fetch_one(&state.db)
+            .await
+            .map(|s| PushSubscriptionResponse {
+                id: s.id,
+                provider: s.provider,
+                endpoint: s.endpoint,
+                platform: s.platform,
+                created_at: s.created_at.map(|dt| dt.to_rfc3339()),
+                last_used_at: s.last_used_at.map(|dt| dt.to_rfc3339()),
+            })
+        }
+        _ => {
+            let device_token = payload.device_token.ok_or(StatusCode::BAD_REQUEST)?;
+
+            if device_token.is_empty() || device_token.len() > MAX_DEVICE_TOKEN_LENGTH {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if payload.platform.as_ref().is_some_and(|p| p.len() > 32) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            let existing = sqlx::query_scalar!(
+                "SELECT user_id FROM push_subscriptions WHERE device_token = $1",
+                device_token
+            )
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if existing != Some(user_id) && count >= MAX_SUBSCRIPTIONS_PER_USER {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO push_subscriptions (id, user_id, provider, device_token, platform)
+                VALUES ($1, $2, 'http', $3, $4)
+                ON CONFLICT (device_token) WHERE device_token IS NOT NULL DO UPDATE
+                SET user_id = EXCLUDED.user_id, platform = EXCLUDED.platform, failure_count = 0
+                RETURNING id, provider, endpoint, platform, created_at, last_used_at
+                "#,
+                subscription_id,
+                user_id,
+                device_token,
+                payload.platform
+            )
+            .fetch_one(&state.db)
+            .await
+            .map(|s| PushSubscriptionResponse {
+                id: s.id,
+                provider: s.provider,
+                endpoint: s.endpoint,
+                platform: s.platform,
+                created_at: s.created_at.map(|dt| dt.to_rfc3339()),
+                last_used_at: s.last_used_at.map(|dt| dt.to_rfc3339()),
+            })
+        }
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(subscription))
+}
+
+// Unregister a device (e.g. on logout)
+pub async fn delete_push_subscription(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(subscription_id): axum::extract::Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM push_subscriptions WHERE id = $1 AND user_id = $2",
+        subscription_id,
+        user_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
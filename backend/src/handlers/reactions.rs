@@ -54,10 +54,24 @@ pub async fn add_reaction(
     Json(payload): Json<AddReactionRequest>,
 ) -> Result<Json<ReactionResponse>, StatusCode> {
     let user_id = extract_user_id(&headers)?;
+
+    // Reacting in a guild channel is subject to the guild's rules screen and verification level
+    let guild_id = sqlx::query_scalar!(
+        "SELECT c.guild_id FROM messages m JOIN channels c ON c.id::text = m.channel WHERE m.id = $1",
+        message_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(guild_id) = guild_id {
+        crate::handlers::screening::check_member_can_participate(&state, guild_id, user_id).await?;
+        crate::handlers::prune::touch_member_activity(&state.db, guild_id, user_id).await;
+    }
     
     let reaction_id = Uuid::new_v4();
     
-    sqlx::query!(
+    let result = sqlx::query!(
         "INSERT INTO message_reactions (id, message_id, user_id, emoji) VALUES ($1, $2, $3, $4) ON CONFLICT (message_id, user_id, emoji) DO NOTHING",
         reaction_id,
         message_id,
@@ -68,6 +82,11 @@ pub async fn add_reaction(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Reacting on a reaction-role message grants its role
+    if let (Some(guild_id), true) = (guild_id, result.rows_affected() > 0) {
+        let _ = crate::handlers::onboarding::apply_reaction_role(&state, guild_id, message_id, user_id, &payload.emoji, true).await;
+    }
+
     Ok(Json(ReactionResponse {
         id: reaction_id.to_string(),
         message_id: message_id.to_string(),
@@ -84,7 +103,7 @@ pub async fn remove_reaction(
 ) -> Result<StatusCode, StatusCode> {
     let user_id = extract_user_id(&headers)?;
     
-    sqlx::query!(
+    let result = sqlx::query!(
         "DELETE FROM message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3",
         message_id,
         user_id,
@@ -94,6 +113,21 @@ pub async fn remove_reaction(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Taking the reaction back removes a reaction role again
+    if result.rows_affected() > 0 {
+        let guild_id = sqlx::query_scalar!(
+            "SELECT c.guild_id FROM messages m JOIN channels c ON c.id::text = m.channel WHERE m.id = $1",
+            message_id
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if let Some(guild_id) = guild_id {
+            let _ = crate::handlers::onboarding::apply_reaction_role(&state, guild_id, message_id, user_id, &emoji, false).await;
+        }
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
 
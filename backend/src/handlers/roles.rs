@@ -50,6 +50,8 @@ pub const CONNECT_VOICE: i64 = 0x1000;
 pub const SPEAK_VOICE: i64 = 0x2000;
 pub const MUTE_MEMBERS: i64 = 0x4000;
 pub const DEAFEN_MEMBERS: i64 = 0x8000;
+pub const VIEW_AUDIT_LOG: i64 = 0x10000;
+pub const MANAGE_EVENTS: i64 = 0x20000;
 
 // Default permissions for @everyone role
 pub const DEFAULT_PERMISSIONS: i64 = SEND_MESSAGES | READ_MESSAGES | CREATE_INVITE | CONNECT_VOICE | SPEAK_VOICE;
@@ -91,7 +93,7 @@ pub struct AssignRoleRequest {
 }
 
 // Check if user has permission in guild
-async fn has_permission(
+pub async fn has_permission(
     state: &AppState,
     user_id: Uuid,
     guild_id: Uuid,
@@ -140,6 +142,43 @@ async fn has_permission(
     Ok(false)
 }
 
+// Check that a user outranks a role: the owner outranks everything, everyone else needs a
+// role positioned above it
+pub async fn outranks_role(
+    state: &AppState,
+    user_id: Uuid,
+    guild_id: Uuid,
+    role_position: i32,
+) -> Result<bool, StatusCode> {
+    let guild = sqlx::query!(
+        "SELECT owner_id FROM guilds WHERE id = $1",
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    if guild.owner_id == user_id {
+        return Ok(true);
+    }
+
+    let highest = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(r.position)
+        FROM roles r
+        INNER JOIN role_members rm ON r.id = rm.role_id
+        WHERE rm.user_id = $1 AND rm.guild_id = $2
+        "#,
+        user_id,
+        guild_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(highest.is_some_and(|highest| highest > role_position))
+}
+
 // Create a new role
 pub async fn create_role(
     State(state): State<AppState>,
@@ -189,6 +228,25 @@ pub async fn create_role(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let details = serde_json::json!({
+        "role_name": payload.name,
+        "color": color,
+        "permissions": permissions,
+        "mentionable": mentionable,
+        "hoist": hoist,
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "role_create",
+        Some("role"),
+        Some(role_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
     Ok(Json(RoleResponse {
         id: role_id,
         guild_id,
@@ -253,6 +311,16 @@ pub async fn update_role(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    let existing = sqlx::query!(
+        "SELECT name, color, position, permissions, mentionable, hoist FROM roles WHERE id = $1 AND guild_id = $2",
+        role_id,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
     // Update fields individually
     if let Some(name) = payload.name {
         sqlx::query!(
@@ -340,6 +408,40 @@ pub async fn update_role(
     .await
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let details = serde_json::json!({
+        "role_name": role.name,
+        "before": {
+            "name": existing.name,
+            "color": existing.color,
+            "position": existing.position,
+            "permissions": existing.permissions,
+            "mentionable": existing.mentionable,
+            "hoist": existing.hoist,
+        },
+        "after": {
+            "name": role.name,
+            "color": role.color,
+            "position": role.position,
+            "permissions": role.permissions,
+            "mentionable": role.mentionable,
+            "hoist": role.hoist,
+        }
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "role_update",
+        Some("role"),
+        Some(role_id),
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    // Hoist and position decide how the member list is grouped
+    state.member_lists.reload(guild_id).await;
+
     Ok(Json(RoleResponse {
         id: role.id,
         guild_id: role.guild_id,
@@ -365,15 +467,29 @@ pub async fn delete_role(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    sqlx::query!(
-        "DELETE FROM roles WHERE id = $1 AND guild_id = $2",
+    let deleted = sqlx::query!(
+        "DELETE FROM roles WHERE id = $1 AND guild_id = $2 RETURNING name, permissions",
         role_id,
         guild_id
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if let Some(role) = deleted {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "role_delete",
+            Some("role"),
+            Some(role_id),
+            Some(serde_json::json!({ "role_name": role.name, "permissions": role.permissions })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+        state.member_lists.reload(guild_id).await;
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -408,7 +524,7 @@ pub async fn assign_role(
     }
 
     // Assign role
-    sqlx::query!(
+    let result = sqlx::query!(
         r#"
         INSERT INTO role_members (role_id, user_id, guild_id)
         VALUES ($1, $2, $3)
@@ -422,6 +538,20 @@ pub async fn assign_role(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "member_role_add",
+            Some("user"),
+            Some(target_user_id),
+            Some(serde_json::json!({ "role_id": role_id })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+        crate::handlers::member_list::member_changed(&state, guild_id, target_user_id).await;
+    }
+
     Ok(StatusCode::OK)
 }
 
@@ -440,7 +570,7 @@ pub async fn remove_role(
 
     let target_user_id = Uuid::parse_str(&user_id_str).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    sqlx::query!(
+    let result = sqlx::query!(
         "DELETE FROM role_members WHERE role_id = $1 AND user_id = $2 AND guild_id = $3",
         role_id,
         target_user_id,
@@ -450,6 +580,20 @@ pub async fn remove_role(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "member_role_remove",
+            Some("user"),
+            Some(target_user_id),
+            Some(serde_json::json!({ "role_id": role_id })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+        crate::handlers::member_list::member_changed(&state, guild_id, target_user_id).await;
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -0,0 +1,339 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::handlers::roles::{has_permission, MANAGE_GUILD};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+// Verification levels, each one includes the checks of the levels below it
+pub const VERIFICATION_NONE: i16 = 0;
+pub const VERIFICATION_EMAIL: i16 = 1;
+pub const VERIFICATION_ACCOUNT_AGE: i16 = 2;
+pub const VERIFICATION_MEMBER_AGE: i16 = 3;
+
+const MIN_ACCOUNT_AGE_MINUTES: i64 = 5;
+const MIN_MEMBER_AGE_MINUTES: i64 = 10;
+
+const MAX_RULES: usize = 20;
+const MAX_RULE_LENGTH: usize = 300;
+const MAX_DESCRIPTION_LENGTH: usize = 300;
+
+#[derive(Debug, Serialize)]
+pub struct MemberScreeningResponse {
+    pub guild_id: Uuid,
+    pub enabled: bool,
+    pub description: Option<String>,
+    pub rules: Vec<String>,
+    pub verification_level: i16,
+    // Whether the requesting member still has to accept the rules
+    pub pending: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberScreeningRequest {
+    pub enabled: Option<bool>,
+    pub description: Option<String>,
+    pub rules: Option<Vec<String>>,
+}
+
+// Check that a member may post or react in a guild: pending members must accept the rules
+// first, and the guild's verification level must be met. The owner is never held back, and
+// members who were given a role skip the verification level (but not the rules screen).
+pub async fn check_member_can_participate(
+    state: &AppState,
+    guild_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), StatusCode> {
+    let member = sqlx::query!(
+        r#"
+        SELECT g.owner_id, g.verification_level, gm.pending, gm.joined_at,
+               u.email_verified, u.created_at,
+               EXISTS(SELECT 1 FROM role_members rm WHERE rm.guild_id = gm.guild_id AND rm.user_id = gm.user_id) as "has_roles!"
+        FROM guild_members gm
+        JOIN guilds g ON g.id = gm.guild_id
+        JOIN users u ON u.id = gm.user_id
+        WHERE gm.guild_id = $1 AND gm.user_id = $2
+        "#,
+        guild_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::FORBIDDEN)?;
+
+    if member.owner_id == user_id {
+        return Ok(());
+    }
+
+    if member.pending {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if member.has_roles {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    let account_age_minutes = member.created_at.map(|c| now.signed_duration_since(c).num_minutes()).unwrap_or(0);
+    let member_age_minutes = member.joined_at.map(|j| now.signed_duration_since(j).num_minutes()).unwrap_or(0);
+
+    if !meets_verification_level(member.verification_level, member.email_verified, account_age_minutes, member_age_minutes) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+// Whether a member without roles passes a guild's verification level
+fn meets_verification_level(level: i16, email_verified: bool, account_age_minutes: i64, member_age_minutes: i64) -> bool {
+    if level >= VERIFICATION_EMAIL && !email_verified {
+        return false;
+    }
+    if level >= VERIFICATION_ACCOUNT_AGE && account_age_minutes < MIN_ACCOUNT_AGE_MINUTES {
+        return false;
+    }
+    if level >= VERIFICATION_MEMBER_AGE && member_age_minutes < MIN_MEMBER_AGE_MINUTES {
+        return false;
+    }
+    true
+}
+
+// Whether members joining right now have to go through the rules screen
+pub async fn is_screening_enabled(db: &sqlx::PgPool, guild_id: Uuid) -> Result<bool, StatusCode> {
+    let enabled = sqlx::query_scalar!(
+        "SELECT enabled FROM guild_member_screening WHERE guild_id = $1",
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(enabled.unwrap_or(false))
+}
+
+async fn load_screening(db: &sqlx::PgPool, guild_id: Uuid, user_id: Uuid) -> Result<MemberScreeningResponse, StatusCode> {
+    let guild = sqlx::query!(
+        r#"
+        SELECT g.verification_level, s.enabled as "enabled?", s.description, s.rules as "rules?"
+        FROM guilds g
+        LEFT JOIN guild_member_screening s ON s.guild_id = g.id
+        WHERE g.id = $1
+        "#,
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let pending = sqlx::query_scalar!(
+        "SELECT pending FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+        guild_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::FORBIDDEN)?;
+
+    Ok(MemberScreeningResponse {
+        guild_id,
+        enabled: guild.enabled.unwrap_or(false),
+        description: guild.description,
+        rules: guild.rules.unwrap_or_default(),
+        verification_level: guild.verification_level,
+        pending,
+    })
+}
+
+// Get a guild's rules screen (members only, pending members included)
+pub async fn get_member_screening(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<MemberScreeningResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    Ok(Json(load_screening(&state.db, guild_id, user_id).await?))
+}
+
+// Configure the rules screen
+pub async fn update_member_screening(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<UpdateMemberScreeningRequest>,
+) -> Result<Json<MemberScreeningResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Some(description) = &payload.description {
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if let Some(rules) = &payload.rules {
+        if rules.len() > MAX_RULES
+            || rules.iter().any(|r| r.trim().is_empty() || r.chars().count() > MAX_RULE_LENGTH)
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let before = load_screening(&state.db, guild_id, user_id).await?;
+
+    let enabled = payload.enabled.unwrap_or(before.enabled);
+    let rules = payload.rules.unwrap_or_else(|| before.rules.clone());
+
+    // A screen with nothing to accept would lock new members out for no reason
+    if enabled && rules.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let description = match payload.description {
+        Some(d) if d.trim().is_empty() => None,
+        Some(d) => Some(d),
+        None => before.description.clone(),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO guild_member_screening (guild_id, enabled, description, rules, updated_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (guild_id) DO UPDATE
+        SET enabled = EXCLUDED.enabled, description = EXCLUDED.description,
+            rules = EXCLUDED.rules, updated_at = NOW()
+        "#,
+        guild_id,
+        enabled,
+        description,
+        &rules
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Turning the screen off releases anyone still waiting on it
+    if !enabled {
+        sqlx::query!(
+            "UPDATE guild_members SET pending = false WHERE guild_id = $1 AND pending",
+            guild_id
+        )
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let after = load_screening(&state.db, guild_id, user_id).await?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "member_screening_update",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({
+            "before": { "enabled": before.enabled, "description": before.description, "rules": before.rules },
+            "after": { "enabled": after.enabled, "description": after.description, "rules": after.rules },
+        })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(after))
+}
+
+// Accept the rules screen, letting a pending member participate
+pub async fn accept_member_screening(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let result = sqlx::query!(
+        "UPDATE guild_members SET pending = false, rules_accepted_at = NOW() WHERE guild_id = $1 AND user_id = $2 AND pending",
+        guild_id,
+        user_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        // Either not a member, or nothing left to accept
+        let is_member = sqlx::query_scalar!(
+            "SELECT 1 FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+            guild_id,
+            user_id
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if is_member.is_none() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn email_level_requires_verified_email() {
+        assert!(meets_verification_level(VERIFICATION_EMAIL, true, 0, 0));
+        assert!(!meets_verification_level(VERIFICATION_EMAIL, false, 0, 0));
+        assert!(!meets_verification_level(VERIFICATION_MEMBER_AGE, false, 60, 60));
+    }
+
+    #[test]
+    fn no_level_lets_everyone_through() {
+        assert!(meets_verification_level(VERIFICATION_NONE, false, 0, 0));
+    }
+
+    #[test]
+    fn age_levels_include_the_levels_below() {
+        assert!(!meets_verification_level(VERIFICATION_ACCOUNT_AGE, true, MIN_ACCOUNT_AGE_MINUTES - 1, 0));
+        assert!(meets_verification_level(VERIFICATION_ACCOUNT_AGE, true, MIN_ACCOUNT_AGE_MINUTES, 0));
+        assert!(!meets_verification_level(VERIFICATION_MEMBER_AGE, true, 60, MIN_MEMBER_AGE_MINUTES - 1));
+        assert!(meets_verification_level(VERIFICATION_MEMBER_AGE, true, 60, MIN_MEMBER_AGE_MINUTES));
+    }
+}
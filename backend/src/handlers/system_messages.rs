@@ -0,0 +1,550 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{models::*, AppState};
+use crate::handlers::roles::{has_permission, MANAGE_GUILD, MANAGE_MESSAGES};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+// Values of messages.message_type
+pub const MESSAGE_TYPE_DEFAULT: &str = "default";
+pub const MESSAGE_TYPE_MEMBER_JOIN: &str = "member_join";
+pub const MESSAGE_TYPE_MEMBER_LEAVE: &str = "member_leave";
+pub const MESSAGE_TYPE_GUILD_BOOST: &str = "guild_boost";
+pub const MESSAGE_TYPE_CHANNEL_PIN: &str = "channel_pin";
+pub const MESSAGE_TYPE_AUTOMOD_ALERT: &str = "automod_alert";
+
+const DEFAULT_WELCOME_MESSAGE: &str = "{user} joined {guild}. Say hi!";
+const LEAVE_MESSAGE: &str = "{user} left {guild}.";
+const BOOST_MESSAGE: &str = "{user} just boosted {guild}!";
+const PIN_MESSAGE: &str = "{user} pinned a message to this channel.";
+
+pub const MAX_WELCOME_MESSAGE_LENGTH: usize = 300;
+const MAX_PINS_PER_CHANNEL: i64 = 50;
+
+// Member events that can be announced in a guild's system channel
+#[derive(Debug, Clone, Copy)]
+pub enum MemberEvent {
+    Join,
+    Leave,
+    Boost,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemChannelSettings {
+    pub system_channel_id: Option<Uuid>,
+    pub join_notifications: bool,
+    pub leave_notifications: bool,
+    pub boost_notifications: bool,
+    pub pin_notifications: bool,
+    pub welcome_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSystemChannelRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub system_channel_id: Option<Option<Uuid>>,
+    pub join_notifications: Option<bool>,
+    pub leave_notifications: Option<bool>,
+    pub boost_notifications: Option<bool>,
+    pub pin_notifications: Option<bool>,
+    // An empty string resets to the default welcome text
+    pub welcome_message: Option<String>,
+}
+
+fn render(template: &str, username: &str, guild_name: &str) -> String {
+    template.replace("{user}", username).replace("{guild}", guild_name)
+}
+
+// Store a server-generated message and push it to the channel's subscribers
+pub async fn post_system_message(
+    state: &AppState,
+    channel_id: Uuid,
+    message_type: &str,
+    author: &str,
+    author_id: Option<Uuid>,
+    text: &str,
+    reference_message_id: Option<Uuid>,
+) -> Result<Uuid, StatusCode> {
+    let message_id = Uuid::new_v4();
+    let channel = channel_id.to_string();
+    let timestamp = chrono::Utc::now().format("%I:%M %p").to_string();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO messages (id, channel, author, author_id, text, timestamp, message_type, reference_message_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        message_id,
+        channel,
+        author,
+        author_id,
+        text,
+        timestamp,
+        message_type,
+        reference_message_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let event = serde_json::json!({
+        "type": "message",
+        "id": message_id.to_string(),
+        "channel": channel,
+        "content": text,
+        "author": author,
+        "timestamp": timestamp,
+        "message_type": message_type,
+        "reference_message_id": reference_message_id,
+    });
+    let tx = state.ws_state.get_or_create_channel(&channel).await;
+    let _ = tx.send(event.to_string());
+
+    Ok(message_id)
+}
+
+// Announce a member event in the guild's system channel, if one is set and the event is enabled.
+// Failures are logged and swallowed; they shouldn't fail the join/leave/boost itself. Lazy
+// member lists of the guild are refreshed as well.
+pub async fn announce_member_event(state: &AppState, guild_id: Uuid, user_id: Uuid, event: MemberEvent) {
+    crate::handlers::member_list::member_changed(state, guild_id, user_id).await;
+
+    let guild = match sqlx::query!(
+        r#"
+        SELECT name, system_channel_id, join_notifications, leave_notifications,
+               boost_notifications, welcome_message
+        FROM guilds WHERE id = $1
+        "#,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(Some(guild)) => guild,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to load system channel settings: {:?}", e);
+            return;
+        }
+    };
+
+    let Some(channel_id) = guild.system_channel_id else { return };
+
+    let (enabled, message_type, template) = match event {
+        MemberEvent::Join => (
+            guild.join_notifications,
+            MESSAGE_TYPE_MEMBER_JOIN,
+            guild.welcome_message.as_deref().unwrap_or(DEFAULT_WELCOME_MESSAGE),
+        ),
+        MemberEvent::Leave => (guild.leave_notifications, MESSAGE_TYPE_MEMBER_LEAVE, LEAVE_MESSAGE),
+        MemberEvent::Boost => (guild.boost_notifications, MESSAGE_TYPE_GUILD_BOOST, BOOST_MESSAGE),
+    };
+
+    if !enabled {
+        return;
+    }
+
+    let username = sqlx::query_scalar!("SELECT username FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let text = render(template, &username, &guild.name);
+
+    if let Err(e) = post_system_message(state, channel_id, message_type, &username, Some(user_id), &text, None).await {
+        eprintln!("Failed to post {} system message: {:?}", message_type, e);
+    }
+}
+
+async fn load_settings(db: &sqlx::PgPool, guild_id: Uuid) -> Result<SystemChannelSettings, StatusCode> {
+    let guild = sqlx::query!(
+        r#"
+        SELECT system_channel_id, join_notifications, leave_notifications,
+               boost_notifications, pin_notifications, welcome_message
+        FROM guilds WHERE id = $1
+        "#,
+        guild_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(SystemChannelSettings {
+        system_channel_id: guild.system_channel_id,
+        join_notifications: guild.join_notifications,
+        leave_notifications: guild.leave_notifications,
+        boost_notifications: guild.boost_notifications,
+        pin_notifications: guild.pin_notifications,
+        welcome_message: guild.welcome_message,
+    })
+}
+
+// Get a guild's system channel settings
+pub async fn get_system_channel_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<SystemChannelSettings>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(Json(load_settings(&state.db, guild_id).await?))
+}
+
+// Update the system channel, event toggles and welcome text
+pub async fn update_system_channel_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<UpdateSystemChannelRequest>,
+) -> Result<Json<SystemChannelSettings>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Validate everything before writing anything
+    if let Some(Some(channel_id)) = payload.system_channel_id {
+        // Only text channels of this guild can be the system channel
+        sqlx::query!(
+            "SELECT id FROM channels WHERE id = $1 AND guild_id = $2 AND channel_type = 'text'",
+            channel_id,
+            guild_id
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    }
+    if payload
+        .welcome_message
+        .as_ref()
+        .is_some_and(|message| message.chars().count() > MAX_WELCOME_MESSAGE_LENGTH)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let welcome_message = payload
+        .welcome_message
+        .as_deref()
+        .map(|message| if message.trim().is_empty() { None } else { Some(message) });
+
+    let before = load_settings(&state.db, guild_id).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE guilds SET
+            system_channel_id = CASE WHEN $2 THEN $3 ELSE system_channel_id END,
+            join_notifications = COALESCE($4, join_notifications),
+            leave_notifications = COALESCE($5, leave_notifications),
+            boost_notifications = COALESCE($6, boost_notifications),
+            pin_notifications = COALESCE($7, pin_notifications),
+            welcome_message = CASE WHEN $8 THEN $9 ELSE welcome_message END
+        WHERE id = $1
+        "#,
+        guild_id,
+        payload.system_channel_id.is_some(),
+        payload.system_channel_id.flatten(),
+        payload.join_notifications,
+        payload.leave_notifications,
+        payload.boost_notifications,
+        payload.pin_notifications,
+        welcome_message.is_some(),
+        welcome_message.flatten()
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let after = load_settings(&state.db, guild_id).await?;
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "system_channel_update",
+        Some("guild"),
+        Some(guild_id),
+        Some(serde_json::json!({ "before": before, "after": after })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(after))
+}
+
+// Boost a guild (premium members only)
+pub async fn boost_guild(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let member = sqlx::query!(
+        r#"
+        SELECT u.is_premium
+        FROM guild_members gm
+        JOIN users u ON u.id = gm.user_id
+        WHERE gm.guild_id = $1 AND gm.user_id = $2
+        "#,
+        guild_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !member.is_premium.unwrap_or(false) {
+        return Err(StatusCode::PAYMENT_REQUIRED);
+    }
+
+    let result = sqlx::query!(
+        "INSERT INTO guild_boosts (guild_id, user_id) VALUES ($1, $2) ON CONFLICT (guild_id, user_id) DO NOTHING",
+        guild_id,
+        user_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Only announce the first boost, not repeated requests
+    if result.rows_affected() > 0 {
+        announce_member_event(&state, guild_id, user_id, MemberEvent::Boost).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Remove your boost from a guild
+pub async fn remove_guild_boost(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM guild_boosts WHERE guild_id = $1 AND user_id = $2",
+        guild_id,
+        user_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Check that a message lives in the given guild channel and return the channel's guild
+async fn message_guild(db: &sqlx::PgPool, channel_id: Uuid, message_id: Uuid) -> Result<Uuid, StatusCode> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT c.guild_id
+        FROM messages m
+        JOIN channels c ON c.id::text = m.channel
+        WHERE m.id = $1 AND c.id = $2 AND m.deleted = false
+        "#,
+        message_id,
+        channel_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Get a channel's pinned messages, most recently pinned first
+pub async fn get_pinned_messages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(channel_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<Message>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let can_view = crate::permissions::check_channel_permission(&state.db, user_id, channel_id, "view")
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    if !can_view {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let messages = sqlx::query!(
+        r#"
+        SELECT id, channel, author, author_id, text, timestamp, edited_at, message_type, reference_message_id
+        FROM messages
+        WHERE channel = $1 AND deleted = false AND pinned_at IS NOT NULL
+        ORDER BY pinned_at DESC
+        "#,
+        channel_id.to_string()
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let result = messages
+        .into_iter()
+        .map(|m| Message {
+            id: m.id,
+            channel: m.channel,
+            author: m.author,
+            author_id: m.author_id.map(|id| id.to_string()),
+            text: m.text,
+            timestamp: m.timestamp,
+            edited: m.edited_at.is_some(),
+            attachments: None,
+            message_type: m.message_type,
+            reference_message_id: m.reference_message_id,
+            pinned: true,
+        })
+        .collect();
+
+    Ok(Json(result))
+}
+
+// Pin a message (requires MANAGE_MESSAGES)
+pub async fn pin_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((channel_id, message_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+    let guild_id = message_guild(&state.db, channel_id, message_id).await?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_MESSAGES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let pin_count = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM messages WHERE channel = $1 AND deleted = false AND pinned_at IS NOT NULL",
+        channel_id.to_string()
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(0);
+
+    if pin_count >= MAX_PINS_PER_CHANNEL {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let result = sqlx::query!(
+        "UPDATE messages SET pinned_at = NOW(), pinned_by = $1 WHERE id = $2 AND pinned_at IS NULL",
+        user_id,
+        message_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Already pinned: nothing to announce
+    if result.rows_affected() == 0 {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "message_pin",
+        Some("message"),
+        Some(message_id),
+        Some(serde_json::json!({ "channel_id": channel_id })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    let pin_notifications = sqlx::query_scalar!("SELECT pin_notifications FROM guilds WHERE id = $1", guild_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if pin_notifications {
+        let username = sqlx::query_scalar!("SELECT username FROM users WHERE id = $1", user_id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        let text = PIN_MESSAGE.replace("{user}", &username);
+        post_system_message(&state, channel_id, MESSAGE_TYPE_CHANNEL_PIN, &username, Some(user_id), &text, Some(message_id)).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Unpin a message (requires MANAGE_MESSAGES)
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((channel_id, message_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+    let guild_id = message_guild(&state.db, channel_id, message_id).await?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_MESSAGES).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let result = sqlx::query!(
+        "UPDATE messages SET pinned_at = NULL, pinned_by = NULL WHERE id = $1 AND pinned_at IS NOT NULL",
+        message_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() > 0 {
+        let _ = crate::handlers::audit_logs::create_audit_log(
+            &state.db,
+            guild_id,
+            Some(user_id),
+            "message_unpin",
+            Some("message"),
+            Some(message_id),
+            Some(serde_json::json!({ "channel_id": channel_id })),
+            crate::handlers::audit_logs::audit_log_reason(&headers)
+        ).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,655 @@
+use axum::{extract::State, http::StatusCode, Json, http::HeaderMap};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use std::collections::HashMap;
+
+use crate::{models::*, AppState};
+use crate::handlers::roles::{has_permission, MANAGE_GUILD};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+    let auth_header = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Uuid::parse_str(&token_data.claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn generate_template_code() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+// Template-local id of the @everyone role
+const EVERYONE_TEMPLATE_ID: i32 = 0;
+
+// Snapshot stored in guild_templates.serialized_guild. Roles and categories get
+// template-local ids so the snapshot never leaks the source guild's UUIDs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateGuild {
+    pub roles: Vec<TemplateRole>,
+    pub categories: Vec<TemplateCategory>,
+    pub channels: Vec<TemplateChannel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateRole {
+    pub id: i32,
+    pub name: String,
+    pub color: String,
+    pub position: i32,
+    pub permissions: i64,
+    pub mentionable: bool,
+    pub hoist: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateCategory {
+    pub id: i32,
+    pub name: String,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateChannel {
+    pub name: String,
+    pub channel_type: String,
+    pub position: i32,
+    pub category_id: Option<i32>,
+    pub topic: Option<String>,
+    pub nsfw: bool,
+    pub slowmode_seconds: i32,
+    pub permission_overwrites: Vec<TemplateOverwrite>,
+}
+
+// Only role overwrites are kept; user overwrites don't carry over to a new guild
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateOverwrite {
+    pub role_id: i32,
+    pub allow_view: Option<bool>,
+    pub allow_send_messages: Option<bool>,
+    pub allow_manage_messages: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateResponse {
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub source_guild_id: Option<Uuid>,
+    pub creator_id: Uuid,
+    pub usage_count: i32,
+    pub created_at: String,
+    pub updated_at: String,
+    pub serialized_guild: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGuildFromTemplateRequest {
+    pub name: String,
+}
+
+// Build a snapshot of a guild's current layout
+async fn snapshot_guild(db: &sqlx::PgPool, guild_id: Uuid) -> Result<TemplateGuild, StatusCode> {
+    let roles = sqlx::query!(
+        r#"
+        SELECT id, name, color, position, permissions, mentionable, hoist
+        FROM roles
+        WHERE guild_id = $1
+        ORDER BY position, created_at
+        "#,
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut role_ids: HashMap<Uuid, i32> = HashMap::new();
+    let mut template_roles = Vec::new();
+    let mut next_role_id = EVERYONE_TEMPLATE_ID;
+
+    for role in roles {
+        // The @everyone role shares its id with the guild
+        let template_id = if role.id == guild_id {
+            EVERYONE_TEMPLATE_ID
+        } else {
+            next_role_id += 1;
+            next_role_id
+        };
+        role_ids.insert(role.id, template_id);
+
+        template_roles.push(TemplateRole {
+            id: template_id,
+            name: role.name,
+            color: role.color.unwrap_or_else(|| "#99aab5".to_string()),
+            position: role.position,
+            permissions: role.permissions,
+            mentionable: role.mentionable.unwrap_or(true),
+            hoist: role.hoist.unwrap_or(false),
+        });
+    }
+
+    let categories = sqlx::query!(
+        "SELECT id, name, position FROM channel_categories WHERE guild_id = $1 ORDER BY position, created_at",
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut category_ids: HashMap<Uuid, i32> = HashMap::new();
+    let template_categories: Vec<TemplateCategory> = categories
+        .into_iter()
+        .enumerate()
+        .map(|(index, c)| {
+            category_ids.insert(c.id, index as i32);
+            TemplateCategory {
+                id: index as i32,
+                name: c.name,
+                position: c.position,
+            }
+        })
+        .collect();
+
+    let channels = sqlx::query!(
+        r#"
+        SELECT id, name, channel_type, position, category_id, topic, nsfw, slowmode_seconds
+        FROM channels
+        WHERE guild_id = $1
+        ORDER BY position, created_at
+        "#,
+        guild_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let channel_ids: Vec<Uuid> = channels.iter().map(|c| c.id).collect();
+
+    let overwrites = sqlx::query!(
+        r#"
+        SELECT channel_id, role_id, allow_view, allow_send_messages, allow_manage_messages
+        FROM channel_permissions
+        WHERE channel_id = ANY($1) AND role_id IS NOT NULL
+        "#,
+        &channel_ids
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let template_channels = channels
+        .into_iter()
+        .map(|c| TemplateChannel {
+            permission_overwrites: overwrites
+                .iter()
+                .filter(|o| o.channel_id == c.id)
+                .filter_map(|o| {
+                    let role_id = o.role_id.and_then(|id| role_ids.get(&id))?;
+                    Some(TemplateOverwrite {
+                        role_id: *role_id,
+                        allow_view: o.allow_view,
+                        allow_send_messages: o.allow_send_messages,
+                        allow_manage_messages: o.allow_manage_messages,
+                    })
+                })
+                .collect(),
+            name: c.name,
+            channel_type: c.channel_type.unwrap_or_else(|| "text".to_string()),
+            position: c.position.unwrap_or(0),
+            category_id: c.category_id.and_then(|id| category_ids.get(&id).copied()),
+            topic: c.topic,
+            nsfw: c.nsfw.unwrap_or(false),
+            slowmode_seconds: c.slowmode_seconds.unwrap_or(0),
+        })
+        .collect();
+
+    Ok(TemplateGuild {
+        roles: template_roles,
+        categories: template_categories,
+        channels: template_channels,
+    })
+}
+
+// Create a template from an existing guild
+pub async fn create_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<CreateTemplateRequest>,
+) -> Result<Json<TemplateResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let snapshot = snapshot_guild(&state.db, guild_id).await?;
+    let serialized = serde_json::to_value(&snapshot).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let template = sqlx::query!(
+        r#"
+        INSERT INTO guild_templates (id, code, name, description, source_guild_id, creator_id, serialized_guild)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING code, name, description, source_guild_id, creator_id, usage_count, created_at, updated_at, serialized_guild
+        "#,
+        Uuid::new_v4(),
+        generate_template_code(),
+        name,
+        payload.description,
+        guild_id,
+        user_id,
+        serialized
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Create audit log entry
+    let details = serde_json::json!({
+        "template_code": template.code,
+        "template_name": template.name
+    });
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "template_create",
+        Some("template"),
+        None,
+        Some(details),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(Json(TemplateResponse {
+        code: template.code,
+        name: template.name,
+        description: template.description,
+        source_guild_id: template.source_guild_id,
+        creator_id: template.creator_id,
+        usage_count: template.usage_count,
+        created_at: template.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        updated_at: template.updated_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        serialized_guild: template.serialized_guild,
+    }))
+}
+
+// List templates created from a guild
+pub async fn get_guild_templates(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(guild_id): axum::extract::Path<Uuid>,
+) -> Result<Json<Vec<TemplateResponse>>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let templates = sqlx::query!(
+        r#"
+        SELECT code, name, description, source_guild_id, creator_id, usage_count, created_at, updated_at, serialized_guild
+        FROM guild_templates
+        WHERE source_guild_id = $1
+        ORDER BY created_at DESC
+        "#,
+        guild_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let response = templates
+        .into_iter()
+        .map(|t| TemplateResponse {
+            code: t.code,
+            name: t.name,
+            description: t.description,
+            source_guild_id: t.source_guild_id,
+            creator_id: t.creator_id,
+            usage_count: t.usage_count,
+            created_at: t.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+            updated_at: t.updated_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+            serialized_guild: t.serialized_guild,
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+// Re-snapshot the source guild into an existing template
+pub async fn sync_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, code)): axum::extract::Path<(Uuid, String)>,
+) -> Result<Json<TemplateResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let snapshot = snapshot_guild(&state.db, guild_id).await?;
+    let serialized = serde_json::to_value(&snapshot).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let template = sqlx::query!(
+        r#"
+        UPDATE guild_templates
+        SET serialized_guild = $1, updated_at = NOW()
+        WHERE code = $2 AND source_guild_id = $3
+        RETURNING code, name, description, source_guild_id, creator_id, usage_count, created_at, updated_at, serialized_guild
+        "#,
+        serialized,
+        code,
+        guild_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(TemplateResponse {
+        code: template.code,
+        name: template.name,
+        description: template.description,
+        source_guild_id: template.source_guild_id,
+        creator_id: template.creator_id,
+        usage_count: template.usage_count,
+        created_at: template.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        updated_at: template.updated_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        serialized_guild: template.serialized_guild,
+    }))
+}
+
+// Delete a template
+pub async fn delete_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path((guild_id, code)): axum::extract::Path<(Uuid, String)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    if !has_permission(&state, user_id, guild_id, MANAGE_GUILD).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let result = sqlx::query!(
+        "DELETE FROM guild_templates WHERE code = $1 AND source_guild_id = $2",
+        code,
+        guild_id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let _ = crate::handlers::audit_logs::create_audit_log(
+        &state.db,
+        guild_id,
+        Some(user_id),
+        "template_delete",
+        Some("template"),
+        None,
+        Some(serde_json::json!({ "template_code": code })),
+        crate::handlers::audit_logs::audit_log_reason(&headers)
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Get a template by its shareable code
+pub async fn get_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(code): axum::extract::Path<String>,
+) -> Result<Json<TemplateResponse>, StatusCode> {
+    let _user_id = extract_user_id(&headers)?;
+
+    let template = sqlx::query!(
+        r#"
+        SELECT code, name, description, source_guild_id, creator_id, usage_count, created_at, updated_at, serialized_guild
+        FROM guild_templates
+        WHERE code = $1
+        "#,
+        code
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(TemplateResponse {
+        code: template.code,
+        name: template.name,
+        description: template.description,
+        source_guild_id: template.source_guild_id,
+        creator_id: template.creator_id,
+        usage_count: template.usage_count,
+        created_at: template.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        updated_at: template.updated_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+        serialized_guild: template.serialized_guild,
+    }))
+}
+
+// Create a new guild from a template
+pub async fn create_guild_from_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(code): axum::extract::Path<String>,
+    Json(payload): Json<CreateGuildFromTemplateRequest>,
+) -> Result<Json<GuildResponse>, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.len() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let template = sqlx::query!(
+        "SELECT serialized_guild FROM guild_templates WHERE code = $1",
+        code
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let snapshot: TemplateGuild = serde_json::from_value(template.serialized_guild)
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    let guild_id = Uuid::new_v4();
+    let icon = name.chars().take(2).collect::<String>().to_uppercase();
+    let created_at = chrono::Utc::now();
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        "INSERT INTO guilds (id, name, owner_id, icon, created_at) VALUES ($1, $2, $3, $4, $5)",
+        guild_id,
+        name,
+        user_id,
+        icon,
+        created_at
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        "INSERT INTO guild_members (guild_id, user_id) VALUES ($1, $2)",
+        guild_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Roles (the template's @everyone maps onto the guild id, as in create_guild)
+    let mut role_ids: HashMap<i32, Uuid> = HashMap::new();
+
+    if !snapshot.roles.iter().any(|r| r.id == EVERYONE_TEMPLATE_ID) {
+        sqlx::query!(
+            "INSERT INTO roles (id, guild_id, name, color, position, permissions) VALUES ($1, $2, $3, $4, $5, $6)",
+            guild_id,
+            guild_id,
+            "@everyone",
+            "#99aab5",
+            0,
+            0
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        role_ids.insert(EVERYONE_TEMPLATE_ID, guild_id);
+    }
+
+    for role in &snapshot.roles {
+        let role_id = if role.id == EVERYONE_TEMPLATE_ID { guild_id } else { Uuid::new_v4() };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO roles (id, guild_id, name, color, position, permissions, mentionable, hoist)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            role_id,
+            guild_id,
+            role.name,
+            role.color,
+            role.position,
+            role.permissions,
+            role.mentionable,
+            role.hoist
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        role_ids.insert(role.id, role_id);
+    }
+
+    let mut category_ids: HashMap<i32, Uuid> = HashMap::new();
+
+    for category in &snapshot.categories {
+        let category_id = Uuid::new_v4();
+
+        sqlx::query!(
+            "INSERT INTO channel_categories (id, guild_id, name, position) VALUES ($1, $2, $3, $4)",
+            category_id,
+            guild_id,
+            category.name,
+            category.position
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        category_ids.insert(category.id, category_id);
+    }
+
+    for channel in &snapshot.channels {
+        let channel_id = Uuid::new_v4();
+        let category_id = channel.category_id.and_then(|id| category_ids.get(&id).copied());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO channels (id, guild_id, name, channel_type, category_id, position, topic, nsfw, slowmode_seconds)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            channel_id,
+            guild_id,
+            channel.name,
+            channel.channel_type,
+            category_id,
+            channel.position,
+            channel.topic,
+            channel.nsfw,
+            channel.slowmode_seconds
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        for overwrite in &channel.permission_overwrites {
+            let Some(role_id) = role_ids.get(&overwrite.role_id) else {
+                continue;
+            };
+
+            sqlx::query!(
+                r#"
+                INSERT INTO channel_permissions
+                (id, channel_id, role_id, allow_view, allow_send_messages, allow_manage_messages)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                Uuid::new_v4(),
+                channel_id,
+                role_id,
+                overwrite.allow_view,
+                overwrite.allow_send_messages,
+                overwrite.allow_manage_messages
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE guild_templates SET usage_count = usage_count + 1 WHERE code = $1",
+        code
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(GuildResponse {
+        id: guild_id,
+        name,
+        owner_id: user_id,
+        icon,
+        created_at: created_at.to_rfc3339(),
+        banner_url: None,
+        icon_url: None,
+    }))
+}
@@ -1,10 +1,13 @@
 use axum::{
-    extract::{Multipart, State},
-    http::StatusCode,
+    extract::{Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Extension,
     Json,
 };
-use serde::Serialize;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -13,7 +16,101 @@ pub struct UploadResponse {
     pub file_id: String,
 }
 
-// Helper to track file upload in database
+const MAX_ORIGINAL_FILENAME_LENGTH: usize = 255;
+
+// What a user may store depends on whether they have premium
+pub struct StorageLimits {
+    // Everything the user has uploaded, plus direct and resumable uploads still in progress
+    pub quota_bytes: i64,
+    // Attachments sent as multipart pass through memory, so they're kept smaller
+    pub max_attachment_bytes: u64,
+    // Direct uploads never pass through us, so attachments can be much larger than via multipart
+    pub max_direct_attachment_bytes: u64,
+}
+
+const FREE_STORAGE_LIMITS: StorageLimits = StorageLimits {
+    quota_bytes: 1024 * 1024 * 1024,
+    max_attachment_bytes: 25 * 1024 * 1024,
+    max_direct_attachment_bytes: 100 * 1024 * 1024,
+};
+
+const PREMIUM_STORAGE_LIMITS: StorageLimits = StorageLimits {
+    quota_bytes: 25 * 1024 * 1024 * 1024,
+    max_attachment_bytes: 100 * 1024 * 1024,
+    max_direct_attachment_bytes: 500 * 1024 * 1024,
+};
+
+// Body limit for the multipart upload routes: the largest attachment anyone may send, plus
+// room for the multipart framing
+pub const MAX_MULTIPART_UPLOAD_BYTES: usize = PREMIUM_STORAGE_LIMITS.max_attachment_bytes as usize + 64 * 1024;
+// How long the presigned URL is valid, and how long after that the upload may still be confirmed
+const UPLOAD_SLOT_URL_TTL_SECS: u64 = 15 * 60;
+const UPLOAD_SLOT_CONFIRM_GRACE_SECS: i64 = 60 * 60;
+const MAX_PENDING_UPLOAD_SLOTS: i64 = 20;
+const UPLOAD_SWEEP_INTERVAL_SECS: u64 = 10 * 60;
+
+// Resumable uploads are sent in chunks of exactly this size (except the last one). Small
+// enough to retry cheaply on a bad connection, and the smallest part S3 accepts.
+pub const UPLOAD_CHUNK_BYTES: usize = crate::storage::MIN_MULTIPART_PART_BYTES;
+// Sessions that see no new chunk for this long are abandoned
+const UPLOAD_SESSION_TTL_HOURS: i64 = 24;
+const MAX_ACTIVE_UPLOAD_SESSIONS: i64 = 10;
+pub const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+// Larger images are too slow to decode for thumbnails
+const MAX_PREVIEWED_IMAGE_BYTES: usize = 50 * 1024 * 1024;
+
+const IMAGE_CONTENT_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadSlotRequest {
+    pub file_type: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadSessionRequest {
+    pub file_type: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+#[derive(Serialize)]
+pub struct UploadSessionResponse {
+    pub session_id: String,
+    // Bytes received so far; the next chunk has to start here
+    pub offset: i64,
+    pub size: i64,
+    pub chunk_size: i32,
+    pub expires_at: String,
+}
+
+#[derive(Serialize)]
+pub struct StorageUsageResponse {
+    pub used_bytes: i64,
+    pub quota_bytes: i64,
+    pub remaining_bytes: i64,
+    pub max_attachment_bytes: u64,
+    pub max_direct_attachment_bytes: u64,
+    pub is_premium: bool,
+}
+
+#[derive(Serialize)]
+pub struct UploadSlotResponse {
+    pub slot_id: String,
+    pub method: String,
+    pub upload_url: String,
+    // Must be sent with the upload exactly as given
+    pub headers: HashMap<String, String>,
+    pub expires_at: String,
+}
+
+// Helper to track file upload in database. With `quota`, the upload has to fit in it; if it
+// doesn't, the stored object is given back.
+#[allow(clippy::too_many_arguments)]
 async fn track_upload(
     state: &AppState,
     user_id: &str,
@@ -22,13 +119,26 @@ async fn track_upload(
     file_url: &str,
     file_size: usize,
     content_type: &str,
+    original_filename: &str,
+    quota: Option<&StorageLimits>,
 ) -> Result<String, StatusCode> {
     let user_uuid = uuid::Uuid::parse_str(user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
-    
+    let original_filename: String = original_filename.chars().take(MAX_ORIGINAL_FILENAME_LENGTH).collect();
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(limits) = quota {
+        if let Err(status) = reserve_storage_quota(&mut tx, &user_uuid, limits, file_size as u64).await {
+            drop(tx);
+            crate::handlers::file_store::release_object(state, file_key).await;
+            return Err(status);
+        }
+    }
+
     let result = sqlx::query!(
         r#"
-        INSERT INTO file_uploads (user_id, file_type, file_key, file_url, file_size, content_type)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO file_uploads (user_id, file_type, file_key, file_url, file_size, content_type, original_filename)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
         RETURNING id
         "#,
         user_uuid,
@@ -36,18 +146,100 @@ async fn track_upload(
         file_key,
         file_url,
         file_size as i32,
-        content_type
+        content_type,
+        original_filename
     )
-    .fetch_one(&state.db)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to track upload: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok(result.id.to_string())
 }
 
+async fn is_premium(state: &AppState, user_id: &uuid::Uuid) -> Result<bool, StatusCode> {
+    let is_premium = sqlx::query_scalar!("SELECT is_premium FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(is_premium.unwrap_or(false))
+}
+
+async fn storage_limits(state: &AppState, user_id: &uuid::Uuid) -> Result<&'static StorageLimits, StatusCode> {
+    Ok(if is_premium(state, user_id).await? { &PREMIUM_STORAGE_LIMITS } else { &FREE_STORAGE_LIMITS })
+}
+
+// Bytes counted against a user's quota: their uploads, and the declared size of direct
+// and resumable uploads they haven't finished, so those can't be used to go over it
+async fn storage_used(db: impl sqlx::PgExecutor<'_>, user_id: &uuid::Uuid) -> Result<i64, StatusCode> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT
+            (SELECT COALESCE(SUM(file_size), 0) FROM file_uploads WHERE user_id = $1)::BIGINT
+            + (SELECT COALESCE(SUM(file_size), 0) FROM upload_slots WHERE user_id = $1 AND expires_at > NOW())::BIGINT
+            + (SELECT COALESCE(SUM(file_size), 0) FROM upload_sessions WHERE user_id = $1 AND expires_at > NOW())::BIGINT
+            AS "used!"
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to count storage used: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+// Make sure `size` more bytes fit in the user's quota. Only a quick check before doing the
+// work; `reserve_storage_quota` is what holds when writing the row that uses the space.
+async fn check_storage_quota(db: impl sqlx::PgExecutor<'_>, user_id: &uuid::Uuid, limits: &StorageLimits, size: u64) -> Result<(), StatusCode> {
+    let used = storage_used(db, user_id).await?;
+    if used.saturating_add(size as i64) > limits.quota_bytes {
+        eprintln!("🚫 Storage quota exceeded for {}: {} of {} bytes used", user_id, used, limits.quota_bytes);
+        return Err(StatusCode::INSUFFICIENT_STORAGE);
+    }
+    Ok(())
+}
+
+// Check the quota with the user's row locked, so concurrent uploads are counted one after
+// another. The row using up the space must be written in the same transaction.
+async fn reserve_storage_quota(conn: &mut sqlx::PgConnection, user_id: &uuid::Uuid, limits: &StorageLimits, size: u64) -> Result<(), StatusCode> {
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    check_storage_quota(&mut *conn, user_id, limits, size).await
+}
+
+// How much of their storage the user has used, and the limits that apply to them
+pub async fn get_storage_usage(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<StorageUsageResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let is_premium = is_premium(&state, &user_uuid).await?;
+    let limits = if is_premium { &PREMIUM_STORAGE_LIMITS } else { &FREE_STORAGE_LIMITS };
+    let used_bytes = storage_used(&state.db, &user_uuid).await?;
+
+    Ok(Json(StorageUsageResponse {
+        used_bytes,
+        quota_bytes: limits.quota_bytes,
+        remaining_bytes: (limits.quota_bytes - used_bytes).max(0),
+        max_attachment_bytes: limits.max_attachment_bytes,
+        max_direct_attachment_bytes: limits.max_direct_attachment_bytes,
+        is_premium,
+    }))
+}
+
 // Upload avatar
 pub async fn upload_avatar(
     State(state): State<AppState>,
@@ -72,21 +264,9 @@ pub async fn upload_avatar(
                 return Err(StatusCode::PAYLOAD_TOO_LARGE);
             }
 
-            let content_type = get_content_type(&filename);
-            let key = format!("avatars/{}-{}", uuid::Uuid::new_v4(), filename);
-            
-            println!("📤 Uploading avatar: {} ({})", key, content_type);
-            
-            let key = state.storage
-                .upload_file(&key, data.clone(), &content_type)
-                .await
-                .map_err(|e| {
-                    eprintln!("❌ S3 upload failed: {:?}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+            println!("📤 Uploading avatar: {}", filename);
 
-            // Track upload in database and get file_id
-            let file_id = track_upload(&state, &user_id, "avatar", &key, "", data.len(), &content_type).await?;
+            let file_id = store_image_upload(&state, &user_id, "avatar", &data, &filename).await?;
 
             // Return file_id instead of direct URL
             let url = format!("/api/files/{}", file_id);
@@ -116,15 +296,7 @@ pub async fn upload_banner(
                 return Err(StatusCode::PAYLOAD_TOO_LARGE);
             }
 
-            let content_type = get_content_type(&filename);
-            let key = format!("banners/{}-{}", uuid::Uuid::new_v4(), filename);
-            
-            let key = state.storage
-                .upload_file(&key, data.clone(), &content_type)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let file_id = track_upload(&state, &user_id, "banner", &key, "", data.len(), &content_type).await?;
+            let file_id = store_image_upload(&state, &user_id, "banner", &data, &filename).await?;
             let url = format!("/api/files/{}", file_id);
             return Ok(Json(UploadResponse { url, file_id }));
         }
@@ -149,15 +321,7 @@ pub async fn upload_icon(
                 return Err(StatusCode::PAYLOAD_TOO_LARGE);
             }
 
-            let content_type = get_content_type(&filename);
-            let key = format!("icons/{}-{}", uuid::Uuid::new_v4(), filename);
-            
-            let key = state.storage
-                .upload_file(&key, data.clone(), &content_type)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let file_id = track_upload(&state, &user_id, "icon", &key, "", data.len(), &content_type).await?;
+            let file_id = store_image_upload(&state, &user_id, "icon", &data, &filename).await?;
             let url = format!("/api/files/{}", file_id);
             return Ok(Json(UploadResponse { url, file_id }));
         }
@@ -182,15 +346,7 @@ pub async fn upload_emoji(
                 return Err(StatusCode::PAYLOAD_TOO_LARGE);
             }
 
-            let content_type = get_content_type(&filename);
-            let key = format!("emoji/{}-{}", uuid::Uuid::new_v4(), filename);
-            
-            let key = state.storage
-                .upload_file(&key, data.clone(), &content_type)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let file_id = track_upload(&state, &user_id, "emoji", &key, "", data.len(), &content_type).await?;
+            let file_id = store_image_upload(&state, &user_id, "emoji", &data, &filename).await?;
             let url = format!("/api/files/{}", file_id);
             return Ok(Json(UploadResponse { url, file_id }));
         }
@@ -211,19 +367,25 @@ pub async fn upload_attachment(
             let filename = field.file_name().unwrap_or("file").to_string();
             let data = field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?;
             
-            if data.len() > 25 * 1024 * 1024 {
+            let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+            let limits = storage_limits(&state, &user_uuid).await?;
+            if data.len() as u64 > limits.max_attachment_bytes {
                 return Err(StatusCode::PAYLOAD_TOO_LARGE);
             }
+            check_storage_quota(&state.db, &user_uuid, limits, data.len() as u64).await?;
 
-            let content_type = get_content_type(&filename);
+            let content_type = crate::content_type::detect_content_type(&data, &filename);
             let key = format!("attachments/{}-{}", uuid::Uuid::new_v4(), sanitize_filename(&filename));
             
-            let key = state.storage
-                .upload_file(&key, data.clone(), &content_type)
+            let key = crate::handlers::file_store::store_object(&state, &key, data.clone(), content_type)
                 .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                .map_err(|e| {
+                    eprintln!("❌ Failed to store attachment: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
 
-            let file_id = track_upload(&state, &user_id, "attachment", &key, "", data.len(), &content_type).await?;
+            let file_id = track_upload(&state, &user_id, "attachment", &key, "", data.len(), content_type, &filename, Some(limits)).await?;
+            describe_attachment(&state, &file_id, &key, content_type, Some(&data), data.len()).await;
             let url = format!("/api/files/{}", file_id);
             return Ok(Json(UploadResponse { url, file_id }));
         }
@@ -232,20 +394,776 @@ pub async fn upload_attachment(
     Err(StatusCode::BAD_REQUEST)
 }
 
-fn get_content_type(filename: &str) -> String {
-    let ext = filename.split('.').last().unwrap_or("").to_lowercase();
-    match ext.as_str() {
-        "jpg" | "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        "svg" => "image/svg+xml",
-        "mp4" => "video/mp4",
-        "webm" => "video/webm",
-        "pdf" => "application/pdf",
-        "txt" => "text/plain",
-        _ => "application/octet-stream",
-    }.to_string()
+// Run an image upload through the image pipeline and store the result with its smaller
+// variants. What the client sent is never stored as is.
+async fn store_image_upload(
+    state: &AppState,
+    user_id: &str,
+    file_type: &str,
+    data: &[u8],
+    filename: &str,
+) -> Result<String, StatusCode> {
+    let profile = crate::images::profile_for(file_type).ok_or(StatusCode::BAD_REQUEST)?;
+    let (prefix, _) = direct_upload_limits(file_type).ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Processing never makes an image larger than the upload by much, so that's what has to fit
+    let user_uuid = uuid::Uuid::parse_str(user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let limits = storage_limits(state, &user_uuid).await?;
+    check_storage_quota(&state.db, &user_uuid, limits, data.len() as u64).await?;
+
+    // Whatever the filename or declared type say, the content has to be an image
+    let is_image = crate::content_type::detect(data).is_some_and(|detected| IMAGE_CONTENT_TYPES.contains(&detected));
+    if !is_image {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let data = data.to_vec();
+    let processed = tokio::task::spawn_blocking(move || crate::images::process_image(profile, &data))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| match e {
+            crate::images::ImageError::Unsupported => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            crate::images::ImageError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            crate::images::ImageError::Encode(message) => {
+                eprintln!("❌ Failed to process {}: {}", file_type, message);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    // Variants sit next to the image, e.g. avatars/<id>.png and avatars/<id>/128.png
+    let image_id = uuid::Uuid::new_v4();
+    let full = processed.full;
+    let key = format!("{}/{}.{}", prefix, image_id, full.extension);
+
+    let file_size = full.data.len();
+    let key = crate::handlers::file_store::store_object(state, &key, Bytes::from(full.data), full.content_type)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to store image: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let filename = replace_extension(filename, full.extension);
+    let file_id = track_upload(state, user_id, file_type, &key, "", file_size, full.content_type, &filename, Some(limits)).await?;
+    let file_uuid = uuid::Uuid::parse_str(&file_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        "UPDATE file_uploads SET width = $1, height = $2 WHERE id = $3",
+        full.width as i32,
+        full.height as i32,
+        file_uuid
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    store_variants(state, file_uuid, &format!("{}/{}", prefix, image_id), &processed.variants).await?;
+
+    Ok(file_id)
+}
+
+// Store smaller copies of an image as `<base>/<size>.<ext>` and record them for ?size=
+async fn store_variants(
+    state: &AppState,
+    file_id: uuid::Uuid,
+    base: &str,
+    variants: &[(u32, crate::images::EncodedImage)],
+) -> Result<(), StatusCode> {
+    for (size, variant) in variants {
+        let variant_key = format!("{}/{}.{}", base, size, variant.extension);
+        let variant_key = crate::handlers::file_store::store_object(state, &variant_key, Bytes::copy_from_slice(&variant.data), variant.content_type)
+            .await
+            .map_err(|e| {
+                eprintln!("❌ Failed to store image variant: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO file_variants (file_id, size, file_key, content_type, file_size, width, height)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            file_id,
+            *size as i32,
+            variant_key,
+            variant.content_type,
+            variant.data.len() as i32,
+            variant.width as i32,
+            variant.height as i32
+        )
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(())
+}
+
+// Dimensions, duration, a placeholder and thumbnails for an attachment, so clients can lay
+// out messages before downloading anything. Best effort: attachments that can't be read
+// just go without. `data` is the file if it is still in memory.
+async fn describe_attachment(
+    state: &AppState,
+    file_id: &str,
+    file_key: &str,
+    content_type: &str,
+    data: Option<&Bytes>,
+    file_size: usize,
+) {
+    let Ok(file_uuid) = uuid::Uuid::parse_str(file_id) else {
+        return;
+    };
+
+    if IMAGE_CONTENT_TYPES.contains(&content_type) {
+        if file_size > MAX_PREVIEWED_IMAGE_BYTES {
+            return;
+        }
+        let data = match data {
+            Some(data) => data.clone(),
+            None => match state.storage.get_file(file_key).await {
+                Ok(data) => Bytes::from(data),
+                Err(_) => return,
+            },
+        };
+
+        let preview = match tokio::task::spawn_blocking(move || crate::images::preview_image(&data)).await {
+            Ok(Ok(preview)) => preview,
+            _ => return,
+        };
+
+        let _ = sqlx::query!(
+            "UPDATE file_uploads SET width = $1, height = $2, placeholder = $3 WHERE id = $4",
+            preview.width as i32,
+            preview.height as i32,
+            preview.placeholder,
+            file_uuid
+        )
+        .execute(&state.db)
+        .await;
+
+        let _ = store_variants(state, file_uuid, &format!("attachments/{}", file_uuid), &preview.thumbnails).await;
+        return;
+    }
+
+    let info = match data {
+        Some(data) => crate::media::probe(content_type, &crate::media::BytesSource(data)).await,
+        None => {
+            let source = crate::media::StorageSource {
+                storage: state.storage.as_ref(),
+                key: file_key,
+                size: file_size as u64,
+            };
+            crate::media::probe(content_type, &source).await
+        }
+    };
+
+    if let Some(info) = info {
+        let _ = sqlx::query!(
+            "UPDATE file_uploads SET width = $1, height = $2, duration_secs = $3 WHERE id = $4",
+            info.width.map(|w| w as i32),
+            info.height.map(|h| h as i32),
+            info.duration_secs,
+            file_uuid
+        )
+        .execute(&state.db)
+        .await;
+    }
+}
+
+// Record an upload that arrived in storage directly or in chunks. Images still go through
+// the image pipeline, replacing the object the client uploaded.
+async fn finish_streamed_upload(
+    state: &AppState,
+    user_id: &str,
+    file_type: &str,
+    file_key: &str,
+    file_size: usize,
+    content_type: &str,
+    original_filename: &str,
+) -> Result<String, StatusCode> {
+    // The slot or session it came in through is gone by now, so nothing else would clean
+    // up the stored object if recording it fails
+    if crate::images::profile_for(file_type).is_none() {
+        // Identical content already stored is shared instead of kept twice
+        let file_key = match crate::handlers::file_store::adopt_object(state, file_key, file_size as u64).await {
+            Ok(file_key) => file_key,
+            Err(e) => {
+                eprintln!("❌ Failed to take over uploaded file {}: {}", file_key, e);
+                let _ = state.storage.delete_file(file_key).await;
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+        let file_key = file_key.as_str();
+
+        // Record what the file really is rather than what the client declared
+        let content_type = match sniff_stored_file(state, file_key, file_size).await {
+            Some(head) => crate::content_type::detect_content_type(&head, original_filename),
+            None => content_type,
+        };
+        // Its space was reserved by the slot or session it came in through
+        let file_id = match track_upload(state, user_id, file_type, file_key, "", file_size, content_type, original_filename, None).await {
+            Ok(file_id) => file_id,
+            Err(status) => {
+                crate::handlers::file_store::release_object(state, file_key).await;
+                return Err(status);
+            }
+        };
+        if file_type == "attachment" {
+            describe_attachment(state, &file_id, file_key, content_type, None, file_size).await;
+        }
+        return Ok(file_id);
+    }
+
+    let data = state.storage.get_file(file_key).await;
+    let _ = state.storage.delete_file(file_key).await;
+    let data = data.map_err(|e| {
+        eprintln!("❌ Failed to read uploaded image {}: {}", file_key, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    store_image_upload(state, user_id, file_type, &data, original_filename).await
+}
+
+// The first bytes of a stored object, enough to detect its type
+async fn sniff_stored_file(state: &AppState, file_key: &str, file_size: usize) -> Option<Vec<u8>> {
+    use futures::StreamExt;
+
+    let length = file_size.min(crate::content_type::SNIFF_BYTES) as u64;
+    if length == 0 {
+        return None;
+    }
+
+    let range = crate::storage::ByteRange { start: 0, end: length - 1 };
+    let mut stream = state.storage.get_file_stream(file_key, Some(range)).await.ok()?;
+
+    let mut head = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        head.extend_from_slice(&chunk.ok()?);
+    }
+    Some(head)
+}
+
+// Where uploads of each type live in storage, and how large they may be when uploaded
+// directly or in chunks. Attachments are further limited by the user's plan.
+fn direct_upload_limits(file_type: &str) -> Option<(&'static str, u64)> {
+    match file_type {
+        "avatar" => Some(("avatars", 5 * 1024 * 1024)),
+        "banner" => Some(("banners", 10 * 1024 * 1024)),
+        "icon" => Some(("icons", 5 * 1024 * 1024)),
+        "emoji" => Some(("emoji", 256 * 1024)),
+        "attachment" => Some(("attachments", PREMIUM_STORAGE_LIMITS.max_direct_attachment_bytes)),
+        _ => None,
+    }
+}
+
+fn is_valid_content_type(file_type: &str, content_type: &str) -> bool {
+    if file_type != "attachment" {
+        return IMAGE_CONTENT_TYPES.contains(&content_type);
+    }
+
+    let Some((kind, subtype)) = content_type.split_once('/') else {
+        return false;
+    };
+    let is_token = |part: &str| {
+        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&^_.+-".contains(c))
+    };
+    content_type.len() <= 100 && is_token(kind) && is_token(subtype)
+}
+
+struct StreamedUpload {
+    key: String,
+    content_type: String,
+    original_filename: String,
+}
+
+// Check a declared upload against the limits for its type and pick its storage key
+fn validate_streamed_upload(
+    file_type: &str,
+    filename: &str,
+    content_type: &str,
+    size: u64,
+    limits: &StorageLimits,
+) -> Result<StreamedUpload, StatusCode> {
+    let (prefix, max_size) = direct_upload_limits(file_type).ok_or(StatusCode::BAD_REQUEST)?;
+    let max_size = if file_type == "attachment" { max_size.min(limits.max_direct_attachment_bytes) } else { max_size };
+    if size == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if size > max_size {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let content_type = content_type.trim().to_lowercase();
+    if !is_valid_content_type(file_type, &content_type) {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let filename = if filename.trim().is_empty() { "file" } else { filename.trim() };
+
+    Ok(StreamedUpload {
+        key: format!("{}/{}-{}", prefix, uuid::Uuid::new_v4(), sanitize_filename(filename)),
+        content_type,
+        original_filename: filename.chars().take(MAX_ORIGINAL_FILENAME_LENGTH).collect(),
+    })
+}
+
+// Request a presigned slot to upload a file straight to the bucket
+pub async fn create_upload_slot(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<CreateUploadSlotRequest>,
+) -> Result<Json<UploadSlotResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let limits = storage_limits(&state, &user_uuid).await?;
+    let upload = validate_streamed_upload(&payload.file_type, &payload.filename, &payload.content_type, payload.size, limits)?;
+
+    let pending = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM upload_slots WHERE user_id = $1 AND expires_at > NOW()",
+        user_uuid
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(0);
+
+    if pending >= MAX_PENDING_UPLOAD_SLOTS {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    check_storage_quota(&state.db, &user_uuid, limits, payload.size).await?;
+
+    let slot_id = uuid::Uuid::new_v4();
+
+    let presigned = state.storage
+        .presign_upload(&upload.key, &upload.content_type, payload.size, std::time::Duration::from_secs(UPLOAD_SLOT_URL_TTL_SECS))
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to presign upload: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        // This storage backend isn't reachable by clients; use the multipart endpoints
+        .ok_or(StatusCode::NOT_IMPLEMENTED)?;
+
+    let url_expires_at = chrono::Utc::now() + chrono::Duration::seconds(UPLOAD_SLOT_URL_TTL_SECS as i64);
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reserve_storage_quota(&mut tx, &user_uuid, limits, payload.size).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO upload_slots (id, user_id, file_type, file_key, content_type, file_size, original_filename, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        slot_id,
+        user_uuid,
+        payload.file_type,
+        upload.key,
+        upload.content_type,
+        payload.size as i64,
+        upload.original_filename,
+        url_expires_at + chrono::Duration::seconds(UPLOAD_SLOT_CONFIRM_GRACE_SECS)
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(UploadSlotResponse {
+        slot_id: slot_id.to_string(),
+        method: presigned.method,
+        upload_url: presigned.url,
+        headers: presigned.headers.into_iter().collect(),
+        expires_at: url_expires_at.to_rfc3339(),
+    }))
+}
+
+// Confirm a direct upload once the client has finished putting it in the bucket. The
+// object has to match the size and type the slot was issued for.
+pub async fn complete_upload_slot(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(slot_id): Path<uuid::Uuid>,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let slot = sqlx::query!(
+        r#"
+        SELECT file_type, file_key, content_type, file_size, original_filename
+        FROM upload_slots
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        slot_id,
+        user_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let meta = match state.storage.head_file(&slot.file_key).await {
+        Ok(meta) => meta,
+        // Not uploaded yet; the slot stays open for another try
+        Err(crate::storage::StorageError::NotFound) => return Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("❌ Failed to check direct upload {}: {}", slot.file_key, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Claim the slot so a concurrent confirmation can't record the file twice
+    let claimed = sqlx::query!("DELETE FROM upload_slots WHERE id = $1", slot_id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if claimed.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let type_matches = meta
+        .content_type
+        .as_deref()
+        .map(|ct| ct.eq_ignore_ascii_case(&slot.content_type))
+        .unwrap_or(true);
+
+    if meta.size != slot.file_size as u64 || !type_matches {
+        eprintln!("🚫 Direct upload {} doesn't match its slot", slot.file_key);
+        let _ = state.storage.delete_file(&slot.file_key).await;
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let file_id = finish_streamed_upload(
+        &state,
+        &user_id,
+        &slot.file_type,
+        &slot.file_key,
+        meta.size as usize,
+        &slot.content_type,
+        &slot.original_filename,
+    )
+    .await?;
+
+    let url = format!("/api/files/{}", file_id);
+    Ok(Json(UploadResponse { url, file_id }))
+}
+
+// Start a resumable upload. Chunks are then sent with PATCH, each starting at the offset
+// the session is at.
+pub async fn create_upload_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<CreateUploadSessionRequest>,
+) -> Result<Json<UploadSessionResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let limits = storage_limits(&state, &user_uuid).await?;
+    let upload = validate_streamed_upload(&payload.file_type, &payload.filename, &payload.content_type, payload.size, limits)?;
+
+    let active = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM upload_sessions WHERE user_id = $1 AND expires_at > NOW()",
+        user_uuid
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(0);
+
+    if active >= MAX_ACTIVE_UPLOAD_SESSIONS {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reserve_storage_quota(&mut tx, &user_uuid, limits, payload.size).await?;
+
+    let storage_upload_id = state.storage
+        .create_multipart_upload(&upload.key, &upload.content_type)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to start multipart upload: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let session = sqlx::query!(
+        r#"
+        INSERT INTO upload_sessions
+            (id, user_id, file_type, file_key, content_type, file_size, original_filename, chunk_size, storage_upload_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + make_interval(hours => $10))
+        RETURNING id, upload_offset, file_size, chunk_size, expires_at
+        "#,
+        uuid::Uuid::new_v4(),
+        user_uuid,
+        payload.file_type,
+        upload.key,
+        upload.content_type,
+        payload.size as i64,
+        upload.original_filename,
+        UPLOAD_CHUNK_BYTES as i32,
+        storage_upload_id,
+        UPLOAD_SESSION_TTL_HOURS as i32
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(UploadSessionResponse {
+        session_id: session.id.to_string(),
+        offset: session.upload_offset,
+        size: session.file_size,
+        chunk_size: session.chunk_size,
+        expires_at: session.expires_at.to_rfc3339(),
+    }))
+}
+
+// Where a session is at, so an interrupted client knows which chunk to send next
+pub async fn get_upload_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(session_id): Path<uuid::Uuid>,
+) -> Result<Json<UploadSessionResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = sqlx::query!(
+        r#"
+        SELECT id, upload_offset, file_size, chunk_size, expires_at
+        FROM upload_sessions
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        session_id,
+        user_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(UploadSessionResponse {
+        session_id: session.id.to_string(),
+        offset: session.upload_offset,
+        size: session.file_size,
+        chunk_size: session.chunk_size,
+        expires_at: session.expires_at.to_rfc3339(),
+    }))
+}
+
+// Append a chunk. The Upload-Offset header must match the session's offset; on a
+// mismatch the current state is returned with 409 so the client can pick up from there.
+pub async fn upload_chunk(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(session_id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let offset: i64 = headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let session = sqlx::query!(
+        r#"
+        SELECT id, file_key, storage_upload_id, upload_offset, file_size, chunk_size, expires_at
+        FROM upload_sessions
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        session_id,
+        user_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let current = UploadSessionResponse {
+        session_id: session.id.to_string(),
+        offset: session.upload_offset,
+        size: session.file_size,
+        chunk_size: session.chunk_size,
+        expires_at: session.expires_at.to_rfc3339(),
+    };
+
+    let remaining = session.file_size - session.upload_offset;
+    if offset != session.upload_offset || remaining == 0 {
+        return Ok((StatusCode::CONFLICT, Json(current)).into_response());
+    }
+
+    let expected_len = remaining.min(session.chunk_size as i64);
+    if body.len() as i64 != expected_len {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let part_number = (offset / session.chunk_size as i64) as i32 + 1;
+    let etag = state.storage
+        .upload_part(&session.file_key, &session.storage_upload_id, part_number, body)
+        .await
+        .map_err(|e| match e {
+            crate::storage::StorageError::NotFound => StatusCode::NOT_FOUND,
+            e => {
+                eprintln!("❌ Failed to store chunk {} of upload {}: {}", part_number, session_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    // Only advance if no other request got there first
+    let updated = sqlx::query!(
+        r#"
+        UPDATE upload_sessions
+        SET upload_offset = upload_offset + $1,
+            part_etags = array_append(part_etags, $2),
+            expires_at = NOW() + make_interval(hours => $3)
+        WHERE id = $4 AND upload_offset = $5
+        RETURNING upload_offset, expires_at
+        "#,
+        expected_len,
+        etag,
+        UPLOAD_SESSION_TTL_HOURS as i32,
+        session_id,
+        offset
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let Some(updated) = updated else {
+        return Ok((StatusCode::CONFLICT, Json(current)).into_response());
+    };
+
+    Ok(Json(UploadSessionResponse {
+        offset: updated.upload_offset,
+        expires_at: updated.expires_at.to_rfc3339(),
+        ..current
+    })
+    .into_response())
+}
+
+// Assemble the chunks into the final file once everything has been received
+pub async fn complete_upload_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(session_id): Path<uuid::Uuid>,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = sqlx::query!(
+        r#"
+        SELECT file_type, file_key, content_type, file_size, original_filename, storage_upload_id, upload_offset, part_etags
+        FROM upload_sessions
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        session_id,
+        user_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if session.upload_offset != session.file_size {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let parts: Vec<crate::storage::CompletedPart> = session
+        .part_etags
+        .into_iter()
+        .enumerate()
+        .map(|(i, etag)| crate::storage::CompletedPart { part_number: i as i32 + 1, etag })
+        .collect();
+
+    // If this fails the session stays, and completing can be retried
+    state.storage
+        .complete_multipart_upload(&session.file_key, &session.storage_upload_id, &parts)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to complete upload {}: {}", session_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let claimed = sqlx::query!("DELETE FROM upload_sessions WHERE id = $1", session_id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if claimed.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let file_id = finish_streamed_upload(
+        &state,
+        &user_id,
+        &session.file_type,
+        &session.file_key,
+        session.file_size as usize,
+        &session.content_type,
+        &session.original_filename,
+    )
+    .await?;
+
+    let url = format!("/api/files/{}", file_id);
+    Ok(Json(UploadResponse { url, file_id }))
+}
+
+// Give up on a resumable upload and discard the chunks received so far
+pub async fn cancel_upload_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(session_id): Path<uuid::Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = sqlx::query!(
+        "DELETE FROM upload_sessions WHERE id = $1 AND user_id = $2 RETURNING file_key, storage_upload_id",
+        session_id,
+        user_uuid
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let _ = state.storage.abort_multipart_upload(&session.file_key, &session.storage_upload_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Drop expired upload slots and anything uploaded to them without being confirmed, and
+// abandoned resumable uploads with their chunks
+pub fn spawn_upload_sweeper(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(UPLOAD_SWEEP_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match sqlx::query!("DELETE FROM upload_slots WHERE expires_at <= NOW() RETURNING file_key")
+                .fetch_all(&state.db)
+                .await
+            {
+                Ok(expired) => {
+                    for slot in expired {
+                        let _ = state.storage.delete_file(&slot.file_key).await;
+                    }
+                }
+                Err(e) => eprintln!("Upload slot sweep failed: {:?}", e),
+            }
+
+            match sqlx::query!("DELETE FROM upload_sessions WHERE expires_at <= NOW() RETURNING file_key, storage_upload_id")
+                .fetch_all(&state.db)
+                .await
+            {
+                Ok(expired) => {
+                    for session in expired {
+                        let _ = state.storage.abort_multipart_upload(&session.file_key, &session.storage_upload_id).await;
+                    }
+                }
+                Err(e) => eprintln!("Upload session sweep failed: {:?}", e),
+            }
+        }
+    });
 }
 
 fn sanitize_filename(filename: &str) -> String {
@@ -254,3 +1172,12 @@ fn sanitize_filename(filename: &str) -> String {
         .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
         .collect()
 }
+
+// Give a filename the extension of what it was converted to
+pub(crate) fn replace_extension(filename: &str, extension: &str) -> String {
+    let stem = match filename.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => filename,
+    };
+    format!("{}.{}", stem, extension)
+}
@@ -3,14 +3,35 @@ use axum::{
     response::Response,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
+use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 use std::time::{Duration, Instant};
+use uuid::Uuid;
 
 use crate::AppState;
 
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    exp: usize,
+}
+
+fn user_id_from_token(token: &str) -> Option<Uuid> {
+    let secret = crate::jwt_secret();
+
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .ok()?;
+
+    Uuid::parse_str(&token_data.claims.sub).ok()
+}
+
 // Connection tracking
 #[derive(Clone)]
 pub struct ConnectionInfo {
@@ -70,7 +91,16 @@ impl WsState {
             println!("❌ Channel not found: {}", channel_name);
         }
     }
-    
+
+    // Whether the user has at least one open gateway connection
+    pub async fn is_user_connected(&self, user_id: &str) -> bool {
+        let channel_name = format!("user-{}", user_id);
+        let channels = self.channels.read().await;
+        channels
+            .get(&channel_name)
+            .is_some_and(|sender| sender.receiver_count() > 0)
+    }
+
     // Track user connection
     pub async fn track_connection(&self, user: &str) -> Result<(), String> {
         let mut connections = self.user_connections.write().await;
@@ -299,15 +329,32 @@ async fn handle_socket(socket: WebSocket, state: AppState, channel: String, user
     let ws_state_clone = ws_state.clone();
     let _username_clone = username.clone();
     let channel_clone = channel.clone();
+    let state_clone = state.clone();
     let mut recv_task = tokio::spawn(async move {
+        // Set once by an `identify` frame; ops acting as a user require it
+        let mut identity: Option<Uuid> = None;
+
         while let Some(Ok(Message::Text(text))) = receiver.next().await {
-            println!("📨 Received message on channel {}: {}", channel_clone, text);
-            
             if let Ok(ws_msg) = serde_json::from_str::<serde_json::Value>(&text) {
                 // Check message type
                 if let Some(msg_type) = ws_msg.get("type").and_then(|t| t.as_str()) {
-                    println!("🔊 Message type: {}", msg_type);
-                    
+                    // Control ops are handled before anything is logged and never broadcast
+                    if msg_type == "identify" {
+                        identity = ws_msg.get("token").and_then(|t| t.as_str()).and_then(user_id_from_token);
+                        println!("🔑 Socket on channel {} identified: {}", channel_clone, identity.is_some());
+                        continue;
+                    }
+
+                    // Lazy member list subscriptions
+                    if msg_type == "member_list_subscribe" {
+                        if let Some(user_id) = identity {
+                            crate::handlers::member_list::handle_subscribe(&state_clone, user_id, &ws_msg).await;
+                        }
+                        continue;
+                    }
+
+                    println!("📨 Received {} on channel {}", msg_type, channel_clone);
+
                     // Handle WebRTC signaling for DM calls
                     if msg_type == "webrtc_signal" {
                         if let Some(to_user_id) = ws_msg.get("to_user_id").and_then(|v| v.as_str()) {
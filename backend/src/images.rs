@@ -0,0 +1,319 @@
+// Decoding, cleaning up and resizing uploaded images. Everything is re-encoded from the
+// decoded pixels, so EXIF (including GPS positions) and any other metadata never make it
+// into storage. EXIF orientation is applied to the pixels first so photos stay upright.
+// Image attachments are kept as uploaded and only get thumbnails.
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, DynamicImage, Frame, ImageDecoder, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+// Anything larger is rejected before decoding
+const MAX_IMAGE_DIMENSION: u32 = 8192;
+const MAX_DECODE_ALLOC_BYTES: u64 = 256 * 1024 * 1024;
+// Animations are re-encoded frame by frame, so keep them bounded
+const MAX_ANIMATION_FRAMES: usize = 500;
+// Every frame is decoded at full canvas size, so the total across frames is capped too
+const MAX_ANIMATION_DECODED_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+// 1 is the best and slowest palette quantisation, 30 the worst and fastest
+const GIF_ENCODE_SPEED: i32 = 10;
+
+#[derive(Debug)]
+pub enum ImageError {
+    // Not a PNG, JPEG, GIF or WebP image, or corrupt
+    Unsupported,
+    TooLarge,
+    Encode(String),
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Unsupported => write!(f, "unsupported or corrupt image"),
+            ImageError::TooLarge => write!(f, "image dimensions too large"),
+            ImageError::Encode(message) => write!(f, "failed to encode image: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+// How images of one upload type are shaped. `sizes` are the longest edge of each variant,
+// in ascending order; the stored image itself is capped at the last one.
+pub struct ImageProfile {
+    // Crop to a centred square, for avatars and icons
+    pub square: bool,
+    pub sizes: &'static [u32],
+}
+
+const AVATAR_PROFILE: ImageProfile = ImageProfile { square: true, sizes: &[64, 128, 256, 512] };
+const ICON_PROFILE: ImageProfile = ImageProfile { square: true, sizes: &[64, 128, 256, 512] };
+const BANNER_PROFILE: ImageProfile = ImageProfile { square: false, sizes: &[480, 960, 1920] };
+const EMOJI_PROFILE: ImageProfile = ImageProfile { square: false, sizes: &[32, 64, 128] };
+const ATTACHMENT_THUMBNAIL_PROFILE: ImageProfile = ImageProfile { square: false, sizes: &[256, 512] };
+// Blurhash only needs a rough idea of the colours, so it's computed from a tiny copy
+const PLACEHOLDER_SOURCE_SIZE: u32 = 32;
+
+// Upload types that go through the image pipeline
+pub fn profile_for(file_type: &str) -> Option<&'static ImageProfile> {
+    match file_type {
+        "avatar" => Some(&AVATAR_PROFILE),
+        "icon" => Some(&ICON_PROFILE),
+        "banner" => Some(&BANNER_PROFILE),
+        "emoji" => Some(&EMOJI_PROFILE),
+        _ => None,
+    }
+}
+
+pub struct EncodedImage {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct ProcessedImage {
+    // The image as it is stored and served by default; still animated for animated GIFs
+    pub full: EncodedImage,
+    // Smaller copies keyed by longest edge. For animations these are stills of the first
+    // frame, including one at full size.
+    pub variants: Vec<(u32, EncodedImage)>,
+}
+
+pub struct ImagePreview {
+    pub width: u32,
+    pub height: u32,
+    pub placeholder: Option<String>,
+    // Keyed by longest edge, like variants; none if the image is already small
+    pub thumbnails: Vec<(u32, EncodedImage)>,
+}
+
+// Decode an upload, normalise it to the profile and produce its variants. CPU heavy, so
+// call it from a blocking task.
+pub fn process_image(profile: &ImageProfile, data: &[u8]) -> Result<ProcessedImage, ImageError> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|_| ImageError::Unsupported)?;
+    reader.limits(decode_limits());
+
+    let format = reader.format().ok_or(ImageError::Unsupported)?;
+    if !matches!(format, ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP) {
+        return Err(ImageError::Unsupported);
+    }
+
+    if format == ImageFormat::Gif {
+        if let Some(frames) = decode_gif_animation(profile, data)? {
+            return process_animation(profile, frames);
+        }
+    }
+
+    let image = decode_oriented(reader)?;
+    let image = fit_to_profile(profile, &image, largest_size(profile));
+    // Keep transparency where there may be some, everything else becomes a JPEG
+    let keep_alpha = image.color().has_alpha();
+
+    let full = encode_still(&image, keep_alpha)?;
+    let longest_edge = full.width.max(full.height);
+
+    let mut variants = Vec::new();
+    for &size in profile.sizes.iter().filter(|&&size| size < longest_edge) {
+        let resized = fit_to_profile(profile, &image, size);
+        variants.push((size, encode_still(&resized, keep_alpha)?));
+    }
+
+    Ok(ProcessedImage { full, variants })
+}
+
+// Dimensions, a placeholder and thumbnails for an image attachment. The attachment itself
+// is kept as uploaded. CPU heavy, so call it from a blocking task.
+pub fn preview_image(data: &[u8]) -> Result<ImagePreview, ImageError> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|_| ImageError::Unsupported)?;
+    reader.limits(decode_limits());
+
+    // Animations are previewed by their first frame
+    let image = decode_oriented(reader)?;
+    let keep_alpha = image.color().has_alpha();
+    let longest_edge = image.width().max(image.height());
+
+    let mut thumbnails = Vec::new();
+    for &size in ATTACHMENT_THUMBNAIL_PROFILE.sizes.iter().filter(|&&size| size < longest_edge) {
+        let resized = fit_to_profile(&ATTACHMENT_THUMBNAIL_PROFILE, &image, size);
+        thumbnails.push((size, encode_still(&resized, keep_alpha)?));
+    }
+
+    Ok(ImagePreview {
+        width: image.width(),
+        height: image.height(),
+        placeholder: placeholder(&image),
+        thumbnails,
+    })
+}
+
+// A blurhash of the image, a few dozen characters clients can paint while it loads
+fn placeholder(image: &DynamicImage) -> Option<String> {
+    let small = image.thumbnail(PLACEHOLDER_SOURCE_SIZE, PLACEHOLDER_SOURCE_SIZE).to_rgba8();
+    let (components_x, components_y) = if small.width() >= small.height() { (4, 3) } else { (3, 4) };
+    blurhash::encode(components_x, components_y, small.width(), small.height(), small.as_raw()).ok()
+}
+
+// Decode a still image, or the first frame of an animation, upright
+fn decode_oriented(reader: ImageReader<Cursor<&[u8]>>) -> Result<DynamicImage, ImageError> {
+    let mut decoder = reader.into_decoder().map_err(decode_error)?;
+    let orientation = decoder.orientation().map_err(decode_error)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC_BYTES);
+    limits
+}
+
+fn decode_error(error: image::ImageError) -> ImageError {
+    match error {
+        image::ImageError::Limits(_) => ImageError::TooLarge,
+        _ => ImageError::Unsupported,
+    }
+}
+
+fn largest_size(profile: &ImageProfile) -> u32 {
+    profile.sizes.last().copied().unwrap_or(MAX_IMAGE_DIMENSION)
+}
+
+// Crop (for square profiles) and scale down so the longest edge is at most `size`. Images
+// are never scaled up.
+fn fit_to_profile(profile: &ImageProfile, image: &DynamicImage, size: u32) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+
+    if profile.square {
+        let side = width.min(height).min(size);
+        if width == height && width == side {
+            return image.clone();
+        }
+        return image.resize_to_fill(side, side, FilterType::Lanczos3);
+    }
+
+    if width.max(height) <= size {
+        return image.clone();
+    }
+    image.resize(size, size, FilterType::Lanczos3)
+}
+
+fn encode_still(image: &DynamicImage, keep_alpha: bool) -> Result<EncodedImage, ImageError> {
+    let mut data = Vec::new();
+
+    let (content_type, extension) = if keep_alpha {
+        image
+            .to_rgba8()
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .map_err(|e| ImageError::Encode(e.to_string()))?;
+        ("image/png", "png")
+    } else {
+        JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .map_err(|e| ImageError::Encode(e.to_string()))?;
+        ("image/jpeg", "jpg")
+    };
+
+    Ok(EncodedImage {
+        data,
+        content_type,
+        extension,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+// Every frame composited onto the full canvas and fitted to the profile as it's decoded,
+// so only one full-size frame is held at a time. None for a single-frame GIF.
+fn decode_gif_animation(profile: &ImageProfile, data: &[u8]) -> Result<Option<Vec<Frame>>, ImageError> {
+    let mut decoder = GifDecoder::new(Cursor::new(data)).map_err(decode_error)?;
+    decoder.set_limits(decode_limits()).map_err(decode_error)?;
+
+    let (canvas_width, canvas_height) = decoder.dimensions();
+    let frame_bytes = (canvas_width as u64 * canvas_height as u64 * 4).max(1);
+    let max_frames = MAX_ANIMATION_FRAMES.min((MAX_ANIMATION_DECODED_BYTES / frame_bytes) as usize);
+    let size = largest_size(profile);
+
+    let mut frames = Vec::new();
+    for frame in decoder.into_frames() {
+        if frames.len() == max_frames {
+            return Err(ImageError::TooLarge);
+        }
+        let frame = frame.map_err(decode_error)?;
+        let delay = frame.delay();
+        let image = fit_to_profile(profile, &DynamicImage::ImageRgba8(frame.into_buffer()), size);
+        frames.push(Frame::from_parts(image.to_rgba8(), 0, 0, delay));
+    }
+
+    match frames.len() {
+        0 => Err(ImageError::Unsupported),
+        1 => Ok(None),
+        _ => Ok(Some(frames)),
+    }
+}
+
+// `frames` are already fitted to the profile
+fn process_animation(profile: &ImageProfile, resized: Vec<Frame>) -> Result<ProcessedImage, ImageError> {
+    let first = DynamicImage::ImageRgba8(resized[0].buffer().clone());
+    let (width, height) = (first.width(), first.height());
+
+    let mut data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut data, GIF_ENCODE_SPEED);
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| ImageError::Encode(e.to_string()))?;
+        encoder.encode_frames(resized).map_err(|e| ImageError::Encode(e.to_string()))?;
+    }
+
+    let full = EncodedImage {
+        data,
+        content_type: "image/gif",
+        extension: "gif",
+        width,
+        height,
+    };
+
+    // Stills of the first frame at each size, and at full size for clients that don't
+    // want the animation at all
+    let longest_edge = width.max(height);
+    let mut variants = Vec::new();
+    for &size in profile.sizes.iter().filter(|&&size| size < longest_edge) {
+        variants.push((size, encode_still(&fit_to_profile(profile, &first, size), true)?));
+    }
+    variants.push((longest_edge, encode_still(&first, true)?));
+
+    Ok(ProcessedImage { full, variants })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Delay, Rgba, RgbaImage};
+
+    #[test]
+    fn animations_are_fitted_frame_by_frame() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]
+                .into_iter()
+                .map(|colour| Frame::from_parts(RgbaImage::from_pixel(600, 400, colour), 0, 0, Delay::from_numer_denom_ms(100, 1)));
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        let processed = process_image(&AVATAR_PROFILE, &data).unwrap();
+        assert_eq!(processed.full.content_type, "image/gif");
+        assert_eq!((processed.full.width, processed.full.height), (400, 400));
+
+        let frames = GifDecoder::new(Cursor::new(&processed.full.data)).unwrap().into_frames().count();
+        assert_eq!(frames, 2);
+    }
+}
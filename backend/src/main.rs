@@ -5,6 +5,11 @@ mod permissions;
 mod storage;
 mod middleware;
 mod stats;
+mod push;
+mod images;
+mod content_type;
+mod media;
+mod email;
 
 use axum::{
     routing::{get, post},
@@ -31,7 +36,9 @@ pub struct AppState {
     db: sqlx::PgPool,
     ws_state: handlers::websocket::WsState,
     cache: Option<RedisCache>,
-    storage: std::sync::Arc<storage::S3Storage>,
+    storage: std::sync::Arc<dyn storage::Storage>,
+    push: std::sync::Arc<push::PushService>,
+    member_lists: handlers::member_list::MemberLists,
 }
 
 async fn health_check() -> (StatusCode, Json<serde_json::Value>) {
@@ -41,6 +48,7 @@ async fn health_check() -> (StatusCode, Json<serde_json::Value>) {
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
+    tracing_subscriber::fmt::init();
     
     let database_url = std::env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set in .env file");
@@ -81,17 +89,26 @@ async fn main() {
         }
     };
 
-    // Initialize S3 storage
-    let storage = storage::S3Storage::new().await.expect("Failed to initialize S3 storage");
-    let storage = std::sync::Arc::new(storage);
+    // Initialize object storage (S3, local disk or memory, see storage::from_env)
+    let storage = storage::from_env().await.expect("Failed to initialize storage");
 
     let state = AppState { 
         db: pool,
         ws_state: handlers::websocket::WsState::new(),
         cache,
         storage,
+        push: std::sync::Arc::new(push::PushService::from_env()),
+        member_lists: handlers::member_list::MemberLists::new(),
     };
 
+    handlers::audit_logs::spawn_retention_sweep(state.db.clone());
+    handlers::exports::fail_interrupted_jobs(&state.db).await;
+    handlers::events::spawn_event_scheduler(state.clone());
+    push::spawn_push_worker(state.db.clone(), state.push.clone());
+    handlers::member_list::spawn_member_list_dispatcher(state.clone());
+    handlers::uploads::spawn_upload_sweeper(state.clone());
+    handlers::file_store::spawn_file_gc(state.clone());
+
     let cors = if let Ok(allowed_origins) = std::env::var("ALLOWED_ORIGINS") {
         let origins: Vec<_> = allowed_origins
             .split(',')
@@ -105,6 +122,7 @@ async fn main() {
                 .allow_methods([
                     axum::http::Method::GET,
                     axum::http::Method::POST,
+                    axum::http::Method::PUT,
                     axum::http::Method::PATCH,
                     axum::http::Method::DELETE,
                     axum::http::Method::OPTIONS,
@@ -112,6 +130,7 @@ async fn main() {
                 .allow_headers([
                     axum::http::header::CONTENT_TYPE,
                     axum::http::header::AUTHORIZATION,
+                    axum::http::HeaderName::from_static(handlers::audit_logs::AUDIT_LOG_REASON_HEADER),
                 ])
                 .allow_credentials(true)
         } else {
@@ -133,6 +152,7 @@ async fn main() {
     let auth_routes = Router::new()
         .route("/api/auth/register", post(handlers::auth::register))
         .route("/api/auth/login", post(handlers::auth::login))
+        .route("/api/auth/verify-email/confirm", post(handlers::auth::confirm_email_verification))
         .with_state(state.clone());
 
     // File Uploads with authentication and rate limiting
@@ -142,25 +162,64 @@ async fn main() {
         .route("/api/upload/icon", post(handlers::uploads::upload_icon))
         .route("/api/upload/emoji", post(handlers::uploads::upload_emoji))
         .route("/api/upload/attachment", post(handlers::uploads::upload_attachment))
+        .route("/api/upload/slots", post(handlers::uploads::create_upload_slot))
+        .route("/api/upload/slots/:slot_id/complete", post(handlers::uploads::complete_upload_slot))
+        .route("/api/upload/sessions", post(handlers::uploads::create_upload_session))
+        .layer(axum::extract::DefaultBodyLimit::max(handlers::uploads::MAX_MULTIPART_UPLOAD_BYTES))
+        .layer(axum_middleware::from_fn_with_state(state.clone(), middleware::rate_limit::upload_rate_limit))
+        .layer(axum_middleware::from_fn(middleware::auth::auth_middleware))
+        .with_state(state.clone());
+
+    // Guild imports are uploads too, rate limited like the others
+    let import_routes = Router::new()
+        .route("/api/guilds/import", post(handlers::exports::import_guild))
+        .layer(axum::extract::DefaultBodyLimit::max(handlers::exports::MAX_ARCHIVE_BYTES))
         .layer(axum_middleware::from_fn_with_state(state.clone(), middleware::rate_limit::upload_rate_limit))
         .layer(axum_middleware::from_fn(middleware::auth::auth_middleware))
         .with_state(state.clone());
 
+    // Storage usage, and resumable upload chunks; the upload was already rate limited when its
+    // session was created
+    let upload_session_routes = Router::new()
+        .route("/api/upload/usage", get(handlers::uploads::get_storage_usage))
+        .route(
+            "/api/upload/sessions/:session_id",
+            get(handlers::uploads::get_upload_session)
+                .patch(handlers::uploads::upload_chunk)
+                .delete(handlers::uploads::cancel_upload_session)
+                .layer(axum::extract::DefaultBodyLimit::max(handlers::uploads::UPLOAD_CHUNK_BYTES)),
+        )
+        .route("/api/upload/sessions/:session_id/complete", post(handlers::uploads::complete_upload_session))
+        .layer(axum_middleware::from_fn(middleware::auth::auth_middleware))
+        .with_state(state.clone());
+
+    // File Serving (with optional auth for public files and signed URLs)
+    let file_routes = Router::new()
+        .route("/api/files/:file_id", get(handlers::files::serve_file))
+        .layer(axum_middleware::from_fn(middleware::auth::optional_auth_middleware))
+        .with_state(state.clone());
+
     // Protected routes with authentication (rate limiting removed for now - causing issues)
     let protected_routes = Router::new()
+        .route("/api/auth/verify-email", post(handlers::auth::request_email_verification))
         .route("/api/messages/:channel", get(handlers::messages::get_messages))
         .route("/api/messages/:channel", post(handlers::messages::send_message))
         .route("/api/messages/:channel/:message_id", axum::routing::patch(handlers::messages::edit_message))
         .route("/api/messages/:channel/:message_id", axum::routing::delete(handlers::messages::delete_message))
         .route("/api/guilds", get(handlers::guilds::get_user_guilds))
         .route("/api/guilds", post(handlers::guilds::create_guild))
-        .route("/api/guilds/public", get(handlers::guilds::get_public_guilds))
+        .route("/api/guilds/public", get(handlers::discovery::get_public_guilds))
         .route("/api/guilds/:guild_id", axum::routing::patch(handlers::guilds::update_guild))
         .route("/api/guilds/:guild_id", axum::routing::delete(handlers::guilds::delete_guild))
         .route("/api/guilds/:guild_id/settings", axum::routing::patch(handlers::guilds::update_guild_settings))
+        .route("/api/guilds/:guild_id/transfer-ownership", post(handlers::guilds::transfer_ownership))
         .route("/api/guilds/:guild_id/members", get(handlers::guilds::get_guild_members))
+        .route("/api/guilds/:guild_id/members/:user_id", axum::routing::patch(handlers::guilds::update_member))
+        .route("/api/guilds/:guild_id/prune", get(handlers::prune::preview_prune).post(handlers::prune::prune_members))
         .route("/api/guilds/:guild_id/channels", get(handlers::channels::get_guild_channels))
         .route("/api/guilds/:guild_id/channels", post(handlers::channels::create_channel))
+        .route("/api/guilds/:guild_id/channels", axum::routing::patch(handlers::channels::reorder_channels))
+        .route("/api/guilds/:guild_id/channels/:channel_id", axum::routing::patch(handlers::channels::update_channel))
         .route("/api/guilds/:guild_id/channels/:channel_id", axum::routing::delete(handlers::channels::delete_channel))
         // Channel Permissions
         .route("/api/guilds/:guild_id/channels/:channel_id/permissions", get(handlers::channels::get_channel_permissions))
@@ -185,14 +244,80 @@ async fn main() {
         .route("/api/guilds/:guild_id/roles/:role_id/members", post(handlers::roles::assign_role))
         .route("/api/guilds/:guild_id/roles/:role_id/members/:user_id", axum::routing::delete(handlers::roles::remove_role))
         .route("/api/guilds/:guild_id/members/:user_id/roles", get(handlers::roles::get_user_roles))
+        // Guild Templates
+        .route("/api/guilds/:guild_id/templates", get(handlers::templates::get_guild_templates))
+        .route("/api/guilds/:guild_id/templates", post(handlers::templates::create_template))
+        .route("/api/guilds/:guild_id/templates/:code", axum::routing::put(handlers::templates::sync_template))
+        .route("/api/guilds/:guild_id/templates/:code", axum::routing::delete(handlers::templates::delete_template))
+        .route("/api/templates/:code", get(handlers::templates::get_template))
+        .route("/api/templates/:code", post(handlers::templates::create_guild_from_template))
+        // Discovery
+        .route("/api/guilds/:guild_id/discovery", get(handlers::discovery::get_discovery_settings))
+        .route("/api/guilds/:guild_id/discovery", axum::routing::patch(handlers::discovery::update_discovery_settings))
+        .route("/api/guilds/:guild_id/preview", get(handlers::discovery::get_guild_preview))
+        .route("/api/guilds/:guild_id/preview/channels/:channel_id/messages", get(handlers::discovery::get_preview_messages))
+        // Scheduled events
+        .route("/api/guilds/:guild_id/events", get(handlers::events::get_scheduled_events))
+        .route("/api/guilds/:guild_id/events", post(handlers::events::create_scheduled_event))
+        .route("/api/guilds/:guild_id/events/:event_id", get(handlers::events::get_scheduled_event))
+        .route("/api/guilds/:guild_id/events/:event_id", axum::routing::patch(handlers::events::update_scheduled_event))
+        .route("/api/guilds/:guild_id/events/:event_id", axum::routing::delete(handlers::events::cancel_scheduled_event))
+        .route("/api/guilds/:guild_id/events/:event_id/rsvps", get(handlers::events::get_event_rsvps))
+        .route("/api/guilds/:guild_id/events/:event_id/rsvp", axum::routing::put(handlers::events::rsvp_scheduled_event))
+        .route("/api/guilds/:guild_id/events/:event_id/rsvp", axum::routing::delete(handlers::events::remove_event_rsvp))
+        // Onboarding: self-assignable roles and reaction roles
+        .route("/api/guilds/:guild_id/onboarding", get(handlers::onboarding::get_onboarding))
+        .route("/api/guilds/:guild_id/onboarding/groups", post(handlers::onboarding::create_self_role_group))
+        .route("/api/guilds/:guild_id/onboarding/groups/:group_id", axum::routing::patch(handlers::onboarding::update_self_role_group))
+        .route("/api/guilds/:guild_id/onboarding/groups/:group_id", axum::routing::delete(handlers::onboarding::delete_self_role_group))
+        .route("/api/guilds/:guild_id/onboarding/roles/:role_id", axum::routing::put(handlers::onboarding::set_self_role))
+        .route("/api/guilds/:guild_id/onboarding/roles/:role_id", axum::routing::delete(handlers::onboarding::remove_self_role))
+        .route("/api/guilds/:guild_id/self-roles/:role_id", axum::routing::put(handlers::onboarding::join_self_role))
+        .route("/api/guilds/:guild_id/self-roles/:role_id", axum::routing::delete(handlers::onboarding::leave_self_role))
+        .route("/api/guilds/:guild_id/reaction-roles", get(handlers::onboarding::get_reaction_roles))
+        .route("/api/guilds/:guild_id/reaction-roles", post(handlers::onboarding::create_reaction_role))
+        .route("/api/guilds/:guild_id/reaction-roles/:reaction_role_id", axum::routing::delete(handlers::onboarding::delete_reaction_role))
+        // Notification settings and unread mentions
+        .route("/api/notification-settings", get(handlers::notifications::get_notification_settings))
+        .route("/api/notification-settings/:scope_type/:scope_id", axum::routing::put(handlers::notifications::update_notification_settings))
+        .route("/api/notification-settings/:scope_type/:scope_id", axum::routing::delete(handlers::notifications::reset_notification_settings))
+        .route("/api/mentions", get(handlers::notifications::get_mention_counts))
+        .route("/api/channels/:channel_id/ack", post(handlers::notifications::ack_channel))
+        // Push notifications
+        .route("/api/push/vapid-public-key", get(handlers::push::get_vapid_public_key))
+        .route("/api/push/subscriptions", get(handlers::push::get_push_subscriptions))
+        .route("/api/push/subscriptions", post(handlers::push::create_push_subscription))
+        .route("/api/push/subscriptions/:subscription_id", axum::routing::delete(handlers::push::delete_push_subscription))
+        // Guild export and import
+        .route("/api/guilds/:guild_id/exports", get(handlers::exports::get_guild_exports))
+        .route("/api/guilds/:guild_id/exports", post(handlers::exports::create_guild_export))
+        .route("/api/guilds/:guild_id/exports/:export_id", get(handlers::exports::get_guild_export))
+        .route("/api/guilds/:guild_id/exports/:export_id/download", get(handlers::exports::download_guild_export))
+        .route("/api/guilds/imports/:import_id", get(handlers::exports::get_guild_import))
+        // Membership screening
+        .route("/api/guilds/:guild_id/member-verification", get(handlers::screening::get_member_screening))
+        .route("/api/guilds/:guild_id/member-verification", axum::routing::put(handlers::screening::update_member_screening))
+        .route("/api/guilds/:guild_id/member-verification/accept", post(handlers::screening::accept_member_screening))
+        // System channel, boosts and pins
+        .route("/api/guilds/:guild_id/system-channel", get(handlers::system_messages::get_system_channel_settings))
+        .route("/api/guilds/:guild_id/system-channel", axum::routing::patch(handlers::system_messages::update_system_channel_settings))
+        .route("/api/guilds/:guild_id/boosts", post(handlers::system_messages::boost_guild))
+        .route("/api/guilds/:guild_id/boosts", axum::routing::delete(handlers::system_messages::remove_guild_boost))
+        .route("/api/channels/:channel_id/pins", get(handlers::system_messages::get_pinned_messages))
+        .route("/api/channels/:channel_id/pins/:message_id", axum::routing::put(handlers::system_messages::pin_message))
+        .route("/api/channels/:channel_id/pins/:message_id", axum::routing::delete(handlers::system_messages::unpin_message))
+        // AutoMod
+        .route("/api/guilds/:guild_id/automod/rules", get(handlers::automod::get_automod_rules))
+        .route("/api/guilds/:guild_id/automod/rules", post(handlers::automod::create_automod_rule))
+        .route("/api/guilds/:guild_id/automod/rules/:rule_id", axum::routing::patch(handlers::automod::update_automod_rule))
+        .route("/api/guilds/:guild_id/automod/rules/:rule_id", axum::routing::delete(handlers::automod::delete_automod_rule))
         // Audit Logs
         .route("/api/guilds/:guild_id/audit-logs", get(handlers::audit_logs::get_guild_audit_logs))
         // Custom Emoji
         .route("/api/guilds/:guild_id/emoji", get(handlers::emoji::get_guild_emoji))
         .route("/api/guilds/:guild_id/emoji", post(handlers::emoji::create_emoji))
         .route("/api/guilds/:guild_id/emoji/:emoji_id", axum::routing::delete(handlers::emoji::delete_emoji))
-        // File Serving (with optional auth for public files)
-        .route("/api/files/:file_id", get(handlers::files::serve_file))
+        .route("/api/files/signed-urls", post(handlers::files::get_signed_urls))
         .route("/api/voice/:channel_id/join", post(handlers::voice::join_voice_channel))
         .route("/api/voice/:channel_id/leave", post(handlers::voice::leave_voice_channel))
         .route("/api/voice/:channel_id/heartbeat", post(handlers::voice::heartbeat_voice_channel))
@@ -253,6 +378,7 @@ async fn main() {
         .route("/api/admin/users/:user_id", axum::routing::delete(handlers::admin::delete_user))
         .route("/api/admin/guilds", get(handlers::admin::get_guilds))
         .route("/api/admin/guilds/:guild_id", axum::routing::delete(handlers::admin::delete_guild))
+        .route("/api/admin/guilds/:guild_id/verified", axum::routing::put(handlers::admin::set_guild_verified))
         // WebRTC signaling
         .route("/api/webrtc/signal", post(handlers::webrtc::send_signal))
         .route("/api/webrtc/call/initiate", post(handlers::webrtc::initiate_call))
@@ -276,6 +402,9 @@ async fn main() {
         .merge(health_router)
         .merge(auth_routes)
         .merge(upload_routes)
+        .merge(upload_session_routes)
+        .merge(import_routes)
+        .merge(file_routes)
         .merge(ws_routes)
         .merge(protected_routes)
         .layer(cors);
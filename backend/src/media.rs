@@ -0,0 +1,348 @@
+// Duration and dimensions of audio and video attachments, read from container headers
+// without decoding any media. Understands MP4/QuickTime/M4A, WebM/Matroska, WAV and FLAC;
+// anything else simply has no metadata.
+use async_trait::async_trait;
+
+use crate::storage::{ByteRange, Storage};
+
+// Containers keep their headers small; anything larger is not worth reading
+const MAX_HEADER_BYTES: u64 = 16 * 1024 * 1024;
+// Matroska and WAV headers sit at the start of the file
+const HEAD_BYTES: u64 = 1024 * 1024;
+// MP4 files are walked box by box, which only takes a few reads unless something is off
+const MAX_MP4_TOP_LEVEL_BOXES: usize = 64;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MediaInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_secs: Option<f64>,
+}
+
+// Random access to the file being probed, so large uploads never have to be read whole
+#[async_trait]
+pub trait MediaSource: Send + Sync {
+    fn size(&self) -> u64;
+
+    // Up to `length` bytes from `offset`, fewer at the end of the file
+    async fn read_at(&self, offset: u64, length: u64) -> Option<Vec<u8>>;
+}
+
+pub struct BytesSource<'a>(pub &'a [u8]);
+
+#[async_trait]
+impl MediaSource for BytesSource<'_> {
+    fn size(&self) -> u64 {
+        self.0.len() as u64
+    }
+
+    async fn read_at(&self, offset: u64, length: u64) -> Option<Vec<u8>> {
+        let start = usize::try_from(offset).ok()?.min(self.0.len());
+        let end = start.saturating_add(usize::try_from(length).ok()?).min(self.0.len());
+        Some(self.0[start..end].to_vec())
+    }
+}
+
+pub struct StorageSource<'a> {
+    pub storage: &'a dyn Storage,
+    pub key: &'a str,
+    pub size: u64,
+}
+
+#[async_trait]
+impl MediaSource for StorageSource<'_> {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    async fn read_at(&self, offset: u64, length: u64) -> Option<Vec<u8>> {
+        use futures::StreamExt;
+
+        let end = offset.checked_add(length)?.min(self.size);
+        if offset >= end {
+            return Some(Vec::new());
+        }
+
+        let range = ByteRange { start: offset, end: end - 1 };
+        let mut stream = self.storage.get_file_stream(self.key, Some(range)).await.ok()?;
+        let mut data = Vec::with_capacity((end - offset) as usize);
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk.ok()?);
+        }
+        Some(data)
+    }
+}
+
+// What can be learned about an audio or video file of `content_type`
+pub async fn probe(content_type: &str, source: &dyn MediaSource) -> Option<MediaInfo> {
+    match content_type {
+        "video/mp4" | "video/quicktime" | "audio/mp4" => probe_mp4(source).await,
+        "video/webm" => probe_matroska(&source.read_at(0, HEAD_BYTES).await?),
+        "audio/wav" => probe_wav(&source.read_at(0, HEAD_BYTES).await?),
+        "audio/flac" => probe_flac(&source.read_at(0, 64).await?),
+        _ => None,
+    }
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// ---- MP4 / QuickTime ----
+
+// Walk the top-level boxes to find `moov`, which may come after the media data
+async fn probe_mp4(source: &dyn MediaSource) -> Option<MediaInfo> {
+    let mut offset = 0u64;
+
+    for _ in 0..MAX_MP4_TOP_LEVEL_BOXES {
+        let header = source.read_at(offset, 16).await?;
+        let mut size = u64::from(be_u32(&header, 0)?);
+        let kind = header.get(4..8)?;
+        let mut header_len = 8;
+
+        if size == 1 {
+            size = be_u64(&header, 8)?;
+            header_len = 16;
+        } else if size == 0 {
+            size = source.size() - offset;
+        }
+        if size < header_len {
+            return None;
+        }
+
+        if kind == b"moov" {
+            if size > MAX_HEADER_BYTES {
+                return None;
+            }
+            let moov = source.read_at(offset + header_len, size - header_len).await?;
+            return Some(parse_moov(&moov));
+        }
+
+        offset = offset.checked_add(size)?;
+        if offset >= source.size() {
+            return None;
+        }
+    }
+
+    None
+}
+
+// Children of a box, as (type, body) pairs
+fn mp4_boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || {
+        let size = be_u32(data, offset)? as usize;
+        let kind = data.get(offset + 4..offset + 8)?;
+        let (header_len, size) = match size {
+            1 => (16, usize::try_from(be_u64(data, offset + 8)?).ok()?),
+            0 => (8, data.len() - offset),
+            size => (8, size),
+        };
+        let body = data.get(offset + header_len..offset.checked_add(size)?)?;
+        offset += size;
+        Some((kind, body))
+    })
+}
+
+fn parse_moov(moov: &[u8]) -> MediaInfo {
+    let mut info = MediaInfo::default();
+
+    for (kind, body) in mp4_boxes(moov) {
+        match kind {
+            b"mvhd" => info.duration_secs = parse_mvhd(body),
+            b"trak" if info.width.is_none() => {
+                if let Some((width, height)) = mp4_boxes(body).find(|(kind, _)| *kind == b"tkhd").and_then(|(_, tkhd)| parse_tkhd(tkhd)) {
+                    info.width = Some(width);
+                    info.height = Some(height);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
+
+fn parse_mvhd(body: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match body.first()? {
+        0 => (be_u32(body, 12)?, u64::from(be_u32(body, 16)?)),
+        1 => (be_u32(body, 20)?, be_u64(body, 24)?),
+        _ => return None,
+    };
+    (timescale > 0).then(|| duration as f64 / f64::from(timescale))
+}
+
+// Display size of a track; audio tracks have none
+fn parse_tkhd(body: &[u8]) -> Option<(u32, u32)> {
+    let matrix_at = match body.first()? {
+        0 => 40,
+        1 => 52,
+        _ => return None,
+    };
+    // 16.16 fixed point, after the 3x3 transformation matrix
+    let width = be_u32(body, matrix_at + 36)? >> 16;
+    let height = be_u32(body, matrix_at + 40)? >> 16;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    // Phones record portrait video as landscape with a 90° rotation in the matrix
+    let a = be_u32(body, matrix_at)?;
+    let b = be_u32(body, matrix_at + 4)?;
+    if a == 0 && b != 0 {
+        Some((height, width))
+    } else {
+        Some((width, height))
+    }
+}
+
+// ---- WebM / Matroska ----
+
+const EBML_SEGMENT: u64 = 0x18538067;
+const EBML_INFO: u64 = 0x1549A966;
+const EBML_TIMECODE_SCALE: u64 = 0x2AD7B1;
+const EBML_DURATION: u64 = 0x4489;
+const EBML_TRACKS: u64 = 0x1654AE6B;
+const EBML_TRACK_ENTRY: u64 = 0xAE;
+const EBML_VIDEO: u64 = 0xE0;
+const EBML_PIXEL_WIDTH: u64 = 0xB0;
+const EBML_PIXEL_HEIGHT: u64 = 0xBA;
+const EBML_CLUSTER: u64 = 0x1F43B675;
+
+// A variable length integer, returning (value, length). IDs keep their length marker.
+fn ebml_vint(data: &[u8], at: usize, keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.get(at)?;
+    let length = first.leading_zeros() as usize + 1;
+    if length > 8 {
+        return None;
+    }
+
+    let mut value = if keep_marker { u64::from(first) } else { u64::from(first) & (0xFF >> length) };
+    for i in 1..length {
+        value = (value << 8) | u64::from(*data.get(at + i)?);
+    }
+    Some((value, length))
+}
+
+// Children of an element, as (id, body) pairs. A body of unknown or overlong size runs to
+// the end of what was read.
+fn ebml_elements(data: &[u8]) -> impl Iterator<Item = (u64, &[u8])> {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || {
+        let (id, id_len) = ebml_vint(data, offset, true)?;
+        let (size, size_len) = ebml_vint(data, offset + id_len, false)?;
+        let start = offset + id_len + size_len;
+        let end = usize::try_from(size).ok().and_then(|size| start.checked_add(size)).unwrap_or(usize::MAX).min(data.len());
+        let body = data.get(start..end)?;
+        offset = end;
+        Some((id, body))
+    })
+}
+
+fn ebml_uint(body: &[u8]) -> Option<u64> {
+    if body.is_empty() || body.len() > 8 {
+        return None;
+    }
+    Some(body.iter().fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+}
+
+fn ebml_float(body: &[u8]) -> Option<f64> {
+    match body.len() {
+        4 => Some(f64::from(f32::from_be_bytes(body.try_into().ok()?))),
+        8 => Some(f64::from_be_bytes(body.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn probe_matroska(head: &[u8]) -> Option<MediaInfo> {
+    let (_, segment) = ebml_elements(head).find(|(id, _)| *id == EBML_SEGMENT)?;
+    let mut info = MediaInfo::default();
+
+    for (id, body) in ebml_elements(segment) {
+        match id {
+            EBML_INFO => {
+                let mut timecode_scale = 1_000_000u64;
+                let mut duration = None;
+                for (id, body) in ebml_elements(body) {
+                    match id {
+                        EBML_TIMECODE_SCALE => timecode_scale = ebml_uint(body).unwrap_or(timecode_scale),
+                        EBML_DURATION => duration = ebml_float(body),
+                        _ => {}
+                    }
+                }
+                // Duration is in timecode units, which are nanoseconds times the scale
+                info.duration_secs = duration.map(|d| d * timecode_scale as f64 / 1e9);
+            }
+            EBML_TRACKS => {
+                let video = ebml_elements(body)
+                    .filter(|(id, _)| *id == EBML_TRACK_ENTRY)
+                    .find_map(|(_, entry)| ebml_elements(entry).find(|(id, _)| *id == EBML_VIDEO));
+                if let Some((_, video)) = video {
+                    for (id, body) in ebml_elements(video) {
+                        match id {
+                            EBML_PIXEL_WIDTH => info.width = ebml_uint(body).and_then(|v| u32::try_from(v).ok()),
+                            EBML_PIXEL_HEIGHT => info.height = ebml_uint(body).and_then(|v| u32::try_from(v).ok()),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            // Media data follows; everything needed comes before it
+            EBML_CLUSTER => break,
+            _ => {}
+        }
+    }
+
+    Some(info)
+}
+
+// ---- WAV ----
+
+fn probe_wav(head: &[u8]) -> Option<MediaInfo> {
+    let mut offset = 12usize;
+    let mut byte_rate = None;
+
+    while let Some(kind) = head.get(offset..offset + 4) {
+        let size = le_u32(head, offset + 4)? as usize;
+        match kind {
+            b"fmt " => byte_rate = le_u32(head, offset + 16),
+            b"data" => {
+                let byte_rate = byte_rate.filter(|&rate| rate > 0)?;
+                return Some(MediaInfo {
+                    duration_secs: Some(size as f64 / f64::from(byte_rate)),
+                    ..MediaInfo::default()
+                });
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        offset = offset.checked_add(8 + size + size % 2)?;
+    }
+
+    None
+}
+
+// ---- FLAC ----
+
+fn probe_flac(head: &[u8]) -> Option<MediaInfo> {
+    // STREAMINFO is always the first metadata block, right after the marker and its header
+    let info = head.get(8..26)?;
+    let sample_rate = (u32::from(info[10]) << 12) | (u32::from(info[11]) << 4) | (u32::from(info[12]) >> 4);
+    let total_samples = (u64::from(info[13] & 0x0F) << 32) | u64::from(be_u32(info, 14)?);
+
+    if sample_rate == 0 || total_samples == 0 {
+        return None;
+    }
+    Some(MediaInfo {
+        duration_secs: Some(total_samples as f64 / f64::from(sample_rate)),
+        ..MediaInfo::default()
+    })
+}
@@ -39,3 +39,33 @@ pub async fn auth_middleware(
 
     Ok(next.run(request).await)
 }
+
+// Like auth_middleware, but lets requests without an Authorization header through
+// unauthenticated. A header that is present must still hold a valid token.
+pub async fn optional_auth_middleware(
+    headers: HeaderMap,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let Some(value) = headers.get("authorization") else {
+        return Ok(next.run(request).await);
+    };
+
+    let token = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let secret = crate::jwt_secret();
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&secret),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    request.extensions_mut().insert(token_data.claims.sub.clone());
+
+    Ok(next.run(request).await)
+}
@@ -1,6 +1,15 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
+// Lets PATCH bodies tell an omitted field (None) apart from an explicit null (Some(None))
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
@@ -31,6 +40,20 @@ pub struct Attachment {
     pub file_url: String,
     pub file_type: Option<String>,
     pub file_size: Option<i64>,
+    // Known for images and most audio and video, so clients can lay out messages before
+    // anything is downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    // Seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    // Blurhash to show while an image loads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +69,17 @@ pub struct Message {
     pub edited: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attachments: Option<Vec<Attachment>>,
+    // "default" for user messages; system messages (joins, pins, ...) use their own type
+    #[serde(default = "default_message_type")]
+    pub message_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_message_id: Option<Uuid>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+fn default_message_type() -> String {
+    crate::handlers::system_messages::MESSAGE_TYPE_DEFAULT.to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,6 +106,7 @@ pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    pub email_verified: bool,
     pub is_premium: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub premium_since: Option<String>,
@@ -125,6 +160,8 @@ pub struct GuildResponse {
 pub struct MemberResponse {
     pub id: Uuid,
     pub username: String,
+    pub nickname: Option<String>,
+    pub roles: Vec<Uuid>,
     pub online: bool,
     pub status: String,
 }
@@ -138,6 +175,10 @@ pub struct ChannelResponse {
     pub position: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub category_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    pub nsfw: bool,
+    pub slowmode_seconds: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,6 +188,24 @@ pub struct CreateChannelRequest {
     pub category_id: Option<Uuid>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UpdateChannelRequest {
+    pub name: Option<String>,
+    pub topic: Option<String>,
+    pub nsfw: Option<bool>,
+    pub slowmode_seconds: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub category_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelPositionUpdate {
+    pub id: Uuid,
+    pub position: i32,
+    #[serde(default, deserialize_with = "double_option")]
+    pub category_id: Option<Option<Uuid>>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CategoryResponse {
     pub id: Uuid,
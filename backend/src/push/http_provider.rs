@@ -0,0 +1,39 @@
+// Generic HTTP push provider used by wryft-mobile: notifications are POSTed as JSON to a
+// relay that knows how to reach the device (APNs, FCM, ...)
+use super::PushError;
+
+pub struct HttpProvider {
+    url: String,
+    token: Option<String>,
+}
+
+impl HttpProvider {
+    pub fn new(url: String, token: Option<String>) -> Self {
+        Self { url, token }
+    }
+
+    pub async fn send(
+        &self,
+        client: &reqwest::Client,
+        device_token: &str,
+        platform: Option<&str>,
+        payload: &serde_json::Value,
+    ) -> Result<(), PushError> {
+        let mut request = client.post(&self.url).json(&serde_json::json!({
+            "device_token": device_token,
+            "platform": platform,
+            "notification": payload,
+        }));
+
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PushError::Retry(e.to_string()))?;
+
+        super::classify_response(response.status())
+    }
+}
@@ -0,0 +1,367 @@
+// Push notifications for users without an open gateway session. Handlers queue
+// notifications with `enqueue`; a background worker batches them per user and delivers
+// them to every registered device, retrying with backoff.
+pub mod http_provider;
+pub mod webpush;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+const WORKER_INTERVAL_SECS: u64 = 2;
+const CLAIM_BATCH_SIZE: i64 = 200;
+// Claimed rows are hidden from other workers for this long in case we crash mid-delivery
+const CLAIM_LEASE_SECS: f64 = 300.0;
+const MAX_ATTEMPTS: i32 = 5;
+const RETRY_BASE_SECS: f64 = 15.0;
+// Notifications nobody could deliver within a day aren't worth sending any more
+const MAX_QUEUE_AGE_HOURS: i32 = 24;
+// Devices that keep failing are dropped
+const MAX_SUBSCRIPTION_FAILURES: i32 = 10;
+// Web push services accept about 4 KB of encrypted payload
+const MAX_PAYLOAD_BYTES: usize = 3072;
+const MAX_BATCHED_NOTIFICATIONS: usize = 5;
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug)]
+pub enum PushError {
+    // The device is gone for good; the subscription should be removed
+    Gone,
+    // Temporary failure worth retrying
+    Retry(String),
+    // The request can never succeed as is
+    Invalid(String),
+}
+
+pub(crate) fn classify_response(status: StatusCode) -> Result<(), PushError> {
+    if status.is_success() {
+        Ok(())
+    } else if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+        Err(PushError::Gone)
+    } else if status == StatusCode::BAD_REQUEST || status == StatusCode::PAYLOAD_TOO_LARGE {
+        Err(PushError::Invalid(format!("push endpoint returned {}", status)))
+    } else {
+        Err(PushError::Retry(format!("push endpoint returned {}", status)))
+    }
+}
+
+pub struct PushService {
+    client: reqwest::Client,
+    // Web push endpoints are given by clients, so they get a client that only connects to
+    // public addresses and doesn't follow redirects
+    webpush_client: reqwest::Client,
+    vapid: Option<webpush::VapidKey>,
+    vapid_subject: String,
+    http_provider: Option<http_provider::HttpProvider>,
+}
+
+impl PushService {
+    // Providers are configured through the environment; anything missing is just disabled
+    pub fn from_env() -> Self {
+        let vapid = match std::env::var("VAPID_PRIVATE_KEY") {
+            Ok(key) => match webpush::VapidKey::from_base64(&key) {
+                Ok(vapid) => {
+                    println!("✅ Web push enabled");
+                    Some(vapid)
+                }
+                Err(e) => {
+                    println!("⚠️  Invalid VAPID_PRIVATE_KEY ({}), web push disabled", e);
+                    None
+                }
+            },
+            Err(_) => {
+                println!("ℹ️  VAPID_PRIVATE_KEY not set, web push disabled");
+                None
+            }
+        };
+
+        let http_provider = std::env::var("PUSH_HTTP_PROVIDER_URL").ok().map(|url| {
+            println!("✅ HTTP push provider enabled");
+            http_provider::HttpProvider::new(url, std::env::var("PUSH_HTTP_PROVIDER_TOKEN").ok())
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+
+        let webpush_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(webpush::PublicResolver))
+            .build()
+            .expect("failed to build web push client");
+
+        Self {
+            client,
+            webpush_client,
+            vapid,
+            vapid_subject: std::env::var("VAPID_SUBJECT").unwrap_or_else(|_| "mailto:admin@wryft.chat".to_string()),
+            http_provider,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.vapid.is_some() || self.http_provider.is_some()
+    }
+
+    pub fn vapid_public_key(&self) -> Option<&str> {
+        self.vapid.as_ref().map(|v| v.public_key())
+    }
+
+    pub fn supports(&self, provider: &str) -> bool {
+        match provider {
+            "webpush" => self.vapid.is_some(),
+            "http" => self.http_provider.is_some(),
+            _ => false,
+        }
+    }
+}
+
+// Queue a notification for a user's devices
+pub async fn enqueue(db: &sqlx::PgPool, user_id: Uuid, payload: serde_json::Value) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO push_queue (user_id, payload) VALUES ($1, $2)",
+        user_id,
+        payload
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// Several notifications for the same user go out as one push
+fn batch_payload(mut notifications: Vec<serde_json::Value>) -> serde_json::Value {
+    if notifications.len() == 1 {
+        return notifications.remove(0);
+    }
+
+    let count = notifications.len();
+    let recent: Vec<_> = notifications.into_iter().rev().take(MAX_BATCHED_NOTIFICATIONS).collect();
+    let payload = serde_json::json!({
+        "type": "batch",
+        "count": count,
+        "notifications": recent,
+    });
+
+    if payload.to_string().len() > MAX_PAYLOAD_BYTES {
+        return serde_json::json!({ "type": "batch", "count": count });
+    }
+
+    payload
+}
+
+// Deliver to every device of the user. Delivered as soon as one device accepted it; an
+// error is returned only when it is worth trying again later.
+async fn deliver(db: &sqlx::PgPool, service: &PushService, user_id: Uuid, payload: &serde_json::Value) -> Result<(), String> {
+    let subscriptions = sqlx::query!(
+        "SELECT id, provider, endpoint, p256dh, auth, device_token, platform FROM push_subscriptions WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let body = payload.to_string();
+    let mut delivered = false;
+    let mut retry_error = None;
+
+    for subscription in subscriptions {
+        let result = match (subscription.provider.as_str(), &service.vapid, &service.http_provider) {
+            ("webpush", Some(vapid), _) => webpush::send(
+                &service.webpush_client,
+                vapid,
+                &service.vapid_subject,
+                subscription.endpoint.as_deref().unwrap_or_default(),
+                subscription.p256dh.as_deref().unwrap_or_default(),
+                subscription.auth.as_deref().unwrap_or_default(),
+                body.as_bytes(),
+            )
+            .await,
+            ("http", _, Some(provider)) => provider
+                .send(
+                    &service.client,
+                    subscription.device_token.as_deref().unwrap_or_default(),
+                    subscription.platform.as_deref(),
+                    payload,
+                )
+                .await,
+            // Provider not configured on this server; nothing to do for this device
+            _ => continue,
+        };
+
+        match result {
+            Ok(()) => {
+                delivered = true;
+                let _ = sqlx::query!(
+                    "UPDATE push_subscriptions SET failure_count = 0, last_used_at = NOW() WHERE id = $1",
+                    subscription.id
+                )
+                .execute(db)
+                .await;
+            }
+            Err(PushError::Gone) => {
+                let _ = sqlx::query!("DELETE FROM push_subscriptions WHERE id = $1", subscription.id)
+                    .execute(db)
+                    .await;
+            }
+            Err(PushError::Retry(message)) => {
+                eprintln!("Push to subscription {} failed: {}", subscription.id, message);
+                retry_error = Some(message);
+                let _ = sqlx::query!(
+                    "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+                    subscription.id
+                )
+                .execute(db)
+                .await;
+            }
+            Err(PushError::Invalid(message)) => {
+                eprintln!("Push to subscription {} rejected: {}", subscription.id, message);
+                let _ = sqlx::query!(
+                    "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+                    subscription.id
+                )
+                .execute(db)
+                .await;
+            }
+        }
+    }
+
+    let _ = sqlx::query!(
+        "DELETE FROM push_subscriptions WHERE user_id = $1 AND failure_count >= $2",
+        user_id,
+        MAX_SUBSCRIPTION_FAILURES
+    )
+    .execute(db)
+    .await;
+
+    match retry_error {
+        Some(error) if !delivered => Err(error),
+        _ => Ok(()),
+    }
+}
+
+async fn process_queue(db: &sqlx::PgPool, service: &PushService) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM push_queue WHERE created_at < NOW() - make_interval(hours => $1)",
+        MAX_QUEUE_AGE_HOURS
+    )
+    .execute(db)
+    .await?;
+
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE push_queue
+        SET next_attempt_at = NOW() + make_interval(secs => $2), attempts = attempts + 1
+        WHERE id IN (
+            SELECT id FROM push_queue
+            WHERE next_attempt_at <= NOW()
+            ORDER BY id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, user_id, payload, attempts
+        "#,
+        CLAIM_BATCH_SIZE,
+        CLAIM_LEASE_SECS
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut per_user: BTreeMap<Uuid, Vec<_>> = BTreeMap::new();
+    for row in claimed {
+        per_user.entry(row.user_id).or_default().push(row);
+    }
+
+    for (user_id, mut rows) in per_user {
+        rows.sort_by_key(|r| r.id);
+        let ids: Vec<i64> = rows.iter().map(|r| r.id).collect();
+        let attempts = rows.iter().map(|r| r.attempts).max().unwrap_or(1);
+        let payload = batch_payload(rows.into_iter().map(|r| r.payload).collect());
+
+        match deliver(db, service, user_id, &payload).await {
+            Ok(()) => {
+                sqlx::query!("DELETE FROM push_queue WHERE id = ANY($1)", &ids)
+                    .execute(db)
+                    .await?;
+            }
+            Err(error) if attempts >= MAX_ATTEMPTS => {
+                eprintln!("Giving up on {} push notification(s) for {}: {}", ids.len(), user_id, error);
+                sqlx::query!("DELETE FROM push_queue WHERE id = ANY($1)", &ids)
+                    .execute(db)
+                    .await?;
+            }
+            Err(error) => {
+                let backoff = RETRY_BASE_SECS * 2f64.powi(attempts - 1);
+                sqlx::query!(
+                    "UPDATE push_queue SET next_attempt_at = NOW() + make_interval(secs => $1), last_error = $2 WHERE id = ANY($3)",
+                    backoff,
+                    error,
+                    &ids
+                )
+                .execute(db)
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn spawn_push_worker(db: sqlx::PgPool, service: Arc<PushService>) {
+    if !service.is_enabled() {
+        println!("ℹ️  No push providers configured, push worker not started");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(WORKER_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = process_queue(&db, &service).await {
+                eprintln!("Push worker failed: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_notifications_go_out_unchanged() {
+        let notification = serde_json::json!({ "type": "message", "text": "hi" });
+        assert_eq!(batch_payload(vec![notification.clone()]), notification);
+    }
+
+    #[test]
+    fn batches_keep_the_newest_notifications() {
+        let notifications: Vec<_> = (0..8).map(|i| serde_json::json!({ "n": i })).collect();
+        let payload = batch_payload(notifications);
+
+        assert_eq!(payload["type"], "batch");
+        assert_eq!(payload["count"], 8);
+        let kept: Vec<i64> = payload["notifications"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["n"].as_i64().unwrap())
+            .collect();
+        assert_eq!(kept, vec![7, 6, 5, 4, 3]);
+    }
+
+    #[test]
+    fn oversized_batches_only_carry_the_count() {
+        let text = "x".repeat(MAX_PAYLOAD_BYTES);
+        let payload = batch_payload(vec![serde_json::json!({ "text": text }), serde_json::json!({ "text": "hi" })]);
+
+        assert_eq!(payload, serde_json::json!({ "type": "batch", "count": 2 }));
+    }
+}
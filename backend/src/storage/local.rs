@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::path::{Path, PathBuf};
+
+use super::{reader_stream, validate_key, ByteRange, CompletedPart, ObjectMeta, ObjectStream, Storage, StorageError};
+
+// Parts of unfinished multipart uploads, kept outside the key space
+const MULTIPART_DIR: &str = ".multipart";
+
+// Objects stored as plain files under a root directory, keyed by their relative path.
+// Content types live in the database alongside the key, so only the bytes are kept.
+pub struct LocalStorage {
+    root: PathBuf,
+    public_url: Option<String>,
+}
+
+impl LocalStorage {
+    pub async fn new(root: impl Into<PathBuf>, public_url: Option<String>) -> Result<Self, StorageError> {
+        let root = root.into();
+        tokio::fs::create_dir_all(&root)
+            .await
+            .map_err(|e| StorageError::Backend(format!("failed to create {}: {}", root.display(), e)))?;
+
+        Ok(Self {
+            root,
+            public_url: public_url.map(|url| url.trim_end_matches('/').to_string()),
+        })
+    }
+
+    pub async fn from_env() -> Result<Self, StorageError> {
+        let root = std::env::var("LOCAL_STORAGE_PATH").unwrap_or_else(|_| "./data/uploads".to_string());
+        Self::new(root, std::env::var("LOCAL_STORAGE_PUBLIC_URL").ok()).await
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    fn multipart_dir(&self, upload_id: &str) -> Result<PathBuf, StorageError> {
+        let upload_id = uuid::Uuid::parse_str(upload_id).map_err(|_| StorageError::NotFound)?;
+        Ok(self.root.join(MULTIPART_DIR).join(upload_id.to_string()))
+    }
+
+    // Write next to the target and rename, so readers never see a partial file
+    async fn write_atomically(&self, path: &Path, data: &[u8]) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+
+        let temp_path = path.with_file_name(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        if let Err(e) = tokio::fs::write(&temp_path, data).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(io_error(e));
+        }
+        if let Err(e) = tokio::fs::rename(&temp_path, path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(io_error(e));
+        }
+
+        Ok(())
+    }
+}
+
+fn io_error(e: std::io::Error) -> StorageError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        StorageError::NotFound
+    } else {
+        StorageError::Backend(e.to_string())
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn upload_file(&self, key: &str, data: Bytes, _content_type: &str) -> Result<String, StorageError> {
+        let path = self.path_for(key)?;
+        self.write_atomically(&path, &data).await?;
+        Ok(key.to_string())
+    }
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let path = self.path_for(key)?;
+        tokio::fs::read(&path).await.map_err(io_error)
+    }
+
+    async fn head_file(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        let path = self.path_for(key)?;
+        let metadata = tokio::fs::metadata(&path).await.map_err(io_error)?;
+
+        Ok(ObjectMeta {
+            size: metadata.len(),
+            last_modified: metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from),
+            content_type: None,
+        })
+    }
+
+    async fn get_file_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ObjectStream, StorageError> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let path = self.path_for(key)?;
+        let mut file = tokio::fs::File::open(&path).await.map_err(io_error)?;
+
+        match range {
+            Some(range) => {
+                file.seek(std::io::SeekFrom::Start(range.start)).await.map_err(io_error)?;
+                Ok(reader_stream(file.take(range.length())))
+            }
+            None => Ok(reader_stream(file)),
+        }
+    }
+
+    async fn delete_file(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        // Deleting a missing object is not an error, same as S3
+        match tokio::fs::remove_file(&path).await.map_err(io_error) {
+            Ok(()) | Err(StorageError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn create_multipart_upload(&self, key: &str, _content_type: &str) -> Result<String, StorageError> {
+        validate_key(key)?;
+
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        tokio::fs::create_dir_all(self.multipart_dir(&upload_id)?).await.map_err(io_error)?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, _key: &str, upload_id: &str, part_number: i32, data: Bytes) -> Result<String, StorageError> {
+        let dir = self.multipart_dir(upload_id)?;
+        if !tokio::fs::try_exists(&dir).await.map_err(io_error)? {
+            return Err(StorageError::NotFound);
+        }
+
+        self.write_atomically(&dir.join(part_number.to_string()), &data).await?;
+        Ok(format!("{}-{}", part_number, data.len()))
+    }
+
+    async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[CompletedPart]) -> Result<(), StorageError> {
+        use tokio::io::AsyncWriteExt;
+
+        let path = self.path_for(key)?;
+        let dir = self.multipart_dir(upload_id)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+
+        // Stream the parts into one file, then move it into place
+        let temp_path = path.with_file_name(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        let result = async {
+            let mut output = tokio::fs::File::create(&temp_path).await.map_err(io_error)?;
+            for part in parts {
+                let mut input = tokio::fs::File::open(dir.join(part.part_number.to_string())).await.map_err(io_error)?;
+                tokio::io::copy(&mut input, &mut output).await.map_err(io_error)?;
+            }
+            output.flush().await.map_err(io_error)?;
+            tokio::fs::rename(&temp_path, &path).await.map_err(io_error)
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return result;
+        }
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_dir_all(self.multipart_dir(upload_id)?).await.map_err(io_error) {
+            Ok(()) | Err(StorageError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_public_url(&self, key: &str) -> Option<String> {
+        self.public_url.as_ref().map(|url| format!("{}/{}", url, key))
+    }
+}
@@ -0,0 +1,214 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+
+use super::{validate_key, ByteRange, CompletedPart, ObjectMeta, ObjectStream, Storage, StorageError};
+
+struct MultipartUpload {
+    key: String,
+    content_type: String,
+    parts: BTreeMap<i32, Bytes>,
+}
+
+// Keeps objects in process memory. Meant for tests and throwaway instances.
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: RwLock<HashMap<String, (Bytes, String)>>,
+    multipart_uploads: RwLock<HashMap<String, MultipartUpload>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn upload_file(&self, key: &str, data: Bytes, content_type: &str) -> Result<String, StorageError> {
+        validate_key(key)?;
+        self.objects
+            .write()
+            .await
+            .insert(key.to_string(), (data, content_type.to_string()));
+        Ok(key.to_string())
+    }
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.objects
+            .read()
+            .await
+            .get(key)
+            .map(|(data, _)| data.to_vec())
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn head_file(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        self.objects
+            .read()
+            .await
+            .get(key)
+            .map(|(data, content_type)| ObjectMeta {
+                size: data.len() as u64,
+                last_modified: None,
+                content_type: Some(content_type.clone()),
+            })
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn get_file_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ObjectStream, StorageError> {
+        use futures::StreamExt;
+
+        let data = self.objects
+            .read()
+            .await
+            .get(key)
+            .map(|(data, _)| data.clone())
+            .ok_or(StorageError::NotFound)?;
+
+        let data = match range {
+            Some(range) => data.slice(range.start as usize..=range.end as usize),
+            None => data,
+        };
+
+        Ok(futures::stream::once(async move { Ok(data) }).boxed())
+    }
+
+    async fn delete_file(&self, key: &str) -> Result<(), StorageError> {
+        self.objects.write().await.remove(key);
+        Ok(())
+    }
+
+    async fn create_multipart_upload(&self, key: &str, content_type: &str) -> Result<String, StorageError> {
+        validate_key(key)?;
+
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        self.multipart_uploads.write().await.insert(
+            upload_id.clone(),
+            MultipartUpload {
+                key: key.to_string(),
+                content_type: content_type.to_string(),
+                parts: BTreeMap::new(),
+            },
+        );
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Bytes) -> Result<String, StorageError> {
+        let mut uploads = self.multipart_uploads.write().await;
+        let upload = uploads
+            .get_mut(upload_id)
+            .filter(|upload| upload.key == key)
+            .ok_or(StorageError::NotFound)?;
+
+        let etag = format!("{}-{}", part_number, data.len());
+        upload.parts.insert(part_number, data);
+        Ok(etag)
+    }
+
+    async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[CompletedPart]) -> Result<(), StorageError> {
+        let mut uploads = self.multipart_uploads.write().await;
+        let upload = uploads
+            .get(upload_id)
+            .filter(|upload| upload.key == key)
+            .ok_or(StorageError::NotFound)?;
+
+        let mut data = Vec::new();
+        for part in parts {
+            let part_data = upload.parts.get(&part.part_number).ok_or(StorageError::NotFound)?;
+            data.extend_from_slice(part_data);
+        }
+
+        let content_type = upload.content_type.clone();
+        uploads.remove(upload_id);
+        self.objects
+            .write()
+            .await
+            .insert(key.to_string(), (Bytes::from(data), content_type));
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<(), StorageError> {
+        self.multipart_uploads.write().await.remove(upload_id);
+        Ok(())
+    }
+
+    fn get_public_url(&self, _key: &str) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    async fn read_stream(storage: &MemoryStorage, key: &str, range: Option<ByteRange>) -> Vec<u8> {
+        let mut stream = storage.get_file_stream(key, range).await.unwrap();
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn round_trips_objects() {
+        let storage = MemoryStorage::new();
+        let key = storage.upload_file("uploads/a.txt", Bytes::from_static(b"hello world"), "text/plain").await.unwrap();
+        assert_eq!(key, "uploads/a.txt");
+
+        assert_eq!(storage.get_file(&key).await.unwrap(), b"hello world");
+        assert_eq!(read_stream(&storage, &key, None).await, b"hello world");
+        assert_eq!(read_stream(&storage, &key, Some(ByteRange { start: 6, end: 10 })).await, b"world");
+
+        let meta = storage.head_file(&key).await.unwrap();
+        assert_eq!(meta.size, 11);
+        assert_eq!(meta.content_type.as_deref(), Some("text/plain"));
+
+        storage.delete_file(&key).await.unwrap();
+        assert!(matches!(storage.get_file(&key).await, Err(StorageError::NotFound)));
+        assert!(matches!(storage.head_file(&key).await, Err(StorageError::NotFound)));
+        assert!(matches!(
+            storage.upload_file("../a.txt", Bytes::new(), "text/plain").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn assembles_multipart_uploads_in_part_order() {
+        let storage = MemoryStorage::new();
+        let key = "uploads/big.bin";
+        let upload_id = storage.create_multipart_upload(key, "application/octet-stream").await.unwrap();
+
+        let second = storage.upload_part(key, &upload_id, 2, Bytes::from_static(b"world")).await.unwrap();
+        let first = storage.upload_part(key, &upload_id, 1, Bytes::from_static(b"hello ")).await.unwrap();
+        assert!(matches!(
+            storage.upload_part("uploads/other.bin", &upload_id, 3, Bytes::new()).await,
+            Err(StorageError::NotFound)
+        ));
+
+        // Nothing is visible until the upload completes
+        assert!(matches!(storage.head_file(key).await, Err(StorageError::NotFound)));
+
+        let parts = [
+            CompletedPart { part_number: 1, etag: first },
+            CompletedPart { part_number: 2, etag: second },
+        ];
+        storage.complete_multipart_upload(key, &upload_id, &parts).await.unwrap();
+        assert_eq!(storage.get_file(key).await.unwrap(), b"hello world");
+
+        // Completed and aborted uploads are gone
+        assert!(matches!(
+            storage.complete_multipart_upload(key, &upload_id, &parts).await,
+            Err(StorageError::NotFound)
+        ));
+        let aborted = storage.create_multipart_upload(key, "application/octet-stream").await.unwrap();
+        storage.abort_multipart_upload(key, &aborted).await.unwrap();
+        assert!(matches!(
+            storage.upload_part(key, &aborted, 1, Bytes::new()).await,
+            Err(StorageError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+// Object storage for uploads and exports. The backend is picked at startup from
+// STORAGE_BACKEND: "s3" (S3 or MinIO), "local" (a directory on disk) or "memory" (for tests).
+// Without STORAGE_BACKEND, S3 is used when S3_BUCKET is set and local storage otherwise.
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use std::sync::Arc;
+
+pub use local::LocalStorage;
+pub use memory::MemoryStorage;
+pub use s3::S3Storage;
+
+#[derive(Debug)]
+pub enum StorageError {
+    NotFound,
+    // Keys must be relative paths without `.` or `..` segments
+    InvalidKey(String),
+    Backend(String),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "object not found"),
+            StorageError::InvalidKey(key) => write!(f, "invalid storage key: {}", key),
+            StorageError::Backend(message) => write!(f, "storage backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+#[derive(Debug, Clone)]
+pub struct ObjectMeta {
+    pub size: u64,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+    // Only known to backends that keep it alongside the object
+    pub content_type: Option<String>,
+}
+
+// A request the client can make itself to put an object straight into the backend
+#[derive(Debug, Clone)]
+pub struct PresignedUpload {
+    pub method: String,
+    pub url: String,
+    // Headers that are part of the signature and must be sent as is
+    pub headers: Vec<(String, String)>,
+}
+
+// Inclusive byte range, already checked against the object size
+#[derive(Debug, Clone, Copy)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+pub type ObjectStream = BoxStream<'static, Result<Bytes, StorageError>>;
+
+// Every part of a multipart upload except the last has to be at least this large (the S3 limit)
+pub const MIN_MULTIPART_PART_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CompletedPart {
+    // Parts are numbered from 1
+    pub part_number: i32,
+    pub etag: String,
+}
+
+// Chunk size for streamed reads from disk
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // Store an object, replacing any existing one, and return its key
+    async fn upload_file(&self, key: &str, data: Bytes, content_type: &str) -> Result<String, StorageError>;
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    async fn head_file(&self, key: &str) -> Result<ObjectMeta, StorageError>;
+
+    // Stream the object, or just `range` of it, without buffering it in memory
+    async fn get_file_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ObjectStream, StorageError>;
+
+    async fn delete_file(&self, key: &str) -> Result<(), StorageError>;
+
+    // Multipart uploads assemble an object from parts sent separately. The object only
+    // appears under `key` once the upload is completed.
+    async fn create_multipart_upload(&self, key: &str, content_type: &str) -> Result<String, StorageError>;
+
+    // Store one part and return its ETag, needed to complete the upload
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Bytes) -> Result<String, StorageError>;
+
+    async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[CompletedPart]) -> Result<(), StorageError>;
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<(), StorageError>;
+
+    // Let a client upload `size` bytes of `content_type` to `key` directly, bypassing us.
+    // Backends that can't be reached by clients return None.
+    async fn presign_upload(
+        &self,
+        _key: &str,
+        _content_type: &str,
+        _size: u64,
+        _expires_in: std::time::Duration,
+    ) -> Result<Option<PresignedUpload>, StorageError> {
+        Ok(None)
+    }
+
+    // Where clients can fetch the object directly, if the backend is publicly reachable
+    fn get_public_url(&self, key: &str) -> Option<String>;
+}
+
+// Reject keys that could escape the storage root or collide after normalisation
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && !key.contains('\\')
+        && !key.contains('\0')
+        && key.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+// Stream an async reader in fixed-size chunks
+pub(crate) fn reader_stream<R>(reader: R) -> ObjectStream
+where
+    R: tokio::io::AsyncRead + Send + 'static,
+{
+    use futures::StreamExt;
+
+    tokio_util::io::ReaderStream::with_capacity(reader, STREAM_CHUNK_BYTES)
+        .map(|chunk| chunk.map_err(|e| StorageError::Backend(e.to_string())))
+        .boxed()
+}
+
+pub async fn from_env() -> Result<Arc<dyn Storage>, StorageError> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| {
+        if std::env::var("S3_BUCKET").is_ok() { "s3" } else { "local" }.to_string()
+    });
+
+    let storage: Arc<dyn Storage> = match backend.as_str() {
+        "s3" => {
+            let storage = S3Storage::new().await.map_err(|e| StorageError::Backend(e.to_string()))?;
+            println!("✅ Using S3 storage");
+            Arc::new(storage)
+        }
+        "local" => {
+            let storage = LocalStorage::from_env().await?;
+            println!("✅ Using local storage in {}", storage.root().display());
+            Arc::new(storage)
+        }
+        "memory" => {
+            println!("⚠️  Using in-memory storage, uploads are lost on restart");
+            Arc::new(MemoryStorage::new())
+        }
+        other => return Err(StorageError::Backend(format!("unknown STORAGE_BACKEND \"{}\"", other))),
+    };
+
+    Ok(storage)
+}
+
+// Helper function to extract key from URL
+pub fn extract_key_from_url(url: &str, public_url: &str) -> Option<String> {
+    url.strip_prefix(&format!("{}/", public_url))
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_relative_keys() {
+        assert!(validate_key("uploads/2026/abc-photo.png").is_ok());
+        assert!(validate_key("avatars/user.1.webp").is_ok());
+    }
+
+    #[test]
+    fn rejects_keys_that_escape_or_collide() {
+        for key in ["", "/etc/passwd", "uploads/../secret", "./uploads/a", "uploads//a", "uploads/a/", "a\\b", "a\0b", ".."] {
+            assert!(matches!(validate_key(key), Err(StorageError::InvalidKey(_))), "{:?} should be rejected", key);
+        }
+    }
+}
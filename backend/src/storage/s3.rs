@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use aws_sdk_s3::{Client, config::Region, config::Credentials, config::Builder, config::BehaviorVersion};
+use aws_sdk_s3::presigning::PresigningConfig;
+use bytes::Bytes;
+use std::env;
+
+use super::{reader_stream, validate_key, ByteRange, CompletedPart, ObjectMeta, ObjectStream, PresignedUpload, Storage, StorageError};
+
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+    public_url: String,
+}
+
+impl S3Storage {
+    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = env::var("S3_ENDPOINT").ok();
+        let region = env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let access_key = env::var("S3_ACCESS_KEY")?;
+        let secret_key = env::var("S3_SECRET_KEY")?;
+        let bucket = env::var("S3_BUCKET")?;
+        let public_url = env::var("S3_PUBLIC_URL").unwrap_or_else(|_| format!("http://localhost:9000/{}", bucket));
+
+        let credentials = Credentials::new(
+            access_key,
+            secret_key,
+            None,
+            None,
+            "wryft-storage",
+        );
+
+        let mut config_builder = Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(region))
+            .credentials_provider(credentials)
+            .force_path_style(true);  // Required for MinIO
+
+        // If endpoint is provided (MinIO), use it
+        if let Some(endpoint_url) = endpoint {
+            config_builder = config_builder.endpoint_url(endpoint_url);
+        }
+
+        let config = config_builder.build();
+        let client = Client::from_conf(config);
+
+        Ok(Self {
+            client,
+            bucket,
+            public_url,
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn upload_file(
+        &self,
+        key: &str,
+        data: Bytes,
+        content_type: &str,
+    ) -> Result<String, StorageError> {
+        validate_key(key)?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(data.into())
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+
+        // Return the S3 key - we'll use file_id for access
+        Ok(key.to_string())
+    }
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_no_such_key() => StorageError::NotFound,
+                _ => StorageError::Backend(e.to_string()),
+            })?;
+
+        let data = response.body.collect().await.map_err(|e| StorageError::Backend(e.to_string()))?;
+        Ok(data.to_vec())
+    }
+
+    async fn head_file(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        let response = self.client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_not_found() => StorageError::NotFound,
+                _ => StorageError::Backend(e.to_string()),
+            })?;
+
+        Ok(ObjectMeta {
+            size: response.content_length().unwrap_or(0).max(0) as u64,
+            last_modified: response
+                .last_modified()
+                .and_then(|dt| chrono::DateTime::from_timestamp(dt.secs(), 0)),
+            content_type: response.content_type().map(|ct| ct.to_string()),
+        })
+    }
+
+    async fn get_file_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ObjectStream, StorageError> {
+        let mut request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key);
+
+        if let Some(range) = range {
+            request = request.range(format!("bytes={}-{}", range.start, range.end));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_no_such_key() => StorageError::NotFound,
+                _ => StorageError::Backend(e.to_string()),
+            })?;
+
+        Ok(reader_stream(response.body.into_async_read()))
+    }
+
+    async fn delete_file(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn create_multipart_upload(&self, key: &str, content_type: &str) -> Result<String, StorageError> {
+        validate_key(key)?;
+
+        let response = self.client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+
+        response
+            .upload_id()
+            .map(|id| id.to_string())
+            .ok_or_else(|| StorageError::Backend("no upload id in response".to_string()))
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Bytes) -> Result<String, StorageError> {
+        let response = self.client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(data.into())
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.meta().code() == Some("NoSuchUpload") => StorageError::NotFound,
+                _ => StorageError::Backend(e.to_string()),
+            })?;
+
+        response
+            .e_tag()
+            .map(|etag| etag.to_string())
+            .ok_or_else(|| StorageError::Backend("no ETag in response".to_string()))
+    }
+
+    async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[CompletedPart]) -> Result<(), StorageError> {
+        let parts = parts
+            .iter()
+            .map(|part| {
+                aws_sdk_s3::types::CompletedPart::builder()
+                    .part_number(part.part_number)
+                    .e_tag(&part.etag)
+                    .build()
+            })
+            .collect();
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.meta().code() == Some("NoSuchUpload") => StorageError::NotFound,
+                _ => StorageError::Backend(e.to_string()),
+            })?;
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<(), StorageError> {
+        match self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            // Already gone is as good as aborted
+            Err(e) if e.as_service_error().map(|se| se.is_no_such_upload()).unwrap_or(false) => Ok(()),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+
+    async fn presign_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        size: u64,
+        expires_in: std::time::Duration,
+    ) -> Result<Option<PresignedUpload>, StorageError> {
+        validate_key(key)?;
+
+        let config = PresigningConfig::expires_in(expires_in).map_err(|e| StorageError::Backend(e.to_string()))?;
+
+        // Content type and length are signed, so the bucket rejects anything else
+        let request = self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(size as i64)
+            .presigned(config)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+
+        Ok(Some(PresignedUpload {
+            method: request.method().to_string(),
+            url: request.uri().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }))
+    }
+
+    fn get_public_url(&self, key: &str) -> Option<String> {
+        Some(format!("{}/{}", self.public_url, key))
+    }
+}